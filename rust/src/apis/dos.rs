@@ -1,25 +1,165 @@
 // as far as i know, DOS is always 16-bit.
 
-use crate::{byte_operation::x86_16::{self, get_dx}, byte_stream::ByteStream, executable::InteruptChange};
-
-pub fn dos_op_cd(execute: bool, bst: &mut ByteStream) -> (String, InteruptChange) {
-    let vcd = bst.read_byte();
-    let mut code = format!("int {vcd:X}h");
+use crate::{
+    byte_operation::x86_16::Cpu,
+    error::Error,
+    executable::InteruptChange,
+    memory::{Memory, DEFAULT_PSP_SEGMENT},
+};
 
+pub fn dos_op_cd(cpu: &mut Cpu, mem: &mut Memory, vcd: u8) -> Result<InteruptChange, Error> {
     match vcd {
-        0x21 => match *x86_16::AH.read().unwrap() {
+        0x21 => match cpu.ah {
             0x09 => {
-                let begin = get_dx();
-                let end = bst.find_first_byte_from(begin as usize, 0x24);
-                let string = bst.read_string_from_to(begin as usize, end);
-                code += format!("\n; printf({});", string.replace("\n", "\\n").replace("\r", "\\r")).as_str();
-                if execute {
-                    print!("{string}");
-                }
-                (code, InteruptChange::String(begin, end as u16))
+                // the string is at DS:DX and runs up to a '$', which has to come before the end of the segment
+                let rest = 0x10000 - cpu.dx() as usize;
+                let len = mem.peek_bytes(cpu.ds, cpu.dx(), rest).iter().position(|b| *b == b'$').ok_or(
+                    Error::Truncated { offset: mem.physical(cpu.ds, cpu.dx()), needed: rest + 1 },
+                )?;
+                // only what DOS reads, '$' included, is watched
+                mem.read_bytes(cpu.ds, cpu.dx(), len + 1);
+                Ok(InteruptChange::String(cpu.dx(), cpu.dx().wrapping_add(len as u16)))
             }
-            _ => panic!(),
+            0x4C => Ok(InteruptChange::Exit(cpu.al)),
+            function => Err(Error::UnsupportedInterrupt { vector: vcd, function }),
         },
-        _ => panic!(),
+        0x20 => Ok(InteruptChange::Exit(0)),
+        _ => Err(Error::UnsupportedInterrupt { vector: vcd, function: cpu.ah }),
+    }
+}
+
+/// <p>How big the environment block is made, in paragraphs.  It goes right below the PSP.</p>
+pub const ENVIRONMENT_PARAGRAPHS: u16 = 0x10;
+/// <p>The first segment past conventional memory, which DOS gives a program all of.</p>
+pub const MEMORY_TOP_SEGMENT: u16 = 0xA000;
+
+/// <p>What DOS hands a program it starts, besides the image itself.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Startup {
+    pub psp_segment: u16,
+    /// Everything after the program name on the command line, usually starting with a space.
+    pub command_tail: String,
+    /// <code>NAME=value</code> strings.
+    pub environment: Vec<String>,
+    /// The full path DOS 3 and up put after the environment, which programs use to find their own file.
+    pub program_path: String,
+}
+
+impl Startup {
+    pub fn new(program_path: &str) -> Self {
+        Self {
+            psp_segment: DEFAULT_PSP_SEGMENT,
+            command_tail: String::new(),
+            environment: vec!["PATH=C:\\".to_string()],
+            program_path: program_path.to_string(),
+        }
+    }
+
+    pub fn environment_segment(&self) -> u16 {
+        self.psp_segment.wrapping_sub(ENVIRONMENT_PARAGRAPHS)
+    }
+
+    /// <p>Sets the registers DOS starts a program with, given where its code and stack are: DS and ES at the PSP,
+    /// CX 00FF, DX the PSP, SI and DI copies of IP and SP, BP 091C and interrupts enabled, the way MS-DOS 5 leaves
+    /// them.</p>
+    pub fn start(&self, cpu: &mut Cpu, (cs, ip): (u16, u16), (ss, sp): (u16, u16)) {
+        *cpu = Cpu::new();
+        (cpu.cs, cpu.ip, cpu.ss, cpu.sp) = (cs, ip, ss, sp);
+        cpu.ds = self.psp_segment;
+        cpu.es = self.psp_segment;
+        cpu.set_cx(0x00FF);
+        cpu.set_dx(self.psp_segment);
+        cpu.si = ip;
+        cpu.di = sp;
+        cpu.bp = 0x091C;
+        cpu.r#if = true;
+    }
+
+    /// <p>Writes the environment block and the PSP for a program whose memory ends at <code>end_segment</code>.</p>
+    pub fn write(&self, mem: &mut Memory, end_segment: u16) {
+        write_environment(mem, self.environment_segment(), &self.environment, &self.program_path);
+        write_psp(mem, self.psp_segment, self.environment_segment(), end_segment, &self.command_tail);
+    }
+}
+
+/// <p>Writes an environment block: each string followed by a NUL, an empty string to end them, then a count of one
+/// and the program's path.  Whatever doesn't fit in <code>ENVIRONMENT_PARAGRAPHS</code> is cut off.</p>
+pub fn write_environment(mem: &mut Memory, segment: u16, environment: &[String], program_path: &str) {
+    let mut block = Vec::new();
+    for variable in environment {
+        block.extend_from_slice(variable.as_bytes());
+        block.push(0);
+    }
+    if environment.is_empty() {
+        block.push(0);
+    }
+    block.push(0);
+    block.extend_from_slice(&1u16.to_le_bytes());
+    block.extend_from_slice(program_path.as_bytes());
+    block.push(0);
+    block.resize(ENVIRONMENT_PARAGRAPHS as usize * 16, 0);
+    mem.write_bytes(segment, 0, &block);
+}
+
+/// <p>Writes the 256-byte program segment prefix at <code>psp</code>: the <code>int 20h</code> exit, the top of the
+/// program's memory, the saved interrupt 22h-24h vectors, a handle table with the standard devices open, the
+/// environment segment, the <code>int 21h; retf</code> call gate, two blank FCBs and the command tail.  DOS only has
+/// room for 126 characters of tail, so anything past that is cut off.</p>
+pub fn write_psp(mem: &mut Memory, psp: u16, environment: u16, end_segment: u16, command_tail: &str) {
+    let mut prefix = [0u8; 0x100];
+    prefix[0x00..0x02].copy_from_slice(&[0xCD, 0x20]);
+    prefix[0x02..0x04].copy_from_slice(&end_segment.to_le_bytes());
+    for (i, vector) in [0x22usize, 0x23, 0x24].into_iter().enumerate() {
+        let saved = mem.read_bytes(0, (vector * 4) as u16, 4);
+        prefix[0x0A + i * 4..0x0E + i * 4].copy_from_slice(&saved);
+    }
+    // started from a shell that is its own parent
+    prefix[0x16..0x18].copy_from_slice(&psp.to_le_bytes());
+    // stdin, stdout and stderr on CON, stdaux on AUX, stdprn on PRN, the rest closed
+    prefix[0x18..0x2C].fill(0xFF);
+    prefix[0x18..0x1D].copy_from_slice(&[1, 1, 1, 0, 2]);
+    prefix[0x2C..0x2E].copy_from_slice(&environment.to_le_bytes());
+    prefix[0x32..0x34].copy_from_slice(&20u16.to_le_bytes());
+    prefix[0x34..0x36].copy_from_slice(&0x18u16.to_le_bytes());
+    prefix[0x36..0x38].copy_from_slice(&psp.to_le_bytes());
+    prefix[0x50..0x53].copy_from_slice(&[0xCD, 0x21, 0xCB]);
+    for fcb in [0x5C, 0x6C] {
+        prefix[fcb + 1..fcb + 12].fill(b' ');
+    }
+    let tail = &command_tail.as_bytes()[..command_tail.len().min(126)];
+    prefix[0x80] = tail.len() as u8;
+    prefix[0x81..0x81 + tail.len()].copy_from_slice(tail);
+    prefix[0x81 + tail.len()] = b'\r';
+    mem.write_bytes(psp, 0, &prefix);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Watchpoint;
+
+    #[test]
+    fn print_string_only_reads_up_to_the_dollar() {
+        let (mut cpu, mut mem) = (Cpu::new(), Memory::new());
+        mem.write_bytes(0x1000, 0x10, b"Hi$ there$");
+        (cpu.ds, cpu.ah) = (0x1000, 0x09);
+        cpu.set_dx(0x10);
+        let start = mem.physical(0x1000, 0x10);
+        mem.watchpoints.push(Watchpoint { range: start..start + 0x10, read: true, write: false });
+
+        assert!(matches!(dos_op_cd(&mut cpu, &mut mem, 0x21).unwrap(), InteruptChange::String(0x10, 0x12)));
+        let read: Vec<usize> = mem.take_hits().iter().map(|a| a.address).collect();
+        assert_eq!(read, vec![start, start + 1, start + 2]);
+    }
+
+    #[test]
+    fn print_string_needs_a_dollar_in_the_segment() {
+        let (mut cpu, mut mem) = (Cpu::new(), Memory::new());
+        (cpu.ds, cpu.ah) = (0x1000, 0x09);
+        cpu.set_dx(0xFFF0);
+        assert!(matches!(
+            dos_op_cd(&mut cpu, &mut mem, 0x21),
+            Err(Error::Truncated { offset: 0x1FFF0, needed: 0x11 })
+        ));
     }
-}
\ No newline at end of file
+}
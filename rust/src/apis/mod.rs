@@ -1,6 +1,6 @@
 pub mod dos;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum API {
     None,
     DOS,
@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::instruction::{Instruction, Operand, Register};
+use super::trace::{ends_path, Disassembly};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Execution runs off the end of the block into the next one.
+    FallThrough,
+    Jump,
+    /// The taken side of a conditional jump, <code>loop</code> or <code>jcxz</code>.
+    Branch,
+    Call,
+    FarJump,
+    FarCall,
+}
+
+impl EdgeKind {
+    /// <p>Calls leave the function; everything else stays inside it.</p>
+    pub fn is_call(&self) -> bool {
+        matches!(self, EdgeKind::Call | EdgeKind::FarCall)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// <p>A straight run of instructions that is only ever entered at the top.  Calls don't end a block; they add an
+/// edge to the callee and execution carries on after them.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub edges: Vec<Edge>,
+}
+
+impl BasicBlock {
+    pub fn end(&self) -> usize {
+        self.instructions.last().map_or(self.start, Instruction::next_address)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FunctionSource {
+    /// An entry point handed to the builder.
+    Entry,
+    /// The target of a direct call.
+    CallTarget,
+    /// Found from a <code>push bp; mov bp,sp</code> prologue.
+    Prologue,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    pub entry: usize,
+    pub source: FunctionSource,
+    /// Starts of the blocks reachable from the entry without calling or running into another function.
+    pub blocks: Vec<usize>,
+}
+
+impl Function {
+    pub fn name(&self) -> String {
+        format!("sub_{:05X}", self.entry)
+    }
+}
+
+/// <p>Basic blocks and functions recovered from a traced disassembly.</p>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    pub functions: Vec<Function>,
+}
+
+fn is_prologue(first: &Instruction, second: Option<&Instruction>) -> bool {
+    first.mnemonic == "push"
+        && first.operands == [Operand::Register(Register::BP)]
+        && second.is_some_and(|s| {
+            s.mnemonic == "mov" && s.operands == [Operand::Register(Register::BP), Operand::Register(Register::SP)]
+        })
+}
+
+/// <p>Where a direct branch or call goes, if it can be known.  Far targets are only resolved with
+/// <code>follow_far</code>, the same way <code>trace_code</code> takes it.</p>
+fn target(ins: &Instruction, follow_far: bool) -> Option<usize> {
+    ins.branch_target().or_else(|| {
+        ins.far_target()
+            .filter(|_| follow_far)
+            .map(|(segment, offset)| segment as usize * 16 + offset as usize)
+    })
+}
+
+fn edge_kind(ins: &Instruction) -> EdgeKind {
+    let far = ins.far_target().is_some();
+    match (ins.is_call(), ins.is_unconditional_jump(), far) {
+        (true, _, true) => EdgeKind::FarCall,
+        (true, _, false) => EdgeKind::Call,
+        (_, true, true) => EdgeKind::FarJump,
+        (_, true, false) => EdgeKind::Jump,
+        _ => EdgeKind::Branch,
+    }
+}
+
+impl ControlFlowGraph {
+    /// <p>Splits the code of a traversal into basic blocks and groups them into functions.  Functions start at the
+    /// given entry points, at the targets of direct calls and at <code>push bp; mov bp,sp</code> prologues.
+    /// <code>follow_far</code> has the same meaning as for <code>trace_code</code>.</p>
+    pub fn build(disassembly: &Disassembly, entries: &[usize], follow_far: bool) -> Self {
+        let code: BTreeMap<usize, &Instruction> = disassembly.code().map(|ins| (ins.address, ins)).collect();
+
+        let mut function_starts: BTreeMap<usize, FunctionSource> = BTreeMap::new();
+        let mut leaders: BTreeSet<usize> = BTreeSet::new();
+        for &entry in entries.iter().filter(|e| code.contains_key(e)) {
+            function_starts.insert(entry, FunctionSource::Entry);
+            leaders.insert(entry);
+        }
+        let mut previous: Option<&Instruction> = None;
+        for ins in code.values() {
+            if let Some(to) = target(ins, follow_far).filter(|t| code.contains_key(t)) {
+                leaders.insert(to);
+                if ins.is_call() {
+                    function_starts.entry(to).or_insert(FunctionSource::CallTarget);
+                }
+            }
+            if ins.is_conditional_jump() || ends_path(ins, previous) {
+                leaders.insert(ins.next_address());
+            }
+            if is_prologue(ins, code.get(&ins.next_address()).copied()) {
+                function_starts.entry(ins.address).or_insert(FunctionSource::Prologue);
+                leaders.insert(ins.address);
+            }
+            // a block also starts wherever code picks up again after data
+            if previous.is_none_or(|p| p.next_address() != ins.address) {
+                leaders.insert(ins.address);
+            }
+            previous = Some(ins);
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut current: Option<BasicBlock> = None;
+        let mut previous: Option<&Instruction> = None;
+        for ins in code.values() {
+            let mut block = current.take().unwrap_or_else(|| BasicBlock {
+                start: ins.address,
+                instructions: Vec::new(),
+                edges: Vec::new(),
+            });
+            block.instructions.push((*ins).clone());
+
+            if let Some(to) = target(ins, follow_far).filter(|t| code.contains_key(t)) {
+                block.edges.push(Edge { to, kind: edge_kind(ins) });
+            }
+            let next = ins.next_address();
+            let stops = ends_path(ins, previous);
+            if !stops && code.contains_key(&next) && leaders.contains(&next) {
+                block.edges.push(Edge { to: next, kind: EdgeKind::FallThrough });
+            }
+            if stops || leaders.contains(&next) || !code.contains_key(&next) {
+                blocks.insert(block.start, block);
+            } else {
+                current = Some(block);
+            }
+            previous = Some(ins);
+        }
+
+        let mut functions = Vec::new();
+        for (&entry, &source) in &function_starts {
+            let mut seen = BTreeSet::new();
+            let mut pending = vec![entry];
+            while let Some(start) = pending.pop() {
+                let Some(block) = blocks.get(&start) else { continue };
+                if !seen.insert(start) {
+                    continue;
+                }
+                for edge in block.edges.iter().filter(|e| !e.kind.is_call()) {
+                    // jumping into another function is a tail call, not part of this one
+                    if !function_starts.contains_key(&edge.to) {
+                        pending.push(edge.to);
+                    }
+                }
+            }
+            functions.push(Function { entry, source, blocks: seen.into_iter().collect() });
+        }
+
+        ControlFlowGraph { blocks, functions }
+    }
+
+    pub fn function_at(&self, entry: usize) -> Option<&Function> {
+        self.functions.iter().find(|f| f.entry == entry)
+    }
+
+    /// <p>One function as a Graphviz <code>digraph</code>: a box per basic block listing its instructions, solid
+    /// edges for jumps, dashed ones for falling through, and dotted edges out to the functions it calls or jumps
+    /// into.</p>
+    pub fn to_dot(&self, function: &Function) -> String {
+        let mut dot = format!("digraph {} {{\n", function.name());
+        dot += "    node [shape=box, fontname=\"Courier\"];\n";
+
+        let mut outside = BTreeSet::new();
+        for start in &function.blocks {
+            let block = &self.blocks[start];
+            let mut label = String::new();
+            for ins in &block.instructions {
+                label += &format!("{:05X}  {}\\l", ins.address, escape(&ins.to_string()));
+            }
+            dot += &format!("    b{start:05X} [label=\"{label}\"];\n");
+
+            for edge in &block.edges {
+                let inside = !edge.kind.is_call() && function.blocks.contains(&edge.to);
+                let to = if inside {
+                    format!("b{:05X}", edge.to)
+                } else {
+                    outside.insert(edge.to);
+                    format!("sub_{:05X}", edge.to)
+                };
+                let style = match edge.kind {
+                    EdgeKind::FallThrough => "style=dashed",
+                    EdgeKind::Branch => "color=darkgreen",
+                    _ if !inside => "style=dotted",
+                    _ => "style=solid",
+                };
+                dot += &format!("    b{start:05X} -> {to} [{style}];\n");
+            }
+        }
+        for to in outside {
+            dot += &format!("    sub_{to:05X} [shape=ellipse];\n");
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// <p>Every function's graph, next to the function's name.</p>
+    pub fn to_dot_all(&self) -> Vec<(String, String)> {
+        self.functions.iter().map(|f| (f.name(), self.to_dot(f))).collect()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_operation::trace::trace_code;
+
+    /// <p>An entry function that calls a near and a far function, branches and then jumps into a function found only
+    /// from its prologue.  The gaps are data.</p>
+    const PROGRAM: &[u8] = &[
+        0xE8, 0x0E, 0x00, //             00 call 0x11
+        0x9A, 0x00, 0x00, 0x02, 0x00, // 03 call 0x2:0x0
+        0x74, 0x01, //                   08 jz 0xb
+        0x90, //                         0a nop
+        0xEB, 0x23, //                   0b jmp short 0x30
+        0x00, 0x00, 0x00, 0x00, //
+        0x55, //                         11 push bp
+        0x89, 0xE5, //                   12 mov bp,sp
+        0x5D, //                         14 pop bp
+        0xC3, //                         15 ret
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0xCB, //                         20 retf
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x55, //                         30 push bp
+        0x89, 0xE5, //                   31 mov bp,sp
+        0x5D, //                         33 pop bp
+        0xC3, //                         34 ret
+    ];
+
+    fn graph() -> ControlFlowGraph {
+        ControlFlowGraph::build(&trace_code(PROGRAM, &[0], true), &[0], true)
+    }
+
+    #[test]
+    fn splits_blocks_at_branches_and_their_targets() {
+        let graph = graph();
+        let starts: Vec<usize> = graph.blocks.keys().copied().collect();
+        assert_eq!(starts, [0x00, 0x0A, 0x0B, 0x11, 0x20, 0x30]);
+
+        let first = &graph.blocks[&0];
+        assert_eq!(first.instructions.len(), 3);
+        assert_eq!(first.end(), 0x0A);
+        let edge = |to, kind| Edge { to, kind };
+        assert_eq!(
+            first.edges,
+            [
+                edge(0x11, EdgeKind::Call),
+                edge(0x20, EdgeKind::FarCall),
+                edge(0x0B, EdgeKind::Branch),
+                edge(0x0A, EdgeKind::FallThrough),
+            ]
+        );
+        assert_eq!(graph.blocks[&0x0A].edges, [edge(0x0B, EdgeKind::FallThrough)]);
+        assert_eq!(graph.blocks[&0x0B].edges, [edge(0x30, EdgeKind::Jump)]);
+        assert!(graph.blocks[&0x11].edges.is_empty());
+    }
+
+    #[test]
+    fn finds_functions_from_entries_calls_and_prologues() {
+        let graph = graph();
+        let functions: Vec<(usize, FunctionSource, Vec<usize>)> =
+            graph.functions.iter().map(|f| (f.entry, f.source, f.blocks.clone())).collect();
+        assert_eq!(
+            functions,
+            [
+                (0x00, FunctionSource::Entry, vec![0x00, 0x0A, 0x0B]),
+                (0x11, FunctionSource::CallTarget, vec![0x11]),
+                (0x20, FunctionSource::CallTarget, vec![0x20]),
+                (0x30, FunctionSource::Prologue, vec![0x30]),
+            ]
+        );
+        assert_eq!(graph.function_at(0x30).unwrap().name(), "sub_00030");
+    }
+
+    #[test]
+    fn far_calls_need_follow_far() {
+        let graph = ControlFlowGraph::build(&trace_code(PROGRAM, &[0], false), &[0], false);
+        assert!(graph.function_at(0x20).is_none());
+        assert!(!graph.blocks[&0].edges.iter().any(|e| e.kind == EdgeKind::FarCall));
+    }
+
+    #[test]
+    fn dot_links_blocks_and_called_functions() {
+        let graph = graph();
+        let dot = graph.to_dot(graph.function_at(0).unwrap());
+        for line in [
+            "digraph sub_00000 {",
+            r#"    b00000 [label="00000  call 0x11\l00003  call 0x2:0x0\l00008  jz 0xB\l"];"#,
+            "    b00000 -> sub_00011 [style=dotted];",
+            "    b00000 -> sub_00020 [style=dotted];",
+            "    b00000 -> b0000B [color=darkgreen];",
+            "    b00000 -> b0000A [style=dashed];",
+            "    b0000B -> sub_00030 [style=dotted];",
+            "    sub_00030 [shape=ellipse];",
+        ] {
+            assert!(dot.lines().any(|l| l == line), "no {line:?} in:\n{dot}");
+        }
+        // nothing outside the function gets a box of its own
+        assert!(!dot.contains("b00011"));
+        assert_eq!(graph.to_dot_all().len(), 4);
+    }
+}
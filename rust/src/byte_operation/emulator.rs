@@ -0,0 +1,314 @@
+use std::{collections::BTreeSet, ops::Range};
+
+use super::{
+    instruction::{Instruction, Operand},
+    x86_16::{execute_fetched, fetch, op_cd, Cpu},
+};
+use crate::{
+    apis::{dos::Startup, API},
+    error::Error,
+    executable::{Executable, InteruptChange},
+    memory::{Memory, Watchpoint},
+};
+
+/// <p>Why the emulator stopped before running as far as it was asked to.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HaltReason {
+    /// CS:IP reached a breakpoint, which hasn't been run yet.
+    Breakpoint { segment: u16, offset: u16 },
+    /// The instruction just run read or wrote a watched address.
+    Watchpoint { address: usize, write: bool },
+    /// An interrupt the API doesn't service.  IP is already past the <code>int</code>, so the caller can service it
+    /// and carry on.
+    Interrupt { vector: u8, function: u8 },
+    /// The bytes at CS:IP aren't an 8086 instruction.
+    InvalidOpcode { segment: u16, offset: u16, byte: u8 },
+    /// An 8086 instruction the emulator doesn't implement yet, at physical address <code>addr</code>.  CS:IP is left
+    /// on it.
+    Unsupported { addr: usize, opcode: u8 },
+    /// The program ended, or ran <code>hlt</code>.
+    Halted,
+}
+
+/// <p>Runs a program loaded into memory one instruction at a time, stopping at breakpoints, watchpoints and
+/// anything it can't go on from.</p>
+pub struct Emulator {
+    pub cpu: Cpu,
+    pub memory: Memory,
+    /// Which interrupts get serviced instead of halting.
+    pub api: API,
+    /// Physical addresses to stop at.
+    breakpoints: BTreeSet<usize>,
+    /// How many instructions have run.
+    pub instruction_count: u64,
+    /// Set once the program has ended through DOS.
+    pub exit_code: Option<u8>,
+    /// What the program printed through DOS, for the caller to show.
+    pub output: Vec<u8>,
+}
+
+impl Emulator {
+    pub fn new(cpu: Cpu, memory: Memory, api: API) -> Self {
+        // whatever loading the program set off isn't the program's doing
+        memory.take_hits();
+        let breakpoints = BTreeSet::new();
+        Self { cpu, memory, api, breakpoints, instruction_count: 0, exit_code: None, output: Vec::new() }
+    }
+
+    /// <p>An emulator with the DOS program of <code>executable</code> loaded and ready to start.</p>
+    pub fn load(executable: &Executable, startup: &Startup, api: API) -> Result<Self, Error> {
+        let (mut cpu, mut memory) = (Cpu::new(), Memory::new());
+        executable.load(&mut memory, &mut cpu, startup)?;
+        Ok(Self::new(cpu, memory, api))
+    }
+
+    pub fn add_breakpoint(&mut self, segment: u16, offset: u16) {
+        self.breakpoints.insert(self.memory.physical(segment, offset));
+    }
+    pub fn remove_breakpoint(&mut self, segment: u16, offset: u16) {
+        self.breakpoints.remove(&self.memory.physical(segment, offset));
+    }
+
+    /// <p>Stops after any instruction that reads, writes or does either to the physical addresses in
+    /// <code>range</code>.</p>
+    pub fn add_watchpoint(&mut self, range: Range<usize>, read: bool, write: bool) {
+        self.memory.watchpoints.push(Watchpoint { range, read, write });
+    }
+    pub fn remove_watchpoint(&mut self, range: Range<usize>) {
+        self.memory.watchpoints.retain(|w| w.range != range);
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.memory.physical(self.cpu.cs, self.cpu.ip))
+    }
+
+    /// <p>The instruction at CS:IP, without running it.</p>
+    pub fn current_instruction(&self) -> Result<Instruction, Error> {
+        fetch(&self.cpu, &self.memory)
+    }
+
+    /// <p>Runs the instruction at CS:IP, whether or not there is a breakpoint on it.  Returns why it stopped if the
+    /// instruction can't run or ran into a watchpoint, an interrupt or the end of the program.</p>
+    pub fn step(&mut self) -> Result<Option<HaltReason>, Error> {
+        if self.exit_code.is_some() {
+            return Ok(Some(HaltReason::Halted));
+        }
+        let (segment, offset) = (self.cpu.cs, self.cpu.ip);
+        let ins = fetch(&self.cpu, &self.memory)?;
+        if ins.is_data() {
+            return Ok(Some(HaltReason::InvalidOpcode { segment, offset, byte: ins.bytes[0] }));
+        }
+        self.cpu.ip = ins.next_address() as u16;
+
+        let halt = match ins.opcode() {
+            0xF4 => Ok(Some(HaltReason::Halted)),
+            0xCD => self.interrupt(&ins),
+            _ => execute_fetched(&mut self.cpu, &mut self.memory, &ins).map(|_| None),
+        };
+        // leave CS:IP on the instruction that failed, for looking at
+        let halt = match halt.inspect_err(|_| self.cpu.ip = offset) {
+            Err(Error::UnsupportedOpcode { byte, .. }) => {
+                // it didn't run, so nothing it read on the way is the program's doing
+                self.memory.take_hits();
+                return Ok(Some(HaltReason::Unsupported { addr: self.memory.physical(segment, offset), opcode: byte }));
+            }
+            halt => halt?,
+        };
+        self.instruction_count += 1;
+        let hit = self.memory.take_hits().first().copied();
+        Ok(halt.or(hit.map(|a| HaltReason::Watchpoint { address: a.address, write: a.write })))
+    }
+
+    fn interrupt(&mut self, ins: &Instruction) -> Result<Option<HaltReason>, Error> {
+        let Some(Operand::Immediate(vector)) = ins.operands.first() else {
+            return Err(Error::InvalidOperand { addr: ins.address });
+        };
+        let unserviced = HaltReason::Interrupt { vector: *vector as u8, function: self.cpu.ah };
+        if self.api == API::None {
+            return Ok(Some(unserviced));
+        }
+        match op_cd(&mut self.cpu, &mut self.memory, ins, self.api) {
+            Ok(InteruptChange::Exit(code)) => {
+                self.exit_code = Some(code);
+                Ok(Some(HaltReason::Halted))
+            }
+            Ok(InteruptChange::String(start, end)) => {
+                let len = end.wrapping_sub(start) as usize;
+                self.output.extend(self.memory.peek_bytes(self.cpu.ds, start, len));
+                Ok(None)
+            }
+            Ok(_) => Ok(None),
+            Err(Error::UnsupportedInterrupt { .. }) => Ok(Some(unserviced)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// <p>Runs up to <code>max_instructions</code> instructions.  Returns <code>None</code> if they all ran, or why
+    /// it stopped early.  A breakpoint at CS:IP when it starts doesn't stop it, so it can carry on from one.</p>
+    pub fn run(&mut self, max_instructions: u64) -> Result<Option<HaltReason>, Error> {
+        for i in 0..max_instructions {
+            if i > 0 && self.at_breakpoint() {
+                return Ok(Some(HaltReason::Breakpoint { segment: self.cpu.cs, offset: self.cpu.ip }));
+            }
+            if let Some(halt) = self.step()? {
+                return Ok(Some(halt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// <p>Runs until CS:IP is <code>segment:offset</code>, returning <code>None</code> once it gets there, or why it
+    /// stopped before.  It has no limit, so a program that never gets there and never halts keeps it running.</p>
+    pub fn run_until(&mut self, segment: u16, offset: u16) -> Result<Option<HaltReason>, Error> {
+        let target = self.memory.physical(segment, offset);
+        let mut first = true;
+        while self.memory.physical(self.cpu.cs, self.cpu.ip) != target {
+            if !first && self.at_breakpoint() {
+                return Ok(Some(HaltReason::Breakpoint { segment: self.cpu.cs, offset: self.cpu.ip }));
+            }
+            first = false;
+            if let Some(halt) = self.step()? {
+                return Ok(Some(halt));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEGMENT: u16 = 0x1000;
+
+    /// <p>Adds 2 to AX three times, stores it at DS:0200, takes 1 from it and exits with the result if it is 5.</p>
+    const PROGRAM: &[u8] = &[
+        0xB9, 0x03, 0x00, // 0100 mov cx,3
+        0x31, 0xC0, //       0103 xor ax,ax
+        0x05, 0x02, 0x00, // 0105 add ax,2
+        0xE2, 0xFB, //       0108 loop 0105
+        0x89, 0x06, 0x00, 0x02, // 010a mov [0200],ax
+        0x83, 0xE8, 0x01, // 010e sub ax,1
+        0x3D, 0x05, 0x00, // 0111 cmp ax,5
+        0x74, 0x01, //       0114 jz 0117
+        0xF4, //             0116 hlt
+        0xB4, 0x4C, //       0117 mov ah,4c
+        0xCD, 0x21, //       0119 int 21
+    ];
+
+    fn emulator(program: &[u8]) -> Emulator {
+        let (mut cpu, mut memory) = (Cpu::new(), Memory::new());
+        memory.write_bytes(SEGMENT, 0x100, program);
+        (cpu.cs, cpu.ds, cpu.es, cpu.ss) = (SEGMENT, SEGMENT, SEGMENT, SEGMENT);
+        (cpu.ip, cpu.sp) = (0x100, 0xFFFE);
+        Emulator::new(cpu, memory, API::DOS)
+    }
+
+    #[test]
+    fn stops_at_breakpoints_and_watchpoints() {
+        let mut emulator = emulator(PROGRAM);
+        emulator.add_breakpoint(SEGMENT, 0x10A);
+        let stored = emulator.memory.physical(SEGMENT, 0x200);
+        emulator.add_watchpoint(stored..stored + 2, false, true);
+
+        assert_eq!(emulator.run(100).unwrap(), Some(HaltReason::Breakpoint { segment: SEGMENT, offset: 0x10A }));
+        assert_eq!((emulator.cpu.ax(), emulator.cpu.cx()), (6, 0));
+        assert_eq!(emulator.memory.read_word(SEGMENT, 0x200), 0);
+
+        assert_eq!(emulator.run(100).unwrap(), Some(HaltReason::Watchpoint { address: stored, write: true }));
+        assert_eq!(emulator.cpu.ip, 0x10E);
+        assert_eq!(emulator.memory.read_word(SEGMENT, 0x200), 6);
+
+        assert_eq!(emulator.run(100).unwrap(), Some(HaltReason::Halted));
+        assert_eq!(emulator.exit_code, Some(5));
+        assert_eq!(emulator.instruction_count, 14);
+    }
+
+    #[test]
+    fn run_until_stops_before_the_target() {
+        let mut emulator = emulator(PROGRAM);
+        assert_eq!(emulator.run_until(SEGMENT, 0x111).unwrap(), None);
+        assert_eq!(emulator.cpu.ax(), 5);
+        assert!(!emulator.cpu.zf && !emulator.cpu.cf);
+    }
+
+    #[test]
+    fn sub_sets_the_flags() {
+        // mov ax,0 / sub ax,1 / sub ax,0xffff
+        let mut emulator = emulator(&[0xB8, 0x00, 0x00, 0x83, 0xE8, 0x01, 0x81, 0xE8, 0xFF, 0xFF]);
+        emulator.run(2).unwrap();
+        assert_eq!(emulator.cpu.ax(), 0xFFFF);
+        assert!(emulator.cpu.cf && emulator.cpu.sf && emulator.cpu.af && !emulator.cpu.zf && !emulator.cpu.of);
+        emulator.step().unwrap();
+        assert_eq!(emulator.cpu.ax(), 0);
+        assert!(!emulator.cpu.cf && !emulator.cpu.sf && emulator.cpu.zf && emulator.cpu.pf);
+    }
+
+    #[test]
+    fn rep_only_repeats_string_instructions() {
+        // mov cx,3 / rep inc ax / repne add ax,1
+        let mut emulator = emulator(&[0xB9, 0x03, 0x00, 0xF3, 0x40, 0xF2, 0x05, 0x01, 0x00]);
+        emulator.run(3).unwrap();
+        assert_eq!((emulator.cpu.ax(), emulator.cpu.cx()), (2, 3));
+    }
+
+    #[test]
+    fn rep_with_cx_0_skips_string_instructions() {
+        // mov di,0200 / rep scasb / repne scasb
+        let mut emulator = emulator(&[0xBF, 0x00, 0x02, 0xF3, 0xAE, 0xF2, 0xAE]);
+        emulator.cpu.zf = true;
+        emulator.run(3).unwrap();
+        assert_eq!((emulator.cpu.di, emulator.cpu.cx(), emulator.cpu.ip), (0x200, 0, 0x107));
+        assert!(emulator.cpu.zf);
+    }
+
+    #[test]
+    fn scasb_sets_the_flags_of_cmp() {
+        // mov al,10 / mov cx,5 / mov di,0200 / repne scasb
+        let mut emulator = emulator(&[0xB0, 0x10, 0xB9, 0x05, 0x00, 0xBF, 0x00, 0x02, 0xF2, 0xAE]);
+        emulator.memory.write_bytes(SEGMENT, 0x200, &[0x11, 0x10]);
+        emulator.run(4).unwrap();
+        assert_eq!((emulator.cpu.di, emulator.cpu.cx()), (0x202, 3));
+        assert!(emulator.cpu.zf && !emulator.cpu.cf);
+        // a single one at 0202, which holds 0
+        emulator.memory.write_bytes(SEGMENT, 0x10A, &[0xAE]);
+        emulator.step().unwrap();
+        assert!(!emulator.cpu.zf && !emulator.cpu.cf && !emulator.cpu.sf && !emulator.cpu.af && !emulator.cpu.of);
+        emulator.cpu.al = 0x01;
+        emulator.memory.write_byte(SEGMENT, 0x203, 0x02);
+        (emulator.cpu.ip, emulator.cpu.di) = (0x10A, 0x203);
+        emulator.step().unwrap();
+        assert!(emulator.cpu.cf && emulator.cpu.sf && emulator.cpu.af && emulator.cpu.pf && !emulator.cpu.zf);
+    }
+
+    #[test]
+    fn neg_sets_af() {
+        // mov ax,0010 / neg ax / mov bl,01 / neg bl
+        let mut emulator = emulator(&[0xB8, 0x10, 0x00, 0xF7, 0xD8, 0xB3, 0x01, 0xF6, 0xDB]);
+        emulator.run(2).unwrap();
+        assert_eq!(emulator.cpu.ax(), 0xFFF0);
+        assert!(emulator.cpu.cf && emulator.cpu.sf && !emulator.cpu.af);
+        emulator.run(2).unwrap();
+        assert_eq!((emulator.cpu.bl, emulator.cpu.bh), (0xFF, 0));
+        assert!(emulator.cpu.cf && emulator.cpu.sf && emulator.cpu.af && !emulator.cpu.of);
+    }
+
+    #[test]
+    fn printed_strings_are_collected() {
+        // mov dx,0200 / mov ah,09 / int 21
+        let mut emulator = emulator(&[0xBA, 0x00, 0x02, 0xB4, 0x09, 0xCD, 0x21]);
+        emulator.memory.write_bytes(SEGMENT, 0x200, b"Hello\r\n$");
+        assert_eq!(emulator.run(3).unwrap(), None);
+        assert_eq!(emulator.output, b"Hello\r\n");
+    }
+
+    #[test]
+    fn unsupported_instructions_halt_in_place() {
+        // mov bx,0 / xlat
+        let mut emulator = emulator(&[0xBB, 0x00, 0x00, 0xD7]);
+        let addr = emulator.memory.physical(SEGMENT, 0x103);
+        assert_eq!(emulator.run(10).unwrap(), Some(HaltReason::Unsupported { addr, opcode: 0xD7 }));
+        assert_eq!(emulator.cpu.ip, 0x103);
+        assert_eq!(emulator.instruction_count, 1);
+    }
+}
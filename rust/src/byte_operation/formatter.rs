@@ -0,0 +1,315 @@
+use super::instruction::{Displacement, Instruction, Memory, Operand, OperandSize, Prefix};
+
+/// <p>Turns a decoded instruction into assembly text for one assembler's syntax.</p>
+pub trait Formatter {
+    fn format(&self, ins: &Instruction) -> String;
+}
+
+/// <p>Microsoft Macro Assembler syntax: <code>mov word ptr es:[bx+4],1234h</code>.</p>
+pub struct Masm;
+/// <p>Netwide Assembler syntax: <code>mov word [es:bx+0x4],0x1234</code>.</p>
+pub struct Nasm;
+/// <p>GNU assembler AT&amp;T syntax: <code>movw $0x1234,%es:0x4(%bx)</code>.</p>
+pub struct Att;
+
+/// <p>Whether a memory operand needs a size annotation, which is when no register operand already gives the size
+/// away.</p>
+fn needs_size(ins: &Instruction) -> bool {
+    let shift = matches!(ins.mnemonic, "rol" | "ror" | "rcl" | "rcr" | "shl" | "shr" | "sar");
+    let sized_by_register = ins
+        .operands
+        .iter()
+        .enumerate()
+        .any(|(i, o)| matches!(o, Operand::Register(_)) && !(shift && i == 1));
+    !sized_by_register
+}
+
+fn memory_size(ins: &Instruction) -> Option<OperandSize> {
+    ins.operands.iter().find_map(|o| match o {
+        Operand::Memory(m) => Some(m.size),
+        _ => None,
+    })
+}
+
+fn is_far_branch(ins: &Instruction) -> bool {
+    matches!(ins.mnemonic, "call" | "jmp") && memory_size(ins) == Some(OperandSize::Far)
+}
+
+/// <p>The <code>lock</code>/<code>rep</code> prefixes, plus a segment override when there is no memory operand to
+/// attach it to.</p>
+fn prefix_text(ins: &Instruction) -> String {
+    let mut text = String::new();
+    let has_memory = memory_size(ins).is_some();
+    for prefix in &ins.prefixes {
+        match prefix {
+            Prefix::Lock => text += "lock ",
+            Prefix::Repne => text += "repne ",
+            Prefix::Rep if matches!(ins.mnemonic, "cmpsb" | "cmpsw" | "scasb" | "scasw") => text += "repe ",
+            Prefix::Rep => text += "rep ",
+            Prefix::Segment(seg) if !has_memory => text += &format!("{seg} "),
+            Prefix::Segment(_) => {}
+        }
+    }
+    text
+}
+
+fn join(mnemonic: &str, operands: &[String]) -> String {
+    if operands.is_empty() {
+        mnemonic.to_owned()
+    } else {
+        format!("{mnemonic} {}", operands.join(","))
+    }
+}
+
+/// <p>Joins base, index and displacement as <code>bx+si+disp</code>, with <code>hex</code> formatting the
+/// displacement.</p>
+fn intel_address(m: &Memory, hex: fn(u16) -> String) -> String {
+    let mut inner = String::new();
+    for r in [m.base, m.index].into_iter().flatten() {
+        if !inner.is_empty() {
+            inner.push('+');
+        }
+        inner += r.name();
+    }
+    match m.displacement {
+        Some(Displacement::Word(w)) if inner.is_empty() => inner = hex(w),
+        Some(Displacement::Word(w)) => inner += &format!("+{}", hex(w)),
+        Some(Displacement::Byte(b)) if b < 0 => inner += &format!("-{}", hex(b.unsigned_abs() as u16)),
+        Some(Displacement::Byte(b)) => inner += &format!("+{}", hex(b as u16)),
+        None => {}
+    }
+    inner
+}
+
+fn masm_hex(v: u16) -> String {
+    let digits = format!("{v:X}");
+    if digits.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        format!("0{digits}h")
+    } else {
+        format!("{digits}h")
+    }
+}
+
+fn masm_signed_hex(v: i16) -> String {
+    if v < 0 {
+        format!("-{}", masm_hex(v.unsigned_abs()))
+    } else {
+        masm_hex(v as u16)
+    }
+}
+
+impl Masm {
+    fn operand(&self, ins: &Instruction, op: &Operand) -> String {
+        match op {
+            Operand::Register(r) => r.name().to_owned(),
+            Operand::Memory(m) => {
+                let size = match m.size {
+                    OperandSize::Byte if needs_size(ins) => "byte ptr ",
+                    OperandSize::Word if needs_size(ins) => "word ptr ",
+                    OperandSize::Far => "dword ptr ",
+                    _ => "",
+                };
+                // MASM reads a bare [1234h] as an immediate, so direct addresses always name their segment
+                let segment = match m.segment {
+                    Some(seg) => format!("{seg}:"),
+                    None if m.base.is_none() && m.index.is_none() => "ds:".to_owned(),
+                    None => String::new(),
+                };
+                format!("{size}{segment}[{}]", intel_address(m, masm_hex))
+            }
+            Operand::Immediate(v) => masm_hex(*v),
+            Operand::SignExtended(v) => masm_signed_hex(*v as i16),
+            Operand::Relative(target) => masm_hex(*target as u16),
+            Operand::Far { segment, offset } => format!("far ptr {}:{}", masm_hex(*segment), masm_hex(*offset)),
+        }
+    }
+}
+
+impl Formatter for Masm {
+    fn format(&self, ins: &Instruction) -> String {
+        let mnemonic = match ins.mnemonic {
+            "int3" => "int 3",
+            "jmp" if ins.opcode() == 0xEB => "jmp short",
+            m => m,
+        };
+        let operands: Vec<String> = ins.operands.iter().map(|o| self.operand(ins, o)).collect();
+        prefix_text(ins) + &join(mnemonic, &operands)
+    }
+}
+
+fn nasm_hex(v: u16) -> String {
+    format!("0x{v:X}")
+}
+
+impl Nasm {
+    fn operand(&self, ins: &Instruction, op: &Operand) -> String {
+        match op {
+            Operand::Register(r) => r.name().to_owned(),
+            Operand::Memory(m) => {
+                let size = match m.size {
+                    OperandSize::Byte if needs_size(ins) => "byte ",
+                    OperandSize::Word if needs_size(ins) => "word ",
+                    OperandSize::Far if is_far_branch(ins) => "far ",
+                    _ => "",
+                };
+                let segment = m.segment.map(|seg| format!("{seg}:")).unwrap_or_default();
+                format!("{size}[{segment}{}]", intel_address(m, nasm_hex))
+            }
+            Operand::Immediate(v) => nasm_hex(*v),
+            Operand::SignExtended(v) if *v < 0 => format!("-{}", nasm_hex(v.unsigned_abs() as u16)),
+            Operand::SignExtended(v) => nasm_hex(*v as u16),
+            Operand::Relative(target) => format!("0x{target:X}"),
+            Operand::Far { segment, offset } => format!("{}:{}", nasm_hex(*segment), nasm_hex(*offset)),
+        }
+    }
+}
+
+impl Formatter for Nasm {
+    fn format(&self, ins: &Instruction) -> String {
+        let mnemonic = match ins.mnemonic {
+            "xlat" => "xlatb",
+            "jmp" if ins.opcode() == 0xEB => "jmp short",
+            m => m,
+        };
+        let operands: Vec<String> = ins.operands.iter().map(|o| self.operand(ins, o)).collect();
+        prefix_text(ins) + &join(mnemonic, &operands)
+    }
+}
+
+fn att_hex(v: u16) -> String {
+    format!("0x{v:x}")
+}
+
+/// <p>The implicit operands of a string instruction or <code>xlat</code> whose source segment is overridden, since
+/// AT&amp;T syntax shows the override on the operand, as <code>movsb %cs:(%si),%es:(%di)</code>.  <code>stos</code>
+/// and <code>scas</code> only address ES:DI, which can't be overridden, so they keep the prefix.</p>
+fn att_string_operands(ins: &Instruction) -> Option<Vec<String>> {
+    let source = ins.segment_override().map(|seg| format!("%{seg}:(%si)"))?;
+    let operands = match ins.mnemonic {
+        "movsb" | "movsw" => [source, "%es:(%di)".to_owned()].to_vec(),
+        "cmpsb" | "cmpsw" => ["%es:(%di)".to_owned(), source].to_vec(),
+        "lodsb" => [source, "%al".to_owned()].to_vec(),
+        "lodsw" => [source, "%ax".to_owned()].to_vec(),
+        "xlat" => [source.replace("%si", "%bx")].to_vec(),
+        _ => return None,
+    };
+    Some(operands)
+}
+
+impl Att {
+    fn operand(&self, ins: &Instruction, op: &Operand) -> String {
+        let indirect = matches!(ins.mnemonic, "call" | "jmp");
+        match op {
+            Operand::Register(r) if indirect => format!("*%{r}"),
+            Operand::Register(r) => format!("%{r}"),
+            Operand::Memory(m) => {
+                let mut text = if indirect { "*".to_owned() } else { String::new() };
+                if let Some(seg) = m.segment {
+                    text += &format!("%{seg}:");
+                }
+                match m.displacement {
+                    Some(Displacement::Byte(b)) if b < 0 => text += &format!("-{}", att_hex(b.unsigned_abs() as u16)),
+                    Some(d) => text += &att_hex(d.value()),
+                    None => {}
+                }
+                match (m.base, m.index) {
+                    (Some(base), Some(index)) => text += &format!("(%{base},%{index})"),
+                    (Some(r), None) | (None, Some(r)) => text += &format!("(%{r})"),
+                    (None, None) => {}
+                }
+                text
+            }
+            Operand::Immediate(v) => format!("${}", att_hex(*v)),
+            Operand::SignExtended(v) if *v < 0 => format!("$-{}", att_hex(v.unsigned_abs() as u16)),
+            Operand::SignExtended(v) => format!("${}", att_hex(*v as u16)),
+            Operand::Relative(target) => format!("0x{target:x}"),
+            Operand::Far { segment, offset } => format!("${},${}", att_hex(*segment), att_hex(*offset)),
+        }
+    }
+}
+
+impl Formatter for Att {
+    fn format(&self, ins: &Instruction) -> String {
+        if let (true, Some(Operand::Immediate(b))) = (ins.is_data(), ins.operands.first()) {
+            return format!(".byte {}", att_hex(*b));
+        }
+
+        let far = ins.far_target().is_some() || is_far_branch(ins);
+        let mut mnemonic = match ins.mnemonic {
+            "call" if far => "lcall".to_owned(),
+            "jmp" if far => "ljmp".to_owned(),
+            "retf" => "lret".to_owned(),
+            "cbw" => "cbtw".to_owned(),
+            "cwd" => "cwtd".to_owned(),
+            m => m.to_owned(),
+        };
+        if needs_size(ins) && !matches!(ins.mnemonic, "call" | "jmp") {
+            match memory_size(ins) {
+                Some(OperandSize::Byte) => mnemonic.push('b'),
+                Some(OperandSize::Word) => mnemonic.push('w'),
+                _ => {}
+            }
+        }
+
+        if let Some(operands) = att_string_operands(ins) {
+            let mut plain = ins.clone();
+            plain.prefixes.retain(|p| !matches!(p, Prefix::Segment(_)));
+            return prefix_text(&plain) + &join(&mnemonic, &operands);
+        }
+
+        // shifts by one are written without the count, as objdump does
+        let implicit_one = matches!(ins.opcode(), 0xD0 | 0xD1);
+        let operands: Vec<String> = ins
+            .operands
+            .iter()
+            .take(if implicit_one { 1 } else { ins.operands.len() })
+            .rev()
+            .map(|o| self.operand(ins, o))
+            .collect();
+        prefix_text(ins) + &join(&mnemonic, &operands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{byte_operation::x86_16::parse_byte_code, byte_stream::ByteStream};
+
+    /// <p>Each encoding as MASM, NASM and AT&amp;T write it.</p>
+    const CORPUS: &[(&[u8], &str, &str, &str)] = &[
+        (&[0x03, 0x46, 0xFC], "add ax,[bp-4h]", "add ax,[bp-0x4]", "add -0x4(%bp),%ax"),
+        (&[0x26, 0x8B, 0x47, 0x04], "mov ax,es:[bx+4h]", "mov ax,[es:bx+0x4]", "mov %es:0x4(%bx),%ax"),
+        (
+            &[0x81, 0x3E, 0x34, 0x12, 0x78, 0x56],
+            "cmp word ptr ds:[1234h],5678h",
+            "cmp word [0x1234],0x5678",
+            "cmpw $0x5678,0x1234",
+        ),
+        (&[0x83, 0xC4, 0xFE], "add sp,-2h", "add sp,-0x2", "add $-0x2,%sp"),
+        (&[0xB4, 0x4C], "mov ah,4Ch", "mov ah,0x4C", "mov $0x4c,%ah"),
+        (&[0xC4, 0x5E, 0x06], "les bx,dword ptr [bp+6h]", "les bx,[bp+0x6]", "les 0x6(%bp),%bx"),
+        (&[0xEB, 0xFE], "jmp short 0h", "jmp short 0x0", "jmp 0x0"),
+        (&[0x9A, 0x78, 0x56, 0x34, 0x12], "call far ptr 1234h:5678h", "call 0x1234:0x5678", "lcall $0x1234,$0x5678"),
+        (&[0xFF, 0x1E, 0x00, 0x02], "call dword ptr ds:[200h]", "call far [0x200]", "lcall *0x200"),
+        (&[0xD3, 0x2F], "shr word ptr [bx],cl", "shr word [bx],cl", "shrw %cl,(%bx)"),
+        (&[0xF0, 0x87, 0x07], "lock xchg [bx],ax", "lock xchg [bx],ax", "lock xchg %ax,(%bx)"),
+        (&[0xCB], "retf", "retf", "lret"),
+        (&[0xD7], "xlat", "xlatb", "xlat"),
+        (&[0x2E, 0xF3, 0xA4], "cs rep movsb", "cs rep movsb", "rep movsb %cs:(%si),%es:(%di)"),
+        (&[0x26, 0xA7], "es cmpsw", "es cmpsw", "cmpsw %es:(%di),%es:(%si)"),
+        (&[0x36, 0xAC], "ss lodsb", "ss lodsb", "lodsb %ss:(%si),%al"),
+        (&[0x26, 0xD7], "es xlat", "es xlatb", "xlat %es:(%bx)"),
+        (&[0x26, 0xAA], "es stosb", "es stosb", "es stosb"),
+        (&[0x0F], "db 0Fh", "db 0xF", ".byte 0xf"),
+    ];
+
+    #[test]
+    fn formats_each_syntax() {
+        for (bytes, masm, nasm, att) in CORPUS {
+            let ins = parse_byte_code(&mut ByteStream::new(bytes.to_vec())).unwrap();
+            assert_eq!(Masm.format(&ins), *masm);
+            assert_eq!(Nasm.format(&ins), *nasm);
+            assert_eq!(Att.format(&ins), *att);
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+
+use super::formatter::{Formatter, Nasm};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Register {
+    AL,
+    CL,
+    DL,
+    BL,
+    AH,
+    CH,
+    DH,
+    BH,
+
+    AX,
+    CX,
+    DX,
+    BX,
+    SP,
+    BP,
+    SI,
+    DI,
+
+    ES,
+    CS,
+    SS,
+    DS,
+}
+
+impl Register {
+    const BYTE: [Register; 8] = [
+        Register::AL,
+        Register::CL,
+        Register::DL,
+        Register::BL,
+        Register::AH,
+        Register::CH,
+        Register::DH,
+        Register::BH,
+    ];
+    const WORD: [Register; 8] = [
+        Register::AX,
+        Register::CX,
+        Register::DX,
+        Register::BX,
+        Register::SP,
+        Register::BP,
+        Register::SI,
+        Register::DI,
+    ];
+    const SEGMENT: [Register; 4] = [Register::ES, Register::CS, Register::SS, Register::DS];
+
+    /// <p>The general register encoded by a 3-bit reg or r/m field.</p>
+    pub fn general(index: u8, word: bool) -> Self {
+        if word {
+            Self::WORD[index as usize & 0b111]
+        } else {
+            Self::BYTE[index as usize & 0b111]
+        }
+    }
+    /// <p>The segment register encoded by a 2-bit sreg field.</p>
+    pub fn segment(index: u8) -> Self {
+        Self::SEGMENT[index as usize & 0b11]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Register::AL => "al",
+            Register::CL => "cl",
+            Register::DL => "dl",
+            Register::BL => "bl",
+            Register::AH => "ah",
+            Register::CH => "ch",
+            Register::DH => "dh",
+            Register::BH => "bh",
+            Register::AX => "ax",
+            Register::CX => "cx",
+            Register::DX => "dx",
+            Register::BX => "bx",
+            Register::SP => "sp",
+            Register::BP => "bp",
+            Register::SI => "si",
+            Register::DI => "di",
+            Register::ES => "es",
+            Register::CS => "cs",
+            Register::SS => "ss",
+            Register::DS => "ds",
+        }
+    }
+
+    pub fn is_byte(&self) -> bool {
+        Self::BYTE.contains(self)
+    }
+    pub fn is_segment(&self) -> bool {
+        Self::SEGMENT.contains(self)
+    }
+
+    /// <p>The 16-bit register an 8-bit register is half of.  Word registers return themselves.</p>
+    pub fn full(&self) -> Register {
+        match self {
+            Register::AL | Register::AH => Register::AX,
+            Register::CL | Register::CH => Register::CX,
+            Register::DL | Register::DH => Register::DX,
+            Register::BL | Register::BH => Register::BX,
+            r => *r,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// <p>How many bytes an operand covers.  <code>None</code> is used where the size has no meaning, such as the source of
+/// <code>lea</code>.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperandSize {
+    None,
+    Byte,
+    Word,
+    /// A 32-bit segment:offset pointer, as loaded by <code>les</code>/<code>lds</code> and used by far indirect calls
+    /// and jumps.
+    Far,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Displacement {
+    /// An 8-bit displacement, sign-extended by the processor.
+    Byte(i8),
+    Word(u16),
+}
+
+impl Displacement {
+    pub fn value(&self) -> u16 {
+        match self {
+            Displacement::Byte(b) => *b as u16,
+            Displacement::Word(w) => *w,
+        }
+    }
+}
+
+/// <p>A memory operand from a ModR/M byte or a direct address.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Memory {
+    /// The segment override prefix that applies to this operand, if any.
+    pub segment: Option<Register>,
+    pub base: Option<Register>,
+    pub index: Option<Register>,
+    pub displacement: Option<Displacement>,
+    pub size: OperandSize,
+}
+
+impl Memory {
+    /// <p>The segment used when there is no override: SS for BP-based addressing, DS for everything else.</p>
+    pub fn default_segment(&self) -> Register {
+        if self.base == Some(Register::BP) {
+            Register::SS
+        } else {
+            Register::DS
+        }
+    }
+    pub fn effective_segment(&self) -> Register {
+        self.segment.unwrap_or_else(|| self.default_segment())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(Register),
+    Memory(Memory),
+    /// An immediate byte or word, zero-extended.
+    Immediate(u16),
+    /// An 8-bit immediate that the processor sign-extends to the operand size (opcode 83).
+    SignExtended(i8),
+    /// The absolute target of a relative jump, call or loop.
+    Relative(usize),
+    /// The target of a direct far call or jump.
+    Far { segment: u16, offset: u16 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prefix {
+    Lock,
+    /// <code>rep</code>/<code>repe</code> (F3).
+    Rep,
+    /// <code>repne</code> (F2).
+    Repne,
+    /// A segment override (26, 2E, 36, 3E).
+    Segment(Register),
+}
+
+/// <p>A single decoded 16-bit x86 instruction.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// The position of the first byte (including prefixes) in the decoded buffer.
+    pub address: usize,
+    pub length: usize,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    pub prefixes: Vec<Prefix>,
+}
+
+impl Instruction {
+    /// <p>A one-byte <code>db</code> placeholder for a byte that isn't code.</p>
+    pub fn data(address: usize, byte: u8) -> Self {
+        Instruction {
+            address,
+            length: 1,
+            bytes: vec![byte],
+            mnemonic: "db",
+            operands: vec![Operand::Immediate(byte as u16)],
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// <p>Formats this instruction in the given assembler syntax.  <code>Display</code> uses NASM syntax.</p>
+    pub fn format_with(&self, syntax: &dyn Formatter) -> String {
+        syntax.format(self)
+    }
+
+    /// <p>The opcode byte, after any prefixes.</p>
+    pub fn opcode(&self) -> u8 {
+        self.bytes[self.prefixes.len()]
+    }
+
+    /// <p>The address right after this instruction, where execution falls through to.</p>
+    pub fn next_address(&self) -> usize {
+        self.address + self.length
+    }
+
+    /// <p>Whether this is a placeholder for a byte that doesn't decode to an 8086 instruction.</p>
+    pub fn is_data(&self) -> bool {
+        self.mnemonic == "db"
+    }
+
+    pub fn segment_override(&self) -> Option<Register> {
+        self.prefixes.iter().find_map(|p| match p {
+            Prefix::Segment(r) => Some(*r),
+            _ => None,
+        })
+    }
+
+    pub fn is_call(&self) -> bool {
+        self.mnemonic == "call"
+    }
+    pub fn is_return(&self) -> bool {
+        matches!(self.mnemonic, "ret" | "retf" | "iret")
+    }
+    pub fn is_unconditional_jump(&self) -> bool {
+        self.mnemonic == "jmp"
+    }
+    /// <p>Conditional jumps, including <code>loop</code> and <code>jcxz</code>.</p>
+    pub fn is_conditional_jump(&self) -> bool {
+        matches!(self.mnemonic, "loopne" | "loope" | "loop" | "jcxz")
+            || (self.mnemonic.starts_with('j') && self.mnemonic != "jmp")
+    }
+
+    /// <p>The target of a direct near branch or call.</p>
+    pub fn branch_target(&self) -> Option<usize> {
+        self.operands.iter().find_map(|o| match o {
+            Operand::Relative(target) => Some(*target),
+            _ => None,
+        })
+    }
+    /// <p>The target of a direct far branch or call.</p>
+    pub fn far_target(&self) -> Option<(u16, u16)> {
+        self.operands.iter().find_map(|o| match o {
+            Operand::Far { segment, offset } => Some((*segment, *offset)),
+            _ => None,
+        })
+    }
+
+    /// <p>Whether the first operand is a byte, which makes <code>mul</code> and <code>div</code> work on AL and AX
+    /// instead of AX and DX:AX.</p>
+    fn is_byte_operation(&self) -> bool {
+        match self.operands.first() {
+            Some(Operand::Register(r)) => r.is_byte(),
+            Some(Operand::Memory(m)) => m.size == OperandSize::Byte,
+            _ => false,
+        }
+    }
+
+    /// <p>The registers this instruction reads: source operands, registers used for addressing memory,
+    /// read-modify-write destinations and the common implicit ones (string registers, the stack pointer, AL or AX
+    /// for multiplication, AX or DX:AX for division).</p>
+    pub fn registers_read(&self) -> Vec<Register> {
+        let mut regs = Vec::new();
+        let writes_only = matches!(self.mnemonic, "mov" | "lea" | "pop" | "les" | "lds" | "in");
+        for (i, op) in self.operands.iter().enumerate() {
+            match op {
+                Operand::Register(r) if i > 0 || !writes_only => regs.push(*r),
+                Operand::Memory(m) => {
+                    regs.extend(m.base);
+                    regs.extend(m.index);
+                    if self.mnemonic != "lea" {
+                        regs.push(m.effective_segment());
+                    }
+                }
+                _ => {}
+            }
+        }
+        let implicit: &[Register] = match self.mnemonic {
+            "push" | "pushf" => &[Register::SP, Register::SS],
+            "pop" | "popf" | "ret" | "retf" | "iret" => &[Register::SP, Register::SS],
+            "call" | "int" | "into" | "int3" => &[Register::SP, Register::SS],
+            "movsb" | "movsw" | "cmpsb" | "cmpsw" => &[Register::SI, Register::DI, Register::DS, Register::ES],
+            "lodsb" | "lodsw" => &[Register::SI, Register::DS],
+            "stosb" | "stosw" | "scasb" | "scasw" => &[Register::DI, Register::ES, Register::AX],
+            "mul" | "imul" if self.is_byte_operation() => &[Register::AL],
+            "mul" | "imul" | "div" | "idiv" if self.is_byte_operation() => &[Register::AX],
+            "mul" | "imul" => &[Register::AX],
+            "div" | "idiv" => &[Register::AX, Register::DX],
+            "cbw" | "aaa" | "aas" | "daa" | "das" | "aam" | "aad" | "sahf" => &[Register::AX],
+            "cwd" => &[Register::AX],
+            "xlat" => &[Register::BX, Register::AL, Register::DS],
+            "loop" | "loope" | "loopne" | "jcxz" => &[Register::CX],
+            _ => &[],
+        };
+        regs.extend_from_slice(implicit);
+        if self.prefixes.iter().any(|p| matches!(p, Prefix::Rep | Prefix::Repne)) {
+            regs.push(Register::CX);
+        }
+        let mut unique = Vec::new();
+        for r in regs {
+            if !unique.contains(&r) {
+                unique.push(r);
+            }
+        }
+        unique
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Nasm.format(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{byte_operation::x86_16::parse_byte_code, byte_stream::ByteStream};
+
+    fn read(bytes: &[u8]) -> Vec<Register> {
+        parse_byte_code(&mut ByteStream::new(bytes.to_vec())).unwrap().registers_read()
+    }
+
+    #[test]
+    fn multiplication_reads_ax_but_only_division_reads_dx() {
+        assert_eq!(read(&[0xF6, 0xE3]), vec![Register::BL, Register::AL]); // mul bl
+        assert_eq!(read(&[0xF7, 0xEB]), vec![Register::BX, Register::AX]); // imul bx
+        assert_eq!(read(&[0xF6, 0xF3]), vec![Register::BL, Register::AX]); // div bl
+        assert_eq!(read(&[0xF7, 0xFB]), vec![Register::BX, Register::AX, Register::DX]); // idiv bx
+    }
+}
@@ -1 +1,7 @@
-pub mod x86_16;
\ No newline at end of file
+pub mod cfg;
+pub mod emulator;
+pub mod formatter;
+pub mod instruction;
+pub mod nasm;
+pub mod trace;
+pub mod x86_16;
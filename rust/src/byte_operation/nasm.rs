@@ -0,0 +1,560 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+};
+
+use super::formatter::{Formatter, Nasm};
+use super::instruction::{Displacement, Instruction, Operand, Prefix, Register};
+use crate::{
+    byte_stream::ByteStream,
+    com::{Com, COM_ORIGIN},
+    mz::MZ,
+};
+
+/// <p>Names of the header words from offset 2 up to the end of the fixed MZ header, for the comments next to them.</p>
+const HEADER_FIELDS: [&str; 13] = [
+    "bytes in last page",
+    "page count",
+    "relocation count",
+    "header size in paragraphs",
+    "minimum extra paragraphs",
+    "maximum extra paragraphs",
+    "initial SS",
+    "initial SP",
+    "checksum",
+    "initial IP",
+    "initial CS",
+    "relocation table offset",
+    "overlay number",
+];
+const FIXED_HEADER_LEN: usize = 0x1C;
+const BYTES_PER_LINE: usize = 16;
+const COMMENT_COLUMN: usize = 48;
+
+/// <p>Everything the emitter needs to know about the load image while writing it out.</p>
+struct Listing<'a> {
+    file: &'a [u8],
+    /// Where the load image starts in the file.
+    image_start: usize,
+    /// Image offset of each patched word, with the label naming it.
+    sites: BTreeMap<usize, String>,
+    /// Image offsets that start a line of output, and the labels placed there.
+    labels: BTreeMap<usize, Vec<String>>,
+    /// Image offsets that are safe to put a label at.
+    anchors: BTreeSet<usize>,
+    out: String,
+}
+
+impl Listing<'_> {
+    fn line(&mut self, text: &str, comment: Option<&str>) {
+        let mut line = format!("        {text}");
+        if let Some(comment) = comment {
+            while line.len() < COMMENT_COLUMN {
+                line.push(' ');
+            }
+            line += &format!(" ; {comment}");
+        }
+        self.out += &line;
+        self.out.push('\n');
+    }
+
+    fn label(&mut self, name: &str) {
+        self.out += &format!("{name}:\n");
+    }
+
+    /// <p>The value written in place of a patched segment word: a paragraph count computed from a label when there
+    /// is one at that paragraph, the literal word otherwise.</p>
+    fn segment_word(&self, pos: usize) -> String {
+        let value = u16::from_le_bytes([self.file[pos], self.file[pos + 1]]);
+        let target = value as usize * 16;
+        match self.labels.get(&target) {
+            Some(names) if self.anchors.contains(&target) => format!("dw ({} - image) >> 4", names[0]),
+            _ => format!("dw 0x{value:04X}"),
+        }
+    }
+
+    /// <p>Writes file bytes <code>from..to</code> as data, placing any labels and patched words that fall inside.
+    /// Returns where it stopped, which is past <code>to</code> when a patched word straddles it.</p>
+    fn raw(&mut self, from: usize, to: usize, comment: Option<&str>) -> usize {
+        let mut comment = comment.map(str::to_owned);
+        let mut pending: Vec<u8> = Vec::new();
+        let mut pos = from;
+        let flush = |listing: &mut Self, pending: &mut Vec<u8>, comment: &mut Option<String>| {
+            if !pending.is_empty() {
+                let bytes: Vec<String> = pending.iter().map(|b| format!("0x{b:02X}")).collect();
+                listing.line(&format!("db {}", bytes.join(",")), comment.take().as_deref());
+                pending.clear();
+            }
+        };
+        while pos < to {
+            let image_pos = pos.checked_sub(self.image_start);
+            if let Some(names) = image_pos.and_then(|p| self.labels.get(&p)).filter(|_| pos != from) {
+                let names = names.clone();
+                flush(self, &mut pending, &mut comment);
+                for name in names {
+                    self.label(&name);
+                }
+            }
+            if let Some(site) = image_pos.and_then(|p| self.sites.get(&p)).cloned() {
+                flush(self, &mut pending, &mut comment);
+                self.label(&site);
+                let word = self.segment_word(pos);
+                self.line(&word, comment.take().as_deref());
+                pos += 2;
+                continue;
+            }
+
+            let zeros = self.file[pos..to].iter().take_while(|b| **b == 0).count();
+            let interrupted = (pos + 1..pos + zeros).any(|p| {
+                p.checked_sub(self.image_start)
+                    .is_some_and(|p| self.labels.contains_key(&p) || self.sites.contains_key(&p))
+            });
+            if zeros >= BYTES_PER_LINE && !interrupted {
+                flush(self, &mut pending, &mut comment);
+                self.line(&format!("times {zeros} db 0"), comment.take().as_deref());
+                pos += zeros;
+                continue;
+            }
+
+            pending.push(self.file[pos]);
+            pos += 1;
+            if pending.len() == BYTES_PER_LINE {
+                flush(self, &mut pending, &mut comment);
+            }
+        }
+        flush(self, &mut pending, &mut comment);
+        pos
+    }
+
+    fn place_labels(&mut self, image_pos: usize) {
+        if let Some(names) = self.labels.get(&image_pos).cloned() {
+            for name in names {
+                self.label(&name);
+            }
+        }
+    }
+}
+
+fn fits_signed_byte(v: u16) -> bool {
+    (-128..=127).contains(&(v as i16))
+}
+
+fn is_register(op: Option<&Operand>, reg: Register) -> bool {
+    op == Some(&Operand::Register(reg))
+}
+
+/// <p>Whether NASM, given the instruction's own text, assembles it back to exactly the same bytes.  NASM always
+/// picks the shortest encoding and, between two of equal length, the <code>r/m,reg</code> form, so anything the
+/// original assembler encoded differently has to be written out as bytes.</p>
+fn reproducible(ins: &Instruction) -> bool {
+    if ins.is_data() || ins.mnemonic == "esc" {
+        return false;
+    }
+
+    let memory = ins.operands.iter().find_map(|o| match o {
+        Operand::Memory(m) => Some(m),
+        _ => None,
+    });
+    // NASM writes prefixes in its own order, and only attaches segment overrides to memory operands
+    match ins.prefixes.as_slice() {
+        [] => {}
+        [Prefix::Segment(_)] if memory.is_none() => return false,
+        [_] => {}
+        _ => return false,
+    }
+    if let Some(m) = memory {
+        let addressed = m.base.is_some() || m.index.is_some();
+        match m.displacement {
+            Some(Displacement::Byte(0)) if m.base != Some(Register::BP) || m.index.is_some() => return false,
+            Some(Displacement::Word(w)) if addressed && fits_signed_byte(w) => return false,
+            _ => {}
+        }
+    }
+
+    let modrm = ins.bytes.get(ins.prefixes.len() + 1).copied().unwrap_or(0);
+    let registers = modrm >> 6 == 3;
+    let first = ins.operands.first();
+    let imm = ins.operands.iter().find_map(|o| match o {
+        Operand::Immediate(v) => Some(*v),
+        _ => None,
+    });
+    let reg = (modrm >> 3) & 0b111;
+    match ins.opcode() {
+        // reg,r/m between two registers: NASM uses the r/m,reg opcode
+        0x02 | 0x03 | 0x0A | 0x0B | 0x12 | 0x13 | 0x1A | 0x1B | 0x22 | 0x23 | 0x2A | 0x2B | 0x32 | 0x33 | 0x3A
+        | 0x3B | 0x8A | 0x8B
+            if registers =>
+        {
+            false
+        }
+        0x86 | 0x87 if registers => false,
+        // AX,imm16 where the immediate fits the sign-extended form
+        op if op < 0x40 && op & 0b111 == 5 => !imm.is_some_and(fits_signed_byte),
+        0x82 => false,
+        0x80 => !is_register(first, Register::AL),
+        0x81 => !is_register(first, Register::AX) && !imm.is_some_and(fits_signed_byte),
+        0xF6 if reg == 0 => !is_register(first, Register::AL),
+        0xF7 if reg == 0 => !is_register(first, Register::AX),
+        0xC6 | 0xC7 | 0x8F if registers => false,
+        0xFF if registers && matches!(reg, 0 | 1 | 6) => false,
+        // moves between the accumulator and a direct address have their own opcodes
+        0x88..=0x8B => {
+            let accumulator = ins.operands.iter().any(|o| matches!(o, Operand::Register(Register::AL | Register::AX)));
+            let direct = memory.is_some_and(|m| m.base.is_none() && m.index.is_none());
+            !(accumulator && direct)
+        }
+        _ => true,
+    }
+}
+
+/// <p>The NASM text of a relative branch, aimed at a label and sized so NASM keeps the original encoding.</p>
+fn branch_text(ins: &Instruction, label: &str) -> String {
+    let mut text = Nasm.format(ins);
+    let target = text.rfind(' ').map_or(0, |i| i + 1);
+    text.truncate(target);
+    match ins.opcode() {
+        0xE9 => text = text.replacen("jmp ", "jmp near ", 1),
+        0x70..=0x7F => text += "short ",
+        _ => {}
+    }
+    text + label
+}
+
+/// <p>Writes the load image, starting at <code>listing.image_start</code> in the file, as the instructions of
+/// <code>code</code> where they reassemble exactly and as bytes where they don't.</p>
+fn write_code(listing: &mut Listing, code: &[Instruction]) {
+    let header_len = listing.image_start;
+    let mut pos = 0;
+    let mut i = 0;
+    while i < code.len() {
+        let ins = &code[i];
+        i += 1;
+        if ins.next_address() <= pos {
+            continue;
+        }
+        let start = pos.max(ins.address);
+        if start == ins.address {
+            listing.place_labels(start);
+        }
+
+        let patched = listing.sites.range(ins.address.saturating_sub(1)..ins.next_address()).next().is_some();
+        let text = match ins.branch_target() {
+            Some(target) => listing.labels.get(&target).map(|names| branch_text(ins, &names[0])),
+            None => Some(Nasm.format(ins)),
+        };
+        match text {
+            Some(text) if start == ins.address && !patched && reproducible(ins) => {
+                listing.line(&text, None);
+                pos = ins.next_address();
+            }
+            _ if ins.is_data() => {
+                // runs of undecodable bytes go out together
+                let mut end = ins.next_address();
+                while i < code.len() && code[i].is_data() {
+                    end = code[i].next_address();
+                    i += 1;
+                }
+                pos = listing.raw(header_len + start, header_len + end, None) - header_len;
+            }
+            _ => {
+                let comment = (start == ins.address).then(|| Nasm.format(ins));
+                pos = listing.raw(header_len + start, header_len + ins.next_address(), comment.as_deref())
+                    - header_len;
+            }
+        }
+    }
+}
+
+/// <p>Writes a plain MZ program as NASM source that assembles (<code>nasm -f bin</code>) back into the identical
+/// file.  Branch targets inside the load image get labels, bytes no path from the entry point reaches become
+/// <code>db</code>, and every relocation table entry points at a label on the word it patches, with that word
+/// computed from the label of the paragraph it refers to where there is one.  Instructions NASM would encode
+/// differently from the original, such as <code>mov ax,bx</code> through opcode 8B, are kept as bytes with the
+/// instruction in a comment.</p>
+pub fn mz_to_nasm(mz: &MZ, bst: &ByteStream) -> String {
+    let file = bst.as_slice();
+    let Range { start: header_len, end: image_end } = mz.load_module_range(file.len());
+    let image = &file[header_len..image_end];
+    let code = mz.disassemble(bst).into_instructions();
+
+    let mut listing = Listing {
+        file,
+        image_start: header_len,
+        sites: BTreeMap::new(),
+        labels: BTreeMap::new(),
+        anchors: code.iter().map(|ins| ins.address).collect(),
+        out: String::new(),
+    };
+
+    // patched words that fit in the image and don't overlap each other
+    let mut reloc_labels = Vec::new();
+    for (i, reloc) in mz.relocation_tables.iter().enumerate() {
+        let site = reloc.segment as usize * 16 + reloc.offset as usize;
+        let clash = listing.sites.range(site.saturating_sub(1)..site + 2).any(|(s, _)| *s != site);
+        if site + 2 <= image.len() && !clash {
+            let name = listing.sites.entry(site).or_insert_with(|| format!("reloc_{i}")).clone();
+            listing.anchors.remove(&(site + 1));
+            reloc_labels.push(Some(name));
+        } else {
+            reloc_labels.push(None);
+        }
+    }
+
+    for ins in &code {
+        if let Some(target) = ins.branch_target().filter(|t| listing.anchors.contains(t)) {
+            listing.labels.entry(target).or_default().push(format!("loc_{target:05X}"));
+        }
+    }
+    for &site in listing.sites.keys() {
+        let value = u16::from_le_bytes([image[site], image[site + 1]]) as usize * 16;
+        if listing.anchors.contains(&value) {
+            let names = listing.labels.entry(value).or_default();
+            let name = format!("seg_{:04X}", value / 16);
+            if !names.contains(&name) {
+                names.insert(0, name);
+            }
+        }
+    }
+    for names in listing.labels.values_mut() {
+        names.dedup();
+    }
+    let entry = mz.entry_point();
+    if listing.anchors.contains(&entry) {
+        listing.labels.entry(entry).or_default().push("start".to_owned());
+    }
+
+    let table_start = mz.relocation_table_offset as usize;
+    let table_end = table_start + mz.relocation_tables.len() * 4;
+    let symbolic_table = header_len >= FIXED_HEADER_LEN
+        && table_start >= FIXED_HEADER_LEN
+        && table_end <= header_len
+        && mz.relocation_tables.len() == mz.relocation_table_entry_count as usize;
+
+    listing.out += "; reassemble with: nasm -f bin -o program.exe program.asm\n";
+    listing.out += "        bits 16\n";
+    listing.out += "        cpu 8086\n\n";
+    listing.label("header");
+    if header_len < FIXED_HEADER_LEN {
+        listing.raw(0, header_len, Some("header"));
+    } else {
+        let magic = String::from_utf8_lossy(&file[..2]).into_owned();
+        listing.line(&format!("db '{magic}'"), Some("signature"));
+        let size = image_end;
+        for (i, name) in HEADER_FIELDS.iter().enumerate() {
+            let pos = 2 + i * 2;
+            let value = u16::from_le_bytes([file[pos], file[pos + 1]]);
+            let text = match pos {
+                0x02 if size % 512 == value as usize => "dw (image_end - header) % 512".to_owned(),
+                0x04 if size.div_ceil(512) == value as usize => "dw (image_end - header + 511) / 512".to_owned(),
+                0x06 if symbolic_table => "dw (relocations_end - relocations) / 4".to_owned(),
+                0x08 if header_len == value as usize * 16 => "dw (image - header) / 16".to_owned(),
+                0x18 if symbolic_table => "dw relocations - header".to_owned(),
+                _ => format!("dw 0x{value:04X}"),
+            };
+            listing.line(&text, Some(name));
+        }
+
+        if symbolic_table {
+            listing.raw(FIXED_HEADER_LEN, table_start, None);
+            listing.label("relocations");
+            for (reloc, label) in mz.relocation_tables.iter().zip(&reloc_labels) {
+                let base = match reloc.segment {
+                    0 => String::new(),
+                    segment => format!(" - 0x{:X}", segment as usize * 16),
+                };
+                match label {
+                    Some(label) => {
+                        listing.line(&format!("dw {label} - image{base}, 0x{:04X}", reloc.segment), None)
+                    }
+                    None => listing.line(&format!("dw 0x{:04X}, 0x{:04X}", reloc.offset, reloc.segment), None),
+                }
+            }
+            listing.label("relocations_end");
+            listing.raw(table_end, header_len, None);
+        } else {
+            listing.raw(FIXED_HEADER_LEN, header_len, None);
+        }
+    }
+
+    listing.out.push('\n');
+    listing.label("image");
+    write_code(&mut listing, &code);
+    listing.label("image_end");
+
+    if image_end < file.len() {
+        listing.out.push('\n');
+        listing.out += "; data past the load image\n";
+        listing.raw(image_end, file.len(), None);
+    }
+    listing.out
+}
+
+/// <p>Writes a .COM program as NASM source that assembles back into the identical file, the same way
+/// <code>mz_to_nasm</code> writes the load image of an MZ program.</p>
+pub fn com_to_nasm(bytes: &[u8]) -> String {
+    let code = Com::disassemble(bytes).into_instructions();
+    let mut listing = Listing {
+        file: bytes,
+        image_start: 0,
+        sites: BTreeMap::new(),
+        labels: BTreeMap::new(),
+        anchors: code.iter().map(|ins| ins.address).collect(),
+        out: String::new(),
+    };
+    for ins in &code {
+        if let Some(target) = ins.branch_target().filter(|t| listing.anchors.contains(t)) {
+            listing.labels.entry(target).or_default().push(format!("loc_{target:05X}"));
+        }
+    }
+    for names in listing.labels.values_mut() {
+        names.dedup();
+    }
+
+    listing.out += "; reassemble with: nasm -f bin -o program.com program.asm\n";
+    listing.out += "        bits 16\n";
+    listing.out += "        cpu 8086\n";
+    listing.out += &format!("        org 0x{COM_ORIGIN:X}\n\n");
+    listing.label("start");
+    write_code(&mut listing, &code);
+    listing.out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process::Command};
+
+    use super::*;
+
+    /// <p>Code NASM encodes the same way, an encoding it doesn't (8B C3), branches both ways and a byte nothing
+    /// reaches.</p>
+    const PROGRAM: &[u8] = &[
+        0xB8, 0x34, 0x12, // mov ax,0x1234
+        0x8B, 0xC3, //       mov ax,bx
+        0x03, 0x46, 0x00, // add ax,[bp+0x0]
+        0x74, 0x02, //       jz 0xc
+        0xEB, 0xFE, //       jmp 0xa
+        0xE8, 0x03, 0x00, // call 0x12
+        0xCD, 0x20, //       int 0x20
+        0x90, //             never reached
+        0xC3, //             ret
+    ];
+
+    /// <p>A three-paragraph header with two relocations, in front of a load module that starts with a string and
+    /// has its entry point at 0001:0000.  The code prints the string, calls a subroutine and exits; the far pointer
+    /// after it is the second relocation, which refers to the entry point's paragraph.</p>
+    fn mz_program() -> Vec<u8> {
+        let mut file = vec![0; 0x60];
+        for (at, word) in [
+            (0x00, 0x5A4D),
+            (0x02, 0x0060),
+            (0x04, 1),
+            (0x06, 2),
+            (0x08, 3),
+            (0x0C, 0xFFFF),
+            (0x0E, 0x0003),
+            (0x10, 0x0100),
+            (0x16, 0x0001),
+            (0x18, 0x001C),
+            (0x1C, 0x0001),
+            (0x1E, 0x0001),
+            (0x20, 0x0007),
+            (0x22, 0x0002),
+        ] {
+            file[at..at + 2].copy_from_slice(&u16::to_le_bytes(word));
+        }
+        file[0x30..0x36].copy_from_slice(b"Hello$");
+        file[0x40..0x59].copy_from_slice(&[
+            0xB8, 0x00, 0x00, // 10 mov ax,seg string
+            0x8E, 0xD8, //       13 mov ds,ax
+            0xBA, 0x00, 0x00, // 15 mov dx,0x0
+            0xB4, 0x09, //       18 mov ah,0x9
+            0xCD, 0x21, //       1a int 0x21
+            0xE8, 0x04, 0x00, // 1c call 0x23
+            0xB4, 0x4C, //       1f mov ah,0x4c
+            0xCD, 0x21, //       21 int 0x21
+            0x74, 0x01, //       23 jz 0x26
+            0x90, //             25 nop
+            0xC3, //             26 ret
+            0x01, 0x00, //       27 dw seg start
+        ]);
+        file
+    }
+
+    /// <p>Assembles <code>source</code> with NASM, which has to be installed.</p>
+    fn assemble(source: &str) -> Vec<u8> {
+        let stem = std::env::temp_dir().join(format!("jj-exe-{}", std::process::id()));
+        let (asm, bin) = (stem.with_extension("asm"), stem.with_extension("bin"));
+        fs::write(&asm, source).unwrap();
+        let status = Command::new("nasm").args(["-f", "bin", "-o"]).arg(&bin).arg(&asm).status();
+        let status = status.expect("nasm isn't installed");
+        assert!(status.success(), "nasm failed on:\n{source}");
+        let bytes = fs::read(&bin).unwrap();
+        let _ = (fs::remove_file(asm), fs::remove_file(bin));
+        bytes
+    }
+
+    fn assert_lines(source: &str, lines: &[&str]) {
+        for line in lines {
+            assert!(source.lines().any(|l| l == *line), "no {line:?} in:\n{source}");
+        }
+    }
+
+    #[test]
+    fn com_source_keeps_what_nasm_would_change() {
+        assert_lines(
+            &com_to_nasm(PROGRAM),
+            &[
+                "        org 0x100",
+                "        mov ax,0x1234",
+                "        db 0x8B,0xC3                             ; mov ax,bx",
+                "        jz short loc_0000C",
+                "loc_0000A:",
+                "        call loc_00012",
+                "        db 0x90",
+            ],
+        );
+    }
+
+    #[test]
+    #[ignore = "needs nasm"]
+    fn com_source_reassembles() {
+        assert_eq!(assemble(&com_to_nasm(PROGRAM)), PROGRAM);
+    }
+
+    fn mz_source() -> String {
+        let mut bst = ByteStream::new(mz_program());
+        let mz = MZ::read(&mut bst).unwrap();
+        mz_to_nasm(&mz, &bst)
+    }
+
+    #[test]
+    fn mz_source_refers_to_labels() {
+        assert_lines(
+            &mz_source(),
+            &[
+                "        dw (image_end - header) % 512            ; bytes in last page",
+                "        dw (relocations_end - relocations) / 4   ; relocation count",
+                "        dw 0x0000                                ; initial IP",
+                "        dw 0x0001                                ; initial CS",
+                "        dw relocations - header                  ; relocation table offset",
+                "        dw reloc_0 - image - 0x10, 0x0001",
+                "        dw reloc_1 - image - 0x20, 0x0002",
+                "        db 0x48,0x65,0x6C,0x6C,0x6F,0x24,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00",
+                "seg_0001:",
+                "start:",
+                "        db 0xB8                                  ; mov ax,0x0",
+                "        dw (seg_0000 - image) >> 4",
+                "        call loc_00023",
+                "loc_00023:",
+                "        jz short loc_00026",
+                "        dw (seg_0001 - image) >> 4",
+            ],
+        );
+    }
+
+    #[test]
+    #[ignore = "needs nasm"]
+    fn mz_source_reassembles() {
+        assert_eq!(assemble(&mz_source()), mz_program());
+    }
+}
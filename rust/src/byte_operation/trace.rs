@@ -0,0 +1,180 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::instruction::{Instruction, Operand, Register};
+use super::x86_16::{parse_byte_code, MAX_INSTRUCTION_TAIL};
+use crate::byte_stream::ByteStream;
+
+/// <p>A run of the traced buffer: either an instruction some path reached, or bytes none did.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Chunk {
+    Code(Instruction),
+    Data { address: usize, bytes: Vec<u8> },
+}
+
+impl Chunk {
+    pub fn address(&self) -> usize {
+        match self {
+            Chunk::Code(ins) => ins.address,
+            Chunk::Data { address, .. } => *address,
+        }
+    }
+}
+
+/// <p>The result of a recursive traversal, covering every byte of the buffer in address order.</p>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Disassembly {
+    pub chunks: Vec<Chunk>,
+}
+
+impl Disassembly {
+    /// <p>The reached instructions, in address order.</p>
+    pub fn code(&self) -> impl Iterator<Item = &Instruction> {
+        self.chunks.iter().filter_map(|c| match c {
+            Chunk::Code(ins) => Some(ins),
+            Chunk::Data { .. } => None,
+        })
+    }
+
+    /// <p>Flattens the traversal into the same shape <code>parse_code</code> returns, with every unreached byte as a
+    /// one-byte <code>db</code>.</p>
+    pub fn into_instructions(self) -> Vec<Instruction> {
+        let mut code = Vec::new();
+        for chunk in self.chunks {
+            match chunk {
+                Chunk::Code(ins) => code.push(ins),
+                Chunk::Data { address, bytes } => {
+                    code.extend(bytes.iter().enumerate().map(|(i, b)| Instruction::data(address + i, *b)))
+                }
+            }
+        }
+        code
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::Code(ins) => {
+                    let hex: Vec<String> = ins.bytes.iter().map(|b| format!("{b:02X}")).collect();
+                    writeln!(f, "{:05X}  {:<20}{ins}", ins.address, hex.join(" "))?;
+                }
+                Chunk::Data { address, bytes } => {
+                    writeln!(f, "; ---- {} byte(s) not reached ----", bytes.len())?;
+                    for (i, line) in bytes.chunks(16).enumerate() {
+                        let hex: Vec<String> = line.iter().map(|b| format!("0x{b:02X}")).collect();
+                        writeln!(f, "{:05X}  db {}", address + i * 16, hex.join(","))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// <p>Whether execution never continues past this instruction: returns, jumps, and the DOS ways of ending a program
+/// (<code>int 20h</code>, and <code>int 21h</code> right after AH is set to 4Ch).</p>
+pub(crate) fn ends_path(ins: &Instruction, previous: Option<&Instruction>) -> bool {
+    if ins.is_return() || ins.is_unconditional_jump() {
+        return true;
+    }
+    match (ins.mnemonic, ins.operands.first()) {
+        ("int", Some(Operand::Immediate(0x20))) => true,
+        ("int", Some(Operand::Immediate(0x21))) => previous.is_some_and(|p| {
+            p.mnemonic == "mov"
+                && match p.operands.as_slice() {
+                    [Operand::Register(Register::AH), Operand::Immediate(0x4C)] => true,
+                    [Operand::Register(Register::AX), Operand::Immediate(v)] => v >> 8 == 0x4C,
+                    _ => false,
+                }
+        }),
+        _ => false,
+    }
+}
+
+/// <p>Disassembles <code>bytes</code> by following control flow from each entry point instead of sweeping
+/// linearly, so data mixed into code doesn't throw the decoding out of step.  Calls, jumps, conditional jumps and
+/// loops are followed to their targets; returns, unconditional jumps and program exits end a path.  Indirect
+/// branches can't be followed.</p>
+/// <p>With <code>follow_far</code>, direct far calls and jumps are followed too, taking <code>seg:off</code> as
+/// paragraphs from the start of <code>bytes</code>, which is how they are stored in an MZ load module.  NE code has
+/// fixups there instead, so it should be traced without.</p>
+/// <p>Bytes no path reaches, and anything that would decode overlapping an already traced instruction, come out as
+/// <code>Chunk::Data</code>.</p>
+pub fn trace_code(bytes: &[u8], entries: &[usize], follow_far: bool) -> Disassembly {
+    let mut padded = bytes.to_vec();
+    padded.extend_from_slice(&[0; MAX_INSTRUCTION_TAIL]);
+    let mut bst = ByteStream::new(padded);
+
+    let mut decoded: BTreeMap<usize, Instruction> = BTreeMap::new();
+    let mut covered = vec![false; bytes.len()];
+    let mut pending: Vec<usize> = entries.iter().rev().copied().collect();
+
+    while let Some(mut address) = pending.pop() {
+        let mut previous: Option<Instruction> = None;
+        while address < bytes.len() && !covered[address] {
+            bst.pos = address;
+            let Ok(ins) = parse_byte_code(&mut bst) else { break };
+            let end = ins.next_address();
+            if ins.is_data() || end > bytes.len() || covered[address..end].contains(&true) {
+                break;
+            }
+            covered[address..end].fill(true);
+
+            pending.extend(ins.branch_target());
+            if let (true, Some((segment, offset))) = (follow_far, ins.far_target()) {
+                pending.push(segment as usize * 16 + offset as usize);
+            }
+
+            address = end;
+            let done = ends_path(&ins, previous.as_ref());
+            decoded.insert(ins.address, ins.clone());
+            if done {
+                break;
+            }
+            previous = Some(ins);
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut address = 0;
+    for (start, ins) in decoded {
+        if start > address {
+            chunks.push(Chunk::Data { address, bytes: bytes[address..start].to_vec() });
+        }
+        address = ins.next_address();
+        chunks.push(Chunk::Code(ins));
+    }
+    if address < bytes.len() {
+        chunks.push(Chunk::Data { address, bytes: bytes[address..].to_vec() });
+    }
+    Disassembly { chunks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(disassembly: &Disassembly) -> Vec<(usize, bool)> {
+        disassembly.chunks.iter().map(|c| (c.address(), matches!(c, Chunk::Code(_)))).collect()
+    }
+
+    #[test]
+    fn data_between_code_stays_data() {
+        // jmp short 5 / "abc" / mov ah,4c / int 21 / "xy"
+        let bytes = [0xEB, 0x03, b'a', b'b', b'c', 0xB4, 0x4C, 0xCD, 0x21, b'x', b'y'];
+        let disassembly = trace_code(&bytes, &[0], false);
+        assert_eq!(addresses(&disassembly), [(0, true), (2, false), (5, true), (7, true), (9, false)]);
+        assert_eq!(disassembly.code().count(), 3);
+    }
+
+    #[test]
+    fn far_calls_are_followed_as_paragraphs() {
+        // call 0001:0000 / ret, with a ret at paragraph 1
+        let mut bytes = vec![0x9A, 0x00, 0x00, 0x01, 0x00, 0xC3];
+        bytes.resize(0x10, 0);
+        bytes.push(0xC3);
+        assert_eq!(addresses(&trace_code(&bytes, &[0], true)), [(0, true), (5, true), (6, false), (0x10, true)]);
+        assert_eq!(addresses(&trace_code(&bytes, &[0], false)), [(0, true), (5, true), (6, false)]);
+    }
+}
@@ -1,764 +1,1049 @@
-use std::{io::Error, sync::RwLock};
-
 use crate::{
     apis::{dos::dos_op_cd, API},
     byte_stream::ByteStream,
+    error::Error,
     executable::InteruptChange,
+    memory::{self, DEFAULT_PSP_SEGMENT},
 };
 
+use super::instruction::{Displacement, Instruction, Memory, Operand, OperandSize, Prefix, Register};
+
 const OPS: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
-const REG_NAMES: [&str; 8] = ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di"];
-const RM_NAMES: [&str; 8] = ["bx+si", "bx+di", "bp+si", "bp+di", "si", "di", "bp", "bx"];
-const SEG_REG_NAMES: [&str; 4] = ["es", "cs", "ss", "ds"];
+const JCC_NAMES: [&str; 16] = [
+    "jo", "jno", "jb", "jnb", "jz", "jnz", "jbe", "ja", "js", "jns", "jp", "jnp", "jl", "jnl", "jle", "jg",
+];
+const SHIFT_OPS: [&str; 8] = ["rol", "ror", "rcl", "rcr", "shl", "shr", "", "sar"];
+const GRP3_OPS: [&str; 8] = ["test", "", "not", "neg", "mul", "imul", "div", "idiv"];
+const RM_REGS: [(Option<Register>, Option<Register>); 8] = [
+    (Some(Register::BX), Some(Register::SI)),
+    (Some(Register::BX), Some(Register::DI)),
+    (Some(Register::BP), Some(Register::SI)),
+    (Some(Register::BP), Some(Register::DI)),
+    (None, Some(Register::SI)),
+    (None, Some(Register::DI)),
+    (Some(Register::BP), None),
+    (Some(Register::BX), None),
+];
+
+/// <p>The most bytes a single instruction can take after its prefixes (opcode, ModR/M, 16-bit displacement, 16-bit
+/// immediate).</p>
+pub(crate) const MAX_INSTRUCTION_TAIL: usize = 6;
+
+fn size_of(word: bool) -> OperandSize {
+    if word {
+        OperandSize::Word
+    } else {
+        OperandSize::Byte
+    }
+}
 
-fn modrm_byte_handling(bst: &mut ByteStream) -> (u8, u8, u8, u8, u16, Option<u16>, Option<String>) {
-    let mod_byte = bst.read_byte();
+/// <p>Reads a ModR/M byte, returning the mod and reg fields and the r/m operand.  A register r/m operand is a word
+/// register unless <code>size</code> is <code>Byte</code>.</p>
+fn modrm_byte_handling(bst: &mut ByteStream, size: OperandSize) -> Result<(u8, u8, Operand), Error> {
+    let mod_byte = bst.read_byte()?;
     let mod_s = mod_byte >> 6;
     let reg = (mod_byte >> 3) & 0b111;
     let rm = mod_byte & 0b111;
 
-    let mut displacement = 0;
-    let mut v = None;
-    let mut v_s = None;
-
-    if mod_s == 0 || mod_s == 1 || mod_s == 2 {
-        match mod_s {
-            0 => {
-                if rm == 6 {
-                    v = Some({
-                        displacement = bst.read_word();
-                        displacement
-                    });
-                    v_s = Some(format!("[0x{displacement:X}]"));
-                } else {
-                    v = Some(match rm {
-                        0 => get_bx() + *SI.read().unwrap(),
-                        1 => get_bx() + *DI.read().unwrap(),
-                        2 => *BP.read().unwrap() + *SI.read().unwrap(),
-                        3 => *BP.read().unwrap() + *DI.read().unwrap(),
-                        4 => *SI.read().unwrap(),
-                        5 => *DI.read().unwrap(),
-                        7 => get_bx(),
-                        _ => panic!(), // literally impossible
-                    });
-                    v_s = Some(format!("[{}]", RM_NAMES[rm as usize]));
-                }
-            }
-            1 | 2 => {
-                displacement = if mod_s == 1 {
-                    bst.read_byte() as u16
-                } else {
-                    bst.read_word()
-                };
-                v = Some(
-                    match rm {
-                        0 => get_bx() + *SI.read().unwrap(),
-                        1 => get_bx() + *DI.read().unwrap(),
-                        2 => *BP.read().unwrap() + *SI.read().unwrap(),
-                        3 => *BP.read().unwrap() + *DI.read().unwrap(),
-                        4 => *SI.read().unwrap(),
-                        5 => *DI.read().unwrap(),
-                        6 => *BP.read().unwrap(),
-                        7 => get_bx(),
-                        _ => panic!(), // literally impossible
-                    } + displacement,
-                );
-                v_s = Some(format!("[{}+0x{displacement:X}]", RM_NAMES[rm as usize]));
-            }
-            _ => panic!(), // literally impossible
+    let operand = match mod_s {
+        0 if rm == 6 => Operand::Memory(Memory {
+            segment: None,
+            base: None,
+            index: None,
+            displacement: Some(Displacement::Word(bst.read_word()?)),
+            size,
+        }),
+        0..=2 => {
+            let (base, index) = RM_REGS[rm as usize];
+            let displacement = match mod_s {
+                1 => Some(Displacement::Byte(bst.read_sbyte()?)),
+                2 => Some(Displacement::Word(bst.read_word()?)),
+                _ => None,
+            };
+            Operand::Memory(Memory {
+                segment: None,
+                base,
+                index,
+                displacement,
+                size,
+            })
         }
-    }
+        _ => Operand::Register(Register::general(rm, size != OperandSize::Byte)),
+    };
 
-    (mod_byte, mod_s, reg, rm, displacement, v, v_s)
-}
-fn get_parsed_reg(reg: &String) -> Result<u16, Error> {
-    match reg.as_str() {
-        "ax" => Ok(get_ax()),
-        "bx" => Ok(get_bx()),
-        "cx" => Ok(get_cx()),
-        "dx" => Ok(get_dx()),
-        "sp" => Ok(*SP.read().unwrap()),
-        "bp" => Ok(*BP.read().unwrap()),
-        "si" => Ok(*SI.read().unwrap()),
-        "di" => Ok(*DI.read().unwrap()),
-        &_ => Err(Error::last_os_error()),
-    }
-}
-fn set_parsed_reg(reg: &String, v: u16) -> Result<(), Error> {
-    match reg.as_str() {
-        "ax" => Ok(set_ax(v)),
-        "bx" => Ok(set_bx(v)),
-        "cx" => Ok(set_cx(v)),
-        "dx" => Ok(set_dx(v)),
-        "sp" => Ok(*SP.write().unwrap() = v),
-        "bp" => Ok(*BP.write().unwrap() = v),
-        "si" => Ok(*SI.write().unwrap() = v),
-        "di" => Ok(*DI.write().unwrap() = v),
-        &_ => Err(Error::last_os_error()),
-    }
+    Ok((mod_s, reg, operand))
 }
 
-fn get_parsed_seg_reg(seg_reg: &String) -> Result<u16, Error> {
-    match seg_reg.as_str() {
-        "es" => Ok(*ES.read().unwrap()),
-        "cs" => Ok(*CS.read().unwrap()),
-        "ss" => Ok(*SS.read().unwrap()),
-        "ds" => Ok(*DS.read().unwrap()),
-        &_ => Err(Error::last_os_error()),
-    }
+fn reg_operand(reg: u8, word: bool) -> Operand {
+    Operand::Register(Register::general(reg, word))
 }
-fn set_parsed_seg_reg(seg_reg: &String, v: u16) -> Result<(), Error> {
-    match seg_reg.as_str() {
-        "es" => Ok(*ES.write().unwrap() = v),
-        "cs" => Ok(*CS.write().unwrap() = v),
-        "ss" => Ok(*SS.write().unwrap() = v),
-        "ds" => Ok(*DS.write().unwrap() = v),
-        &_ => Err(Error::last_os_error()),
+
+fn imm_operand(bst: &mut ByteStream, word: bool) -> Result<Operand, Error> {
+    if word {
+        Ok(Operand::Immediate(bst.read_word()?))
+    } else {
+        Ok(Operand::Immediate(bst.read_byte()? as u16))
     }
 }
 
-pub fn op_00() -> String {
-    "nop".to_owned()
+fn rel_operand(bst: &mut ByteStream, word: bool) -> Result<Operand, Error> {
+    let disp = if word {
+        bst.read_sword()? as isize
+    } else {
+        bst.read_sbyte()? as isize
+    };
+    Ok(Operand::Relative(bst.pos.wrapping_add_signed(disp)))
 }
-// 01-0d
-pub fn op_0e(execute: bool) -> String {
-    if execute {
-        STACK.write().unwrap().push(*CS.read().unwrap());
-    }
-    "push cs".to_owned()
+
+fn peek_reg(bst: &ByteStream) -> Result<u8, Error> {
+    Ok((bst.peek_byte()? >> 3) & 0b111)
 }
-// 0f-1e
-pub fn op_1f(execute: bool) -> String {
-    if execute {
-        *DS.write().unwrap() = STACK.write().unwrap().pop().unwrap();
-    }
-    "pop ds".to_owned()
+fn peek_mod(bst: &ByteStream) -> Result<u8, Error> {
+    Ok(bst.peek_byte()? >> 6)
 }
-// 20-32
-pub fn op_33(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, mod_s, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-    let destreg = REG_NAMES[reg as usize].to_owned();
-
-    if execute {
-        let regv = get_parsed_reg(&destreg).unwrap();
-        if v.is_some() {
-            let index =
-                ((if rm == 0 || rm == 1 || rm == 4 || rm == 5 || (rm == 6 && mod_s == 0) || rm == 7
-                {
-                    *DS.read().unwrap()
-                } else if rm == 2 || rm == 3 || (rm == 6 && mod_s != 0) {
-                    *SS.read().unwrap()
-                } else {
-                    panic!()
-                } as u32)
-                    << 4)
-                    + v.unwrap() as u32;
-            let r_bst = bst.read_word_at(index as usize);
-            set_parsed_reg(&destreg, regv ^ r_bst).unwrap();
-        } else {
-            let reg_name = REG_NAMES[rm as usize].to_owned();
-            set_parsed_reg(&destreg, regv ^ get_parsed_reg(&reg_name).unwrap()).unwrap();
+
+type Decoded = (&'static str, Vec<Operand>);
+
+/// <p>00-3d: <code>add</code>, <code>or</code>, <code>adc</code>, <code>sbb</code>, <code>and</code>, <code>sub</code>,
+/// <code>xor</code> and <code>cmp</code> in their six encodings.</p>
+fn parse_alu(bst: &mut ByteStream, byte: u8) -> Result<Decoded, Error> {
+    let mnemonic = OPS[(byte >> 3) as usize];
+    let word = byte & 1 == 1;
+    Ok(match byte & 0b111 {
+        0 | 1 => {
+            let (_, reg, rm) = modrm_byte_handling(bst, size_of(word))?;
+            (mnemonic, vec![rm, reg_operand(reg, word)])
         }
+        2 | 3 => {
+            let (_, reg, rm) = modrm_byte_handling(bst, size_of(word))?;
+            (mnemonic, vec![reg_operand(reg, word), rm])
+        }
+        _ => (mnemonic, vec![reg_operand(0, word), imm_operand(bst, word)?]),
+    })
+}
+
+/// <p>A ModR/M instruction with a general register operand, such as <code>test</code>, <code>xchg</code> and
+/// <code>mov</code>.</p>
+fn parse_rm_reg(bst: &mut ByteStream, mnemonic: &'static str, word: bool, reg_first: bool) -> Result<Decoded, Error> {
+    let (_, reg, rm) = modrm_byte_handling(bst, size_of(word))?;
+    if reg_first {
+        Ok((mnemonic, vec![reg_operand(reg, word), rm]))
+    } else {
+        Ok((mnemonic, vec![rm, reg_operand(reg, word)]))
     }
+}
 
-    format!(
-        "xor {destreg},{}",
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
+fn parse_grp1(bst: &mut ByteStream, byte: u8) -> Result<Decoded, Error> {
+    let (_, reg, rm) = modrm_byte_handling(bst, size_of(byte & 1 == 1))?;
+    let imm = match byte {
+        0x83 => Operand::SignExtended(bst.read_sbyte()?),
+        _ => imm_operand(bst, byte == 0x81)?,
+    };
+    Ok((OPS[reg as usize], vec![rm, imm]))
 }
-// 34-4f
-pub fn op_50(execute: bool) -> String {
-    if execute {
-        STACK.write().unwrap().push(get_ax());
+
+fn parse_grp2(bst: &mut ByteStream, byte: u8) -> Result<Option<Decoded>, Error> {
+    if peek_reg(bst)? == 6 {
+        return Ok(None);
     }
-    "push ax".to_owned()
+    let (_, reg, rm) = modrm_byte_handling(bst, size_of(byte & 1 == 1))?;
+    let count = if byte < 0xD2 {
+        Operand::Immediate(1)
+    } else {
+        Operand::Register(Register::CL)
+    };
+    Ok(Some((SHIFT_OPS[reg as usize], vec![rm, count])))
 }
-// 51-54
-pub fn op_55(execute: bool) -> String {
-    if execute {
-        STACK.write().unwrap().push(*BP.read().unwrap());
+
+fn parse_grp3(bst: &mut ByteStream, word: bool) -> Result<Option<Decoded>, Error> {
+    if peek_reg(bst)? == 1 {
+        return Ok(None);
+    }
+    let (_, reg, rm) = modrm_byte_handling(bst, size_of(word))?;
+    if reg == 0 {
+        Ok(Some(("test", vec![rm, imm_operand(bst, word)?])))
+    } else {
+        Ok(Some((GRP3_OPS[reg as usize], vec![rm])))
     }
-    "push bp".to_owned()
 }
-pub fn op_56(execute: bool) -> String {
-    if execute {
-        STACK.write().unwrap().push(*SI.read().unwrap());
+
+fn parse_grp4_5(bst: &mut ByteStream, byte: u8) -> Result<Option<Decoded>, Error> {
+    let reg = peek_reg(bst)?;
+    let far = reg == 3 || reg == 5;
+    if (byte == 0xFE && reg > 1) || reg == 7 || (far && peek_mod(bst)? == 3) {
+        return Ok(None);
     }
-    "push si".to_owned()
+    let size = if far { OperandSize::Far } else { size_of(byte == 0xFF) };
+    let (_, reg, rm) = modrm_byte_handling(bst, size)?;
+    let mnemonic = match reg {
+        0 => "inc",
+        1 => "dec",
+        2 | 3 => "call",
+        4 | 5 => "jmp",
+        _ => "push",
+    };
+    Ok(Some((mnemonic, vec![rm])))
 }
-// 57-5c
-pub fn op_5d(execute: bool) -> String {
-    if execute {
-        *BP.write().unwrap() = STACK.write().unwrap().pop().unwrap();
-    }
-    "pop bp".to_owned()
-}
-// 5e-80
-pub fn op_81(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, _, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-
-    let immediate = bst.read_word();
-    let mnemonic = OPS[reg as usize];
-
-    if execute {
-        match mnemonic {
-            "sub" => {
-                if v.is_some() {
-                    let vt = bst.read_word_at(v.unwrap() as usize);
-                    bst.replace_word(v.unwrap() as usize, vt.wrapping_sub(immediate));
-                } else {
-                    let sregv = REG_NAMES[rm as usize].to_owned();
-                    let regv = get_parsed_reg(&sregv).unwrap();
-                    set_parsed_reg(&sregv, regv.wrapping_sub(immediate)).unwrap();
-                }
+
+fn parse_opcode(bst: &mut ByteStream, byte: u8) -> Result<Option<Decoded>, Error> {
+    let none = Vec::new;
+    Ok(Some(match byte {
+        0x00..=0x3F if byte & 0b111 < 6 => parse_alu(bst, byte)?,
+        0x06 | 0x0E | 0x16 | 0x1E => ("push", vec![Operand::Register(Register::segment(byte >> 3))]),
+        0x07 | 0x17 | 0x1F => ("pop", vec![Operand::Register(Register::segment(byte >> 3))]),
+        0x27 => ("daa", none()),
+        0x2F => ("das", none()),
+        0x37 => ("aaa", none()),
+        0x3F => ("aas", none()),
+        0x40..=0x47 => ("inc", vec![reg_operand(byte, true)]),
+        0x48..=0x4F => ("dec", vec![reg_operand(byte, true)]),
+        0x50..=0x57 => ("push", vec![reg_operand(byte, true)]),
+        0x58..=0x5F => ("pop", vec![reg_operand(byte, true)]),
+        0x70..=0x7F => (JCC_NAMES[(byte & 0xF) as usize], vec![rel_operand(bst, false)?]),
+        0x80..=0x83 => parse_grp1(bst, byte)?,
+        0x84 | 0x85 => parse_rm_reg(bst, "test", byte & 1 == 1, false)?,
+        0x86 | 0x87 => parse_rm_reg(bst, "xchg", byte & 1 == 1, false)?,
+        0x88 | 0x89 => parse_rm_reg(bst, "mov", byte & 1 == 1, false)?,
+        0x8A | 0x8B => parse_rm_reg(bst, "mov", byte & 1 == 1, true)?,
+        0x8C | 0x8E if peek_reg(bst)? > 3 => return Ok(None),
+        0x8C | 0x8E => {
+            let (_, reg, rm) = modrm_byte_handling(bst, OperandSize::Word)?;
+            let seg_reg = Operand::Register(Register::segment(reg));
+            if byte == 0x8C {
+                ("mov", vec![rm, seg_reg])
+            } else {
+                ("mov", vec![seg_reg, rm])
             }
-            &_ => panic!(),
         }
-    }
+        0x8D if peek_mod(bst)? == 3 => return Ok(None),
+        0x8D => {
+            let (_, reg, rm) = modrm_byte_handling(bst, OperandSize::None)?;
+            ("lea", vec![reg_operand(reg, true), rm])
+        }
+        0x8F if peek_reg(bst)? != 0 => return Ok(None),
+        0x8F => ("pop", vec![modrm_byte_handling(bst, OperandSize::Word)?.2]),
+        0x90 => ("nop", none()),
+        0x91..=0x97 => ("xchg", vec![Operand::Register(Register::AX), reg_operand(byte, true)]),
+        0x98 => ("cbw", none()),
+        0x99 => ("cwd", none()),
+        0x9A | 0xEA => {
+            let offset = bst.read_word()?;
+            let segment = bst.read_word()?;
+            let mnemonic = if byte == 0x9A { "call" } else { "jmp" };
+            (mnemonic, vec![Operand::Far { segment, offset }])
+        }
+        0x9B => ("wait", none()),
+        0x9C => ("pushf", none()),
+        0x9D => ("popf", none()),
+        0x9E => ("sahf", none()),
+        0x9F => ("lahf", none()),
+        0xA0..=0xA3 => {
+            let word = byte & 1 == 1;
+            let mem = Operand::Memory(Memory {
+                segment: None,
+                base: None,
+                index: None,
+                displacement: Some(Displacement::Word(bst.read_word()?)),
+                size: size_of(word),
+            });
+            if byte < 0xA2 {
+                ("mov", vec![reg_operand(0, word), mem])
+            } else {
+                ("mov", vec![mem, reg_operand(0, word)])
+            }
+        }
+        0xA4 => ("movsb", none()),
+        0xA5 => ("movsw", none()),
+        0xA6 => ("cmpsb", none()),
+        0xA7 => ("cmpsw", none()),
+        0xA8 | 0xA9 => ("test", vec![reg_operand(0, byte == 0xA9), imm_operand(bst, byte == 0xA9)?]),
+        0xAA => ("stosb", none()),
+        0xAB => ("stosw", none()),
+        0xAC => ("lodsb", none()),
+        0xAD => ("lodsw", none()),
+        0xAE => ("scasb", none()),
+        0xAF => ("scasw", none()),
+        0xB0..=0xB7 => ("mov", vec![reg_operand(byte, false), imm_operand(bst, false)?]),
+        0xB8..=0xBF => ("mov", vec![reg_operand(byte, true), imm_operand(bst, true)?]),
+        0xC2 => ("ret", vec![imm_operand(bst, true)?]),
+        0xC3 => ("ret", none()),
+        0xC4 | 0xC5 if peek_mod(bst)? == 3 => return Ok(None),
+        0xC4 | 0xC5 => {
+            let (_, reg, rm) = modrm_byte_handling(bst, OperandSize::Far)?;
+            let mnemonic = if byte == 0xC4 { "les" } else { "lds" };
+            (mnemonic, vec![reg_operand(reg, true), rm])
+        }
+        0xC6 | 0xC7 if peek_reg(bst)? != 0 => return Ok(None),
+        0xC6 | 0xC7 => {
+            let (_, _, rm) = modrm_byte_handling(bst, size_of(byte == 0xC7))?;
+            ("mov", vec![rm, imm_operand(bst, byte == 0xC7)?])
+        }
+        0xCA => ("retf", vec![imm_operand(bst, true)?]),
+        0xCB => ("retf", none()),
+        0xCC => ("int3", none()),
+        0xCD => ("int", vec![imm_operand(bst, false)?]),
+        0xCE => ("into", none()),
+        0xCF => ("iret", none()),
+        0xD0..=0xD3 => return parse_grp2(bst, byte),
+        0xD4 => ("aam", vec![imm_operand(bst, false)?]),
+        0xD5 => ("aad", vec![imm_operand(bst, false)?]),
+        0xD7 => ("xlat", none()),
+        0xD8..=0xDF => {
+            let (_, reg, rm) = modrm_byte_handling(bst, OperandSize::None)?;
+            ("esc", vec![Operand::Immediate((((byte & 0b111) << 3) | reg) as u16), rm])
+        }
+        0xE0 => ("loopne", vec![rel_operand(bst, false)?]),
+        0xE1 => ("loope", vec![rel_operand(bst, false)?]),
+        0xE2 => ("loop", vec![rel_operand(bst, false)?]),
+        0xE3 => ("jcxz", vec![rel_operand(bst, false)?]),
+        0xE4 | 0xE5 => ("in", vec![reg_operand(0, byte == 0xE5), imm_operand(bst, false)?]),
+        0xE6 | 0xE7 => ("out", vec![imm_operand(bst, false)?, reg_operand(0, byte == 0xE7)]),
+        0xE8 => ("call", vec![rel_operand(bst, true)?]),
+        0xE9 => ("jmp", vec![rel_operand(bst, true)?]),
+        0xEB => ("jmp", vec![rel_operand(bst, false)?]),
+        0xEC | 0xED => ("in", vec![reg_operand(0, byte == 0xED), Operand::Register(Register::DX)]),
+        0xEE | 0xEF => ("out", vec![Operand::Register(Register::DX), reg_operand(0, byte == 0xEF)]),
+        0xF4 => ("hlt", none()),
+        0xF5 => ("cmc", none()),
+        0xF6 | 0xF7 => return parse_grp3(bst, byte == 0xF7),
+        0xF8 => ("clc", none()),
+        0xF9 => ("stc", none()),
+        0xFA => ("cli", none()),
+        0xFB => ("sti", none()),
+        0xFC => ("cld", none()),
+        0xFD => ("std", none()),
+        0xFE | 0xFF => return parse_grp4_5(bst, byte),
+        // 0f (pop cs), 60-6f, c0, c1, c8, c9, d6 and f1 are undocumented or 186+ only
+        _ => return Ok(None),
+    }))
+}
 
-    format!(
-        "{} {},0x{immediate:X}",
-        mnemonic,
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
-}
-// 82
-pub fn op_83(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, _, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-
-    let immediate = bst.read_byte();
-    let mnemonic = OPS[reg as usize];
-
-    if execute {
-        match mnemonic {
-            "sub" => {
-                if v.is_some() {
-                    let vt = bst.read_byte_at(v.unwrap() as usize);
-                    bst.replace_byte(v.unwrap() as usize, vt.wrapping_sub(immediate));
-                } else {
-                    let sregv = REG_NAMES[rm as usize].to_owned();
-                    let regv = get_parsed_reg(&sregv).unwrap();
-                    set_parsed_reg(&sregv, regv.wrapping_sub(immediate as u16)).unwrap();
-                }
+/// <p>Decodes a single instruction at <code>bst.pos</code>.  Bytes that are not a documented 8086 instruction come out
+/// as a one-byte <code>db</code>.  Fails with <code>Error::Truncated</code> if the buffer ends partway through the
+/// instruction.</p>
+pub fn parse_byte_code(bst: &mut ByteStream) -> Result<Instruction, Error> {
+    let address = bst.pos;
+
+    let mut prefixes = Vec::new();
+    let decoded = loop {
+        let byte = bst.read_byte()?;
+        match byte {
+            0x26 | 0x2E | 0x36 | 0x3E => prefixes.push(Prefix::Segment(Register::segment(byte >> 3))),
+            0xF0 => prefixes.push(Prefix::Lock),
+            0xF2 => prefixes.push(Prefix::Repne),
+            0xF3 => prefixes.push(Prefix::Rep),
+            _ => break parse_opcode(bst, byte)?,
+        }
+    };
+
+    let (mnemonic, mut operands) = match decoded {
+        Some(decoded) => decoded,
+        None => {
+            prefixes.clear();
+            bst.pos = address + 1;
+            ("db", vec![Operand::Immediate(bst.read_byte_at(address)? as u16)])
+        }
+    };
+
+    if let Some(Prefix::Segment(seg)) = prefixes.iter().rev().find(|p| matches!(p, Prefix::Segment(_))) {
+        for op in operands.iter_mut() {
+            if let Operand::Memory(m) = op {
+                m.segment = Some(*seg);
             }
-            &_ => panic!(),
         }
     }
 
-    format!(
-        "{} {},0x{immediate:X}",
+    let length = bst.pos - address;
+    Ok(Instruction {
+        address,
+        length,
+        bytes: bst.read_bytes_at(length, address)?,
         mnemonic,
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
+        operands,
+        prefixes,
+    })
 }
-// 84-8a
-pub fn op_8b(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, mod_s, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-    let destreg = REG_NAMES[reg as usize].to_owned();
-
-    if execute {
-        if v.is_some() {
-            let index =
-                if rm == 0 || rm == 1 || rm == 4 || rm == 5 || (rm == 6 && mod_s == 0) || rm == 7 {
-                    *DS.read().unwrap()
-                } else if rm == 2 || rm == 3 || (rm == 6 && mod_s != 0) {
-                    *SS.read().unwrap()
-                } else {
-                    panic!()
-                } << 4 + v.unwrap();
-
-            set_parsed_reg(&destreg, bst.read_word_at(index as usize)).unwrap();
-        } else {
-            let regv = get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap();
-            set_parsed_reg(&destreg, regv).unwrap();
-        }
-    }
 
-    format!(
-        "mov {destreg},{}",
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
-}
-pub fn op_8c(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, mod_s, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-    let seg_reg = SEG_REG_NAMES[reg as usize].to_owned();
-
-    if execute {
-        if v.is_some() {
-            let index =
-                if rm == 0 || rm == 1 || rm == 4 || rm == 5 || (rm == 6 && mod_s == 0) || rm == 7 {
-                    *DS.read().unwrap()
-                } else if rm == 2 || rm == 3 || (rm == 6 && mod_s != 0) {
-                    *SS.read().unwrap()
-                } else {
-                    panic!()
-                } << 4 + v.unwrap();
-
-            let regv = get_parsed_seg_reg(&seg_reg).unwrap();
-            bst.replace_word(index as usize, regv);
-        } else {
-            let regv = get_parsed_seg_reg(&seg_reg).unwrap();
-            set_parsed_reg(&REG_NAMES[rm as usize].to_owned(), regv).unwrap();
+/// <p>Parses code and converts it into 16-bit assembly code with the x86 instruction set.</p>
+/// <p>An instruction cut off by the end of the buffer is emitted as <code>db</code> bytes instead.</p>
+pub fn parse_code(bytes: &[u8]) -> Vec<Instruction> {
+    // padded so a truncated instruction at the end can still be decoded and then thrown away
+    let mut padded = bytes.to_vec();
+    padded.extend_from_slice(&[0; MAX_INSTRUCTION_TAIL]);
+    let mut bst = ByteStream::new(padded);
+
+    let mut code = Vec::new();
+
+    while bst.pos < bytes.len() {
+        let start = bst.pos;
+        match parse_byte_code(&mut bst) {
+            Ok(ins) if bst.pos <= bytes.len() => code.push(ins),
+            _ => {
+                for (i, b) in bytes[start..].iter().enumerate() {
+                    code.push(Instruction::data(start + i, *b));
+                }
+                break;
+            }
         }
     }
 
-    format!(
-        "mov {},{seg_reg}",
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
-}
-pub fn op_8d(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, _, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-    if execute {
-        set_parsed_reg(
-            &REG_NAMES[reg as usize].to_owned(),
-            v.unwrap_or_else(|| get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap()),
-        )
-        .unwrap();
-    }
-    format!(
-        "lea {},{}",
-        REG_NAMES[reg as usize],
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
-}
-pub fn op_8e(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, mod_s, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
-    let seg_reg = SEG_REG_NAMES[reg as usize].to_owned();
-
-    if execute {
-        if v.is_some() {
-            let index =
-                if rm == 0 || rm == 1 || rm == 4 || rm == 5 || (rm == 6 && mod_s == 0) || rm == 7 {
-                    *DS.read().unwrap()
-                } else if rm == 2 || rm == 3 || (rm == 6 && mod_s != 0) {
-                    *SS.read().unwrap()
-                } else {
-                    panic!()
-                } << 4 + v.unwrap();
-
-            let regv = bst.read_word_at(index as usize);
-            set_parsed_seg_reg(&seg_reg, regv).unwrap();
-        } else {
-            let regv = get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap();
-            set_parsed_seg_reg(&seg_reg, regv).unwrap();
-        }
+    code
+}
+
+fn operand_is_word(op: &Operand) -> bool {
+    match op {
+        Operand::Register(r) => !r.is_byte(),
+        Operand::Memory(m) => m.size != OperandSize::Byte,
+        _ => true,
     }
+}
 
-    format!(
-        "mov {seg_reg},{}",
-        v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-    )
+fn set_logic_flags(cpu: &mut Cpu, res: u16, word: bool) {
+    cpu.cf = false;
+    cpu.of = false;
+    set_result_flags(cpu, res, word);
 }
-// 8f-ad
-pub fn op_ae(execute: bool, bst: &mut ByteStream) -> String {
-    if execute {
-        let ptr_val = bst.read_byte_at(
-            (((*ES.read().unwrap() as u32) << 4) + *DI.read().unwrap() as u32) as usize,
-        );
-        *ZF.write().unwrap() = ptr_val == *AL.read().unwrap();
-        if *DF.read().unwrap() {
-            *DI.write().unwrap() -= 1;
-        } else {
-            *DI.write().unwrap() += 1;
+/// <p>ZF, SF and PF, which every arithmetic and logic instruction sets from its result the same way.</p>
+fn set_result_flags(cpu: &mut Cpu, res: u16, word: bool) {
+    cpu.zf = res == 0;
+    cpu.sf = if word { res >> 15 == 1 } else { (res >> 7) & 1 == 1 };
+    cpu.pf = (res & 0xFF).count_ones().is_multiple_of(2);
+}
+
+/// <p>The operation <code>op</code>, one of the eight of <code>OPS</code>, on <code>a</code> and <code>b</code>,
+/// setting the flags the way the 8086 does, AF included.  Returns the result, which <code>cmp</code> only sets the
+/// flags from.</p>
+fn arithmetic(cpu: &mut Cpu, ins: &Instruction, op: &str, a: u16, b: u16, word: bool) -> Result<u16, Error> {
+    let (mask, sign) = if word { (0xFFFF, 0x8000) } else { (0xFF, 0x80) };
+    let (a, b) = (a as u32 & mask, b as u32 & mask);
+    let res = match op {
+        "add" | "adc" => {
+            let res = a + b + (op == "adc" && cpu.cf) as u32;
+            cpu.cf = res > mask;
+            cpu.of = (a ^ res) & (b ^ res) & sign != 0;
+            res
+        }
+        "sub" | "sbb" | "cmp" => {
+            let borrow = (op == "sbb" && cpu.cf) as u32;
+            let res = a.wrapping_sub(b).wrapping_sub(borrow);
+            cpu.cf = b + borrow > a;
+            cpu.of = (a ^ b) & (a ^ res) & sign != 0;
+            res
+        }
+        "and" => a & b,
+        "or" => a | b,
+        "xor" => a ^ b,
+        _ => return Err(Error::UnsupportedOpcode { addr: ins.address, byte: ins.opcode() }),
+    };
+    let res = (res & mask) as u16;
+    match op {
+        "and" | "or" | "xor" => set_logic_flags(cpu, res, word),
+        _ => {
+            cpu.af = (a ^ b ^ res as u32) & 0x10 != 0;
+            set_result_flags(cpu, res, word);
         }
     }
+    Ok(res)
+}
 
-    "scasb".to_owned()
+/// <p>Whether the condition of the <code>jcc</code> with opcode <code>byte</code> holds.  The odd opcodes are the
+/// even ones negated.</p>
+fn condition(cpu: &Cpu, byte: u8) -> bool {
+    let holds = match (byte >> 1) & 0b111 {
+        0 => cpu.of,
+        1 => cpu.cf,
+        2 => cpu.zf,
+        3 => cpu.cf || cpu.zf,
+        4 => cpu.sf,
+        5 => cpu.pf,
+        6 => cpu.sf != cpu.of,
+        _ => cpu.zf || cpu.sf != cpu.of,
+    };
+    holds != (byte & 1 == 1)
 }
-// af
-pub fn op_b0(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov al,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *AL.write().unwrap() = b;
-        }
-        b
-    })
+
+/// <p>Moves IP to the target of a relative branch.</p>
+fn jump(cpu: &mut Cpu, ins: &Instruction) -> Result<(), Error> {
+    cpu.ip = ins.branch_target().ok_or(Error::InvalidOperand { addr: ins.address })? as u16;
+    Ok(())
 }
-pub fn op_b1(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov cl,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *CL.write().unwrap() = b;
-        }
-        b
-    })
+
+/// <p>00-3d: <code>add</code>, <code>or</code>, <code>adc</code>, <code>sbb</code>, <code>and</code>,
+/// <code>sub</code>, <code>xor</code> and <code>cmp</code> between a register and memory, or AL/AX and an
+/// immediate.</p>
+pub fn op_00_3d(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let (target, source) = (&ins.operands[0], &ins.operands[1]);
+    let (a, b) = (cpu.read_operand(mem, target)?, cpu.read_operand(mem, source)?);
+    let res = arithmetic(cpu, ins, ins.mnemonic, a, b, operand_is_word(target))?;
+    match ins.mnemonic {
+        "cmp" => Ok(()),
+        _ => cpu.write_operand(mem, ins, target, res),
+    }
 }
-pub fn op_b2(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov dl,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *DL.write().unwrap() = b;
-        }
-        b
-    })
+pub fn op_0e(cpu: &mut Cpu, mem: &mut memory::Memory) {
+    cpu.push(mem, cpu.cs);
 }
-pub fn op_b3(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov bl,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *BL.write().unwrap() = b;
-        }
-        b
-    })
+// 0f-1e
+pub fn op_1f(cpu: &mut Cpu, mem: &memory::Memory) {
+    cpu.ds = cpu.pop(mem);
 }
-pub fn op_b4(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov ah,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *AL.write().unwrap() = b;
-        }
-        b
-    })
+// 20-32
+pub fn op_33(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let res = cpu.read_operand(mem, &ins.operands[0])? ^ cpu.read_operand(mem, &ins.operands[1])?;
+    cpu.write_operand(mem, ins, &ins.operands[0], res)?;
+    set_logic_flags(cpu, res, true);
+    Ok(())
 }
-pub fn op_b5(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov ch,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *CL.write().unwrap() = b;
-        }
-        b
-    })
+// 34-3f
+/// <p>40-4f: <code>inc</code> and <code>dec</code> of a word register, which leave CF alone.</p>
+pub fn op_40_4f(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let target = &ins.operands[0];
+    let v = cpu.read_operand(mem, target)?;
+    let (cf, res) = (cpu.cf, if ins.mnemonic == "inc" { v.wrapping_add(1) } else { v.wrapping_sub(1) });
+    cpu.of = if ins.mnemonic == "inc" { res == 0x8000 } else { v == 0x8000 };
+    cpu.af = (v ^ res) & 0x10 != 0;
+    set_result_flags(cpu, res, true);
+    cpu.cf = cf;
+    cpu.write_operand(mem, ins, target, res)
 }
-pub fn op_b6(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov dh,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *DL.write().unwrap() = b;
-        }
-        b
-    })
+pub fn op_50(cpu: &mut Cpu, mem: &mut memory::Memory) {
+    cpu.push(mem, cpu.ax());
 }
-pub fn op_b7(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov bh,0x{}", {
-        let b = bst.read_byte();
-        if execute {
-            *BL.write().unwrap() = b;
-        }
-        b
-    })
+// 51-54
+pub fn op_55(cpu: &mut Cpu, mem: &mut memory::Memory) {
+    cpu.push(mem, cpu.bp);
 }
-pub fn op_b8(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov ax,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            set_ax(w);
-        }
-        w
-    })
+pub fn op_56(cpu: &mut Cpu, mem: &mut memory::Memory) {
+    cpu.push(mem, cpu.si);
 }
-pub fn op_b9(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov cx,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            set_cx(w);
-        }
-        w
-    })
+// 57-5c
+pub fn op_5d(cpu: &mut Cpu, mem: &memory::Memory) {
+    cpu.bp = cpu.pop(mem);
 }
-pub fn op_ba(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov dx,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            set_dx(w);
-        }
-        w
-    })
+// 5e-6f
+/// <p>70-7f: the conditional jumps.</p>
+pub fn op_70_7f(cpu: &mut Cpu, ins: &Instruction) -> Result<(), Error> {
+    match condition(cpu, ins.opcode()) {
+        true => jump(cpu, ins),
+        false => Ok(()),
+    }
 }
-pub fn op_bb(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov bx,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            set_bx(w);
-        }
-        w
-    })
+/// <p>80-83: the operations of 00-3d with an immediate, which 83 sign-extends from a byte.</p>
+pub fn op_81(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    op_00_3d(cpu, mem, ins)
 }
-pub fn op_bc(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov sp,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            *SP.write().unwrap() = w;
-        }
-        w
-    })
+pub fn op_83(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    op_81(cpu, mem, ins)
 }
-pub fn op_bd(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov bp,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            *BP.write().unwrap() = w;
-        }
-        w
-    })
+// 84-8a
+pub fn op_8b(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let v = cpu.read_operand(mem, &ins.operands[1])?;
+    cpu.write_operand(mem, ins, &ins.operands[0], v)
 }
-pub fn op_be(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov si,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            *SI.write().unwrap() = w;
-        }
-        w
-    })
+pub fn op_8c(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    op_8b(cpu, mem, ins)
 }
-pub fn op_bf(execute: bool, bst: &mut ByteStream) -> String {
-    format!("mov di,0x{}", {
-        let w = bst.read_word();
-        if execute {
-            *DI.write().unwrap() = w;
+pub fn op_8d(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    match &ins.operands[1] {
+        Operand::Memory(m) => {
+            let ea = cpu.effective_address(m);
+            cpu.write_operand(mem, ins, &ins.operands[0], ea)
         }
-        w
-    })
+        _ => Err(Error::InvalidOperand { addr: ins.address }),
+    }
 }
-// c0-c2
-pub fn op_c3(execute: bool, bst: &mut ByteStream) -> String {
-    if execute {
-        bst.pos = STACK.write().unwrap().pop().unwrap() as usize;
+pub fn op_8e(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    op_8b(cpu, mem, ins)
+}
+// 8f-ad
+/// <p><code>scasb</code>: compares AL with <code>ES:[DI]</code> like <code>cmp</code> does and moves DI on.</p>
+pub fn op_ae(cpu: &mut Cpu, mem: &memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let ptr_val = mem.read_byte(cpu.es, cpu.di);
+    arithmetic(cpu, ins, "cmp", cpu.al as u16, ptr_val as u16, false)?;
+    if cpu.df {
+        cpu.di = cpu.di.wrapping_sub(1);
+    } else {
+        cpu.di = cpu.di.wrapping_add(1);
     }
-
-    "ret".to_owned()
+    Ok(())
+}
+// af
+pub fn op_b0_bf(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    op_8b(cpu, mem, ins)
+}
+// c0-c2
+pub fn op_c3(cpu: &mut Cpu, mem: &memory::Memory) {
+    cpu.ip = cpu.pop(mem);
 }
 // c4-cb
-pub fn op_cd(execute: bool, bst: &mut ByteStream, api: API) -> (String, InteruptChange) {
-    match api {
-        API::DOS => dos_op_cd(execute, bst),
-        _ => (format!("int {}h", bst.read_byte()), InteruptChange::None),
-    }
-}
-// ce-e7
-pub fn op_e8(execute: bool, bst: &mut ByteStream) -> String {
-    let disp = bst.read_sword();
-    if execute {
-        STACK.write().unwrap().push(bst.pos as u16);
-        bst.pos += disp as usize;
-        format!("call 0x{:0000X}", bst.pos)
-    } else {
-        format!("call 0x{:0000X}", bst.pos + disp as usize)
+pub fn op_cd(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction, api: API) -> Result<InteruptChange, Error> {
+    match (api, &ins.operands[0]) {
+        (API::DOS, Operand::Immediate(vector)) => dos_op_cd(cpu, mem, *vector as u8),
+        _ => Ok(InteruptChange::None),
+    }
+}
+// ce-e1
+pub fn op_e2(cpu: &mut Cpu, ins: &Instruction) -> Result<(), Error> {
+    cpu.set_cx(cpu.cx().wrapping_sub(1));
+    match cpu.cx() {
+        0 => Ok(()),
+        _ => jump(cpu, ins),
     }
 }
+pub fn op_e3(cpu: &mut Cpu, ins: &Instruction) -> Result<(), Error> {
+    match cpu.cx() {
+        0 => jump(cpu, ins),
+        _ => Ok(()),
+    }
+}
+// e4-e7
+pub fn op_e8(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let target = ins.branch_target().ok_or(Error::InvalidOperand { addr: ins.address })?;
+    cpu.push(mem, ins.next_address() as u16);
+    cpu.ip = target as u16;
+    Ok(())
+}
+/// <p>e9 and eb: <code>jmp</code> near and short.</p>
+pub fn op_e9(cpu: &mut Cpu, ins: &Instruction) -> Result<(), Error> {
+    jump(cpu, ins)
+}
 
-pub fn op_f2(execute: bool, bst: &mut ByteStream) -> String {
-    if execute {
-        let fixed_pos = bst.pos;
-        let r = execute_byte_code(bst);
-        set_cx(get_cx() - 1);
-        while get_cx() > 0 || *ZF.read().unwrap() == true {
-            bst.pos = fixed_pos;
-            if execute_byte_code(bst) != r {
-                panic!();
-            }
+/// <p>Whether <code>opcode</code> is one of the string instructions, which are the only ones a <code>rep</code> prefix
+/// repeats.</p>
+fn is_string(opcode: u8) -> bool {
+    matches!(opcode, 0xA4..=0xA7 | 0xAA..=0xAF)
+}
+
+/// <p>Runs a string instruction with a <code>repne</code> prefix until CX runs out or ZF gets set.  Any other
+/// instruction runs once, as if it had no prefix.</p>
+pub fn op_f2(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    if !is_string(ins.opcode()) {
+        return execute_instruction(cpu, mem, ins);
+    }
+    while cpu.cx() > 0 {
+        execute_instruction(cpu, mem, ins)?;
+        cpu.set_cx(cpu.cx() - 1);
+        if cpu.zf {
+            break;
         }
-        format!("repne {r}")
-    } else {
-        format!("repne {}", parse_byte_code(bst))
     }
+    Ok(())
 }
-pub fn op_f3() -> String {
-    format!("")
+/// <p>Runs a string instruction with a <code>rep</code> prefix until CX runs out, or for
+/// <code>cmps</code>/<code>scas</code>, until ZF gets cleared.  Any other instruction runs once, as if it had no
+/// prefix.</p>
+pub fn op_f3(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    if !is_string(ins.opcode()) {
+        return execute_instruction(cpu, mem, ins);
+    }
+    let repe = matches!(ins.opcode(), 0xA6 | 0xA7 | 0xAE | 0xAF);
+    while cpu.cx() > 0 {
+        execute_instruction(cpu, mem, ins)?;
+        cpu.set_cx(cpu.cx() - 1);
+        if repe && !cpu.zf {
+            break;
+        }
+    }
+    Ok(())
 }
 
-pub fn op_f7(execute: bool, bst: &mut ByteStream) -> String {
-    let (_, _, reg, rm, _, v, v_s) = modrm_byte_handling(bst);
+/// <p>f6 and f7: <code>test</code>, <code>not</code> and <code>neg</code> of a byte or a word.</p>
+pub fn op_f6(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    op_f7(cpu, mem, ins)
+}
+pub fn op_f7(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    let target = &ins.operands[0];
+    let word = operand_is_word(target);
+    let (mask, sign) = if word { (0xFFFF, 0x8000) } else { (0xFF, 0x80) };
+    let w = cpu.read_operand(mem, target)? & mask;
 
-    match reg {
-        0 => {
-            let imm16 = bst.read_word();
-            let res =
-                v.unwrap_or_else(|| get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap());
-            if execute {
-                *CF.write().unwrap() = false;
-                *OF.write().unwrap() = false;
-                *ZF.write().unwrap() = res == 0;
-                *SF.write().unwrap() = (res >> 15) == 1;
-                *PF.write().unwrap() = (res & 0xFF).count_ones() % 2 == 0;
-            }
-            format!(
-                "test {},0x{imm16:0000X}",
-                v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-            )
+    match ins.mnemonic {
+        "test" => {
+            let imm = cpu.read_operand(mem, &ins.operands[1])?;
+            set_logic_flags(cpu, w & imm, word);
         }
-        2 => {
-            if execute {
-                if let Some(v1) = v {
-                    let w = bst.read_word_at(v1 as usize);
-                    bst.replace_word(v1 as usize, !w);
-                } else {
-                    let w = get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap();
-                    set_parsed_reg(&REG_NAMES[rm as usize].to_owned(), !w).unwrap();
-                }
-            }
-            format!(
-                "not {}",
-                v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-            )
-        }
-        3 => {
-            if execute {
-                let res = if let Some(v1) = v {
-                    let w = bst.read_word_at(v1 as usize);
-                    bst.replace_word(v1 as usize, 0u16.wrapping_sub(w));
-                    bst.read_word_at(v1 as usize)
-                } else {
-                    let w = get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap();
-                    set_parsed_reg(&REG_NAMES[rm as usize].to_owned(), 0u16.wrapping_sub(w))
-                        .unwrap();
-                    get_parsed_reg(&REG_NAMES[rm as usize].to_owned()).unwrap()
-                };
-                *CF.write().unwrap() = res != 0;
-                *OF.write().unwrap() = res == 0x8000;
-                *ZF.write().unwrap() = res == 0;
-                *SF.write().unwrap() = (res >> 15) & 0b1 == 1;
-                *PF.write().unwrap() = (res & 0xFF).count_ones() % 2 == 0;
-                // AF?
-            }
-            format!(
-                "neg {}",
-                v_s.unwrap_or_else(|| REG_NAMES[rm as usize].to_owned())
-            )
+        "not" => cpu.write_operand(mem, ins, target, !w & mask)?,
+        "neg" => {
+            let res = 0u16.wrapping_sub(w) & mask;
+            cpu.write_operand(mem, ins, target, res)?;
+            cpu.cf = res != 0;
+            cpu.of = res == sign;
+            cpu.af = (w & 0xF) != 0;
+            set_result_flags(cpu, res, word);
         }
+        _ => return Err(Error::UnsupportedOpcode { addr: ins.address, byte: ins.opcode() }),
+    }
+    Ok(())
+}
 
-        _ => panic!(),
+fn execute_instruction(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    match ins.opcode() {
+        0x0E => op_0e(cpu, mem),
+        0x1F => op_1f(cpu, mem),
+        0x33 => op_33(cpu, mem, ins)?,
+        0x00..=0x3D if ins.opcode() & 0b111 < 6 => op_00_3d(cpu, mem, ins)?,
+        0x40..=0x4F => op_40_4f(cpu, mem, ins)?,
+        0x50 => op_50(cpu, mem),
+        0x55 => op_55(cpu, mem),
+        0x56 => op_56(cpu, mem),
+        0x5D => op_5d(cpu, mem),
+        0x70..=0x7F => op_70_7f(cpu, ins)?,
+        0x80..=0x82 => op_81(cpu, mem, ins)?,
+        0x83 => op_83(cpu, mem, ins)?,
+        0x88..=0x8B | 0xC6 | 0xC7 => op_8b(cpu, mem, ins)?,
+        0x8C => op_8c(cpu, mem, ins)?,
+        0x8D => op_8d(cpu, mem, ins)?,
+        0x8E => op_8e(cpu, mem, ins)?,
+        0x90 => {}
+        0xAE => op_ae(cpu, mem, ins)?,
+        0xB0..=0xBF => op_b0_bf(cpu, mem, ins)?,
+        0xC3 => op_c3(cpu, mem),
+        0xE2 => op_e2(cpu, ins)?,
+        0xE3 => op_e3(cpu, ins)?,
+        0xE8 => op_e8(cpu, mem, ins)?,
+        0xE9 | 0xEB => op_e9(cpu, ins)?,
+        0xF6 => op_f6(cpu, mem, ins)?,
+        0xF7 => op_f7(cpu, mem, ins)?,
+        byte => return Err(Error::UnsupportedOpcode { addr: ins.address, byte }),
     }
+    Ok(())
 }
 
-pub fn parse_byte_code(bst: &mut ByteStream) -> String {
-    let byte = bst.read_byte();
+/// <p>The longest instruction fetched from memory.  The 8086 itself has no limit on repeated prefixes, but no real
+/// code needs more than this.</p>
+const FETCH_WINDOW: usize = 16;
 
-    match byte {
-        0x00 => op_00(),
-        0x0E => op_0e(false),
-        0x1F => op_1f(false),
-        0x33 => op_33(false, bst),
-        _ => panic!(),
+/// <p>Decodes the instruction at <code>CS:IP</code>.  Its address is the offset in CS, and so are the targets of
+/// relative branches.  Fetching doesn't set off read watchpoints.</p>
+pub fn fetch(cpu: &Cpu, mem: &memory::Memory) -> Result<Instruction, Error> {
+    let mut bst = ByteStream::new(mem.peek_bytes(cpu.cs, cpu.ip, FETCH_WINDOW));
+    let mut ins = parse_byte_code(&mut bst)?;
+    ins.address = cpu.ip as usize;
+    for op in ins.operands.iter_mut() {
+        if let Operand::Relative(target) = op {
+            *target = target.wrapping_add(cpu.ip as usize) & 0xFFFF;
+        }
     }
+    Ok(ins)
 }
 
-/// <p>Parses code and converts it into 16-bit assembly code with the x86 instruction set.</p>
-pub fn parse_code(bytes: &Vec<u8>) -> Vec<String> {
-    let mut bst = ByteStream::new(bytes.clone());
+/// <p>Decodes the instruction at <code>CS:IP</code>, moves IP past it and runs it on the given CPU.</p>
+pub fn execute_byte_code(cpu: &mut Cpu, mem: &mut memory::Memory) -> Result<Instruction, Error> {
+    let ins = fetch(cpu, mem)?;
+    cpu.ip = ins.next_address() as u16;
+    execute_fetched(cpu, mem, &ins)?;
+    Ok(ins)
+}
+
+/// <p>Runs an instruction <code>fetch</code> returned, repeating it if it is a string instruction with a
+/// <code>rep</code> prefix.  IP should already be past it.</p>
+pub fn execute_fetched(cpu: &mut Cpu, mem: &mut memory::Memory, ins: &Instruction) -> Result<(), Error> {
+    if ins.prefixes.contains(&Prefix::Repne) {
+        op_f2(cpu, mem, ins)
+    } else if ins.prefixes.contains(&Prefix::Rep) {
+        op_f3(cpu, mem, ins)
+    } else {
+        execute_instruction(cpu, mem, ins)
+    }
+}
+
+/// <p>Loads <code>bytes</code> into <code>mem</code> as a program image after a PSP at
+/// <code>DEFAULT_PSP_SEGMENT</code>, points CS, DS, ES and SS at it and runs it from its first byte until IP leaves
+/// it, returning every executed instruction.</p>
+pub fn execute_code(cpu: &mut Cpu, mem: &mut memory::Memory, bytes: &[u8]) -> Result<Vec<Instruction>, Error> {
+    let segment = memory::Memory::program_segment(DEFAULT_PSP_SEGMENT);
+    mem.load(segment, bytes);
+    (cpu.cs, cpu.ds, cpu.es, cpu.ss) = (segment, segment, segment, segment);
+    cpu.ip = 0;
 
     let mut code = Vec::new();
 
-    while bst.available() {
-        code.push(parse_byte_code(&mut bst));
+    while (cpu.ip as usize) < bytes.len() {
+        code.push(execute_byte_code(cpu, mem)?)
     }
 
-    code
+    Ok(code)
 }
 
-pub fn execute_byte_code(bst: &mut ByteStream) -> String {
-    let byte = bst.read_byte();
+/// <p>The register file and flags of a single 16-bit x86 processor.  The stack is in memory, at <code>SS:SP</code>.</p>
+/// <p>Every emulation owns its own <code>Cpu</code>, so several executables can run side by side and a state can be
+/// cloned to compare it with a later one.</p>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cpu {
+    pub ah: u8,
+    pub al: u8,
+    pub bh: u8,
+    pub bl: u8,
+    pub ch: u8,
+    pub cl: u8,
+    pub dh: u8,
+    pub dl: u8,
 
-    match byte {
-        0x00 => op_00(),
-        0x0E => op_0e(true),
-        0x1F => op_1f(true),
-        _ => panic!()
-    }
+    pub si: u16,
+    pub di: u16,
+    pub bp: u16,
+    pub sp: u16,
+
+    pub cs: u16,
+    pub ds: u16,
+    pub ss: u16,
+    pub es: u16,
+
+    pub ip: u16,
+
+    pub cf: bool,
+    pub pf: bool,
+    pub af: bool,
+    pub zf: bool,
+    pub sf: bool,
+    pub tf: bool,
+    pub r#if: bool,
+    pub df: bool,
+    pub of: bool,
+    pub iopl: (bool, bool),
+    pub nt: bool,
 }
 
-pub fn execute_code(bytes: &Vec<u8>) -> Vec<String> {
-    let mut bst = ByteStream::new(bytes.clone());
+impl Cpu {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    let mut code = Vec::new();
+    pub fn flags(&self) -> u16 {
+        (if self.nt { 1 << 14 } else { 0 })
+            | (if self.iopl.0 { 1 << 13 } else { 0 })
+            | (if self.iopl.1 { 1 << 12 } else { 0 })
+            | (if self.of { 1 << 11 } else { 0 })
+            | (if self.df { 1 << 10 } else { 0 })
+            | (if self.r#if { 1 << 9 } else { 0 })
+            | (if self.tf { 1 << 8 } else { 0 })
+            | (if self.sf { 1 << 7 } else { 0 })
+            | (if self.zf { 1 << 6 } else { 0 })
+            | (if self.af { 1 << 4 } else { 0 })
+            | (if self.pf { 1 << 2 } else { 0 })
+            | (if self.cf { 1 } else { 0 })
+    }
+    pub fn set_flags(&mut self, v: u16) {
+        self.cf = (v & 1) == 1;
+        self.pf = ((v >> 2) & 1) == 1;
+        self.af = ((v >> 4) & 1) == 1;
+        self.zf = ((v >> 6) & 1) == 1;
+        self.sf = ((v >> 7) & 1) == 1;
+        self.tf = ((v >> 8) & 1) == 1;
+        self.r#if = ((v >> 9) & 1) == 1;
+        self.df = ((v >> 10) & 1) == 1;
+        self.of = ((v >> 11) & 1) == 1;
+        self.iopl.1 = ((v >> 12) & 1) == 1;
+        self.iopl.0 = ((v >> 13) & 1) == 1;
+        self.nt = ((v >> 14) & 1) == 1;
+    }
 
-    while bst.available() {
-        code.push(execute_byte_code(&mut bst))
+    pub fn ax(&self) -> u16 {
+        ((self.ah as u16) << 8) | (self.al as u16)
+    }
+    pub fn set_ax(&mut self, v: u16) {
+        self.ah = (v >> 8) as u8;
+        self.al = (v & 0xFF) as u8;
+    }
+    pub fn bx(&self) -> u16 {
+        ((self.bh as u16) << 8) | (self.bl as u16)
+    }
+    pub fn set_bx(&mut self, v: u16) {
+        self.bh = (v >> 8) as u8;
+        self.bl = (v & 0xFF) as u8;
+    }
+    pub fn cx(&self) -> u16 {
+        ((self.ch as u16) << 8) | (self.cl as u16)
+    }
+    pub fn set_cx(&mut self, v: u16) {
+        self.ch = (v >> 8) as u8;
+        self.cl = (v & 0xFF) as u8;
+    }
+    pub fn dx(&self) -> u16 {
+        ((self.dh as u16) << 8) | (self.dl as u16)
+    }
+    pub fn set_dx(&mut self, v: u16) {
+        self.dh = (v >> 8) as u8;
+        self.dl = (v & 0xFF) as u8;
     }
 
-    code
+    pub fn register(&self, reg: Register) -> u16 {
+        match reg {
+            Register::AL => self.al as u16,
+            Register::CL => self.cl as u16,
+            Register::DL => self.dl as u16,
+            Register::BL => self.bl as u16,
+            Register::AH => self.ah as u16,
+            Register::CH => self.ch as u16,
+            Register::DH => self.dh as u16,
+            Register::BH => self.bh as u16,
+            Register::AX => self.ax(),
+            Register::CX => self.cx(),
+            Register::DX => self.dx(),
+            Register::BX => self.bx(),
+            Register::SP => self.sp,
+            Register::BP => self.bp,
+            Register::SI => self.si,
+            Register::DI => self.di,
+            Register::ES => self.es,
+            Register::CS => self.cs,
+            Register::SS => self.ss,
+            Register::DS => self.ds,
+        }
+    }
+    /// <p>Sets a register.  8-bit registers only take the low byte of <code>v</code>.</p>
+    pub fn set_register(&mut self, reg: Register, v: u16) {
+        match reg {
+            Register::AL => self.al = v as u8,
+            Register::CL => self.cl = v as u8,
+            Register::DL => self.dl = v as u8,
+            Register::BL => self.bl = v as u8,
+            Register::AH => self.ah = v as u8,
+            Register::CH => self.ch = v as u8,
+            Register::DH => self.dh = v as u8,
+            Register::BH => self.bh = v as u8,
+            Register::AX => self.set_ax(v),
+            Register::CX => self.set_cx(v),
+            Register::DX => self.set_dx(v),
+            Register::BX => self.set_bx(v),
+            Register::SP => self.sp = v,
+            Register::BP => self.bp = v,
+            Register::SI => self.si = v,
+            Register::DI => self.di = v,
+            Register::ES => self.es = v,
+            Register::CS => self.cs = v,
+            Register::SS => self.ss = v,
+            Register::DS => self.ds = v,
+        }
+    }
+
+    pub fn effective_address(&self, m: &Memory) -> u16 {
+        let mut ea = m.displacement.map(|d| d.value()).unwrap_or(0);
+        for r in [m.base, m.index].into_iter().flatten() {
+            ea = ea.wrapping_add(self.register(r));
+        }
+        ea
+    }
+    /// <p>The <code>segment:offset</code> a memory operand refers to, using its segment override or the default
+    /// segment.</p>
+    pub fn segment_offset(&self, m: &Memory) -> (u16, u16) {
+        (self.register(m.effective_segment()), self.effective_address(m))
+    }
+    /// <p>Turns a memory operand into a physical address, using its segment override or the default segment.</p>
+    pub fn physical_address(&self, m: &Memory, mem: &memory::Memory) -> usize {
+        let (segment, offset) = self.segment_offset(m);
+        mem.physical(segment, offset)
+    }
+
+    pub fn push(&mut self, mem: &mut memory::Memory, v: u16) {
+        self.sp = self.sp.wrapping_sub(2);
+        mem.write_word(self.ss, self.sp, v);
+    }
+    pub fn pop(&mut self, mem: &memory::Memory) -> u16 {
+        let v = mem.read_word(self.ss, self.sp);
+        self.sp = self.sp.wrapping_add(2);
+        v
+    }
+
+    fn read_operand(&self, mem: &memory::Memory, op: &Operand) -> Result<u16, Error> {
+        Ok(match op {
+            Operand::Register(r) => self.register(*r),
+            Operand::Memory(m) if m.size == OperandSize::Byte => {
+                let (segment, offset) = self.segment_offset(m);
+                mem.read_byte(segment, offset) as u16
+            }
+            Operand::Memory(m) => {
+                let (segment, offset) = self.segment_offset(m);
+                mem.read_word(segment, offset)
+            }
+            Operand::Immediate(v) => *v,
+            Operand::SignExtended(v) => *v as u16,
+            Operand::Relative(target) => *target as u16,
+            Operand::Far { offset, .. } => *offset,
+        })
+    }
+    fn write_operand(
+        &mut self,
+        mem: &mut memory::Memory,
+        ins: &Instruction,
+        op: &Operand,
+        v: u16,
+    ) -> Result<(), Error> {
+        match op {
+            Operand::Register(r) => {
+                self.set_register(*r, v);
+                Ok(())
+            }
+            Operand::Memory(m) => {
+                let (segment, offset) = self.segment_offset(m);
+                match operand_is_word(op) {
+                    true => mem.write_word(segment, offset, v),
+                    false => mem.write_byte(segment, offset, v as u8),
+                }
+                Ok(())
+            }
+            _ => Err(Error::InvalidOperand { addr: ins.address }),
+        }
+    }
 }
 
-pub static STACK: RwLock<Vec<u16>> = RwLock::new(Vec::new());
-
-pub static AH: RwLock<u8> = RwLock::new(0);
-pub static AL: RwLock<u8> = RwLock::new(0);
-pub static BH: RwLock<u8> = RwLock::new(0);
-pub static BL: RwLock<u8> = RwLock::new(0);
-pub static CH: RwLock<u8> = RwLock::new(0);
-pub static CL: RwLock<u8> = RwLock::new(0);
-pub static DH: RwLock<u8> = RwLock::new(0);
-pub static DL: RwLock<u8> = RwLock::new(0);
-
-pub static SI: RwLock<u16> = RwLock::new(0);
-pub static DI: RwLock<u16> = RwLock::new(0);
-pub static BP: RwLock<u16> = RwLock::new(0);
-pub static SP: RwLock<u16> = RwLock::new(0);
-
-pub static CS: RwLock<u16> = RwLock::new(0);
-pub static DS: RwLock<u16> = RwLock::new(0);
-pub static SS: RwLock<u16> = RwLock::new(0);
-pub static ES: RwLock<u16> = RwLock::new(0);
-
-pub static IP: RwLock<u16> = RwLock::new(0);
-
-pub static CF: RwLock<bool> = RwLock::new(false);
-pub static PF: RwLock<bool> = RwLock::new(false);
-pub static AF: RwLock<bool> = RwLock::new(false);
-pub static ZF: RwLock<bool> = RwLock::new(false);
-pub static SF: RwLock<bool> = RwLock::new(false);
-pub static TF: RwLock<bool> = RwLock::new(false);
-pub static IF: RwLock<bool> = RwLock::new(false);
-pub static DF: RwLock<bool> = RwLock::new(false);
-pub static OF: RwLock<bool> = RwLock::new(false);
-pub static IOPL: RwLock<(bool, bool)> = RwLock::new((false, false));
-pub static NT: RwLock<bool> = RwLock::new(false);
-
-pub unsafe fn get_flags() -> u16 {
-    (if *NT.read().unwrap() { 1 << 14 } else { 0 })
-        | (if IOPL.read().unwrap().0 { 1 << 13 } else { 0 })
-        | (if IOPL.read().unwrap().1 { 1 << 12 } else { 0 })
-        | (if *OF.read().unwrap() { 1 << 11 } else { 0 })
-        | (if *DF.read().unwrap() { 1 << 10 } else { 0 })
-        | (if *IF.read().unwrap() { 1 << 9 } else { 0 })
-        | (if *TF.read().unwrap() { 1 << 8 } else { 0 })
-        | (if *SF.read().unwrap() { 1 << 7 } else { 0 })
-        | (if *ZF.read().unwrap() { 1 << 6 } else { 0 })
-        | (if *AF.read().unwrap() { 1 << 4 } else { 0 })
-        | (if *PF.read().unwrap() { 1 << 2 } else { 0 })
-        | (if *CF.read().unwrap() { 1 } else { 0 })
-}
-pub unsafe fn set_flags(v: u16) {
-    *CF.write().unwrap() = (v & 1) == 1;
-    *PF.write().unwrap() = ((v >> 2) & 1) == 1;
-    *AF.write().unwrap() = ((v >> 4) & 1) == 1;
-    *ZF.write().unwrap() = ((v >> 6) & 1) == 1;
-    *SF.write().unwrap() = ((v >> 7) & 1) == 1;
-    *TF.write().unwrap() = ((v >> 8) & 1) == 1;
-    *IF.write().unwrap() = ((v >> 9) & 1) == 1;
-    *DF.write().unwrap() = ((v >> 10) & 1) == 1;
-    *OF.write().unwrap() = ((v >> 11) & 1) == 1;
-    IOPL.write().unwrap().1 = ((v >> 12) & 1) == 1;
-    IOPL.write().unwrap().0 = ((v >> 13) & 1) == 1;
-    *NT.write().unwrap() = ((v >> 14) & 1) == 1;
-}
-
-pub fn get_ax() -> u16 {
-    ((*AH.read().unwrap() as u16) << 8) | (*AL.read().unwrap() as u16)
-}
-pub fn set_ax(v: u16) {
-    *AH.write().unwrap() = (v >> 8) as u8;
-    *AL.write().unwrap() = (v & 0xFF) as u8;
-}
-pub fn get_bx() -> u16 {
-    ((*BH.read().unwrap() as u16) << 8) | (*BL.read().unwrap() as u16)
-}
-pub fn set_bx(v: u16) {
-    *BH.write().unwrap() = (v >> 8) as u8;
-    *BL.write().unwrap() = (v & 0xFF) as u8;
-}
-pub fn get_cx() -> u16 {
-    ((*CH.read().unwrap() as u16) << 8) | (*CL.read().unwrap() as u16)
-}
-pub fn set_cx(v: u16) {
-    *CH.write().unwrap() = (v >> 8) as u8;
-    *CL.write().unwrap() = (v & 0xFF) as u8;
-}
-pub fn get_dx() -> u16 {
-    ((*DH.read().unwrap() as u16) << 8) | (*DL.read().unwrap() as u16)
-}
-pub fn set_dx(v: u16) {
-    *DH.write().unwrap() = (v >> 8) as u8;
-    *DL.write().unwrap() = (v & 0xFF) as u8;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// <p>Encodings from each part of the opcode map, with what they decode to at address 0.</p>
+    const CORPUS: &[(&[u8], &str)] = &[
+        (&[0x00, 0xD8], "add al,bl"),
+        (&[0x03, 0x46, 0xFC], "add ax,[bp-0x4]"),
+        (&[0x26, 0x8B, 0x47, 0x04], "mov ax,[es:bx+0x4]"),
+        (&[0x81, 0x3E, 0x34, 0x12, 0x78, 0x56], "cmp word [0x1234],0x5678"),
+        (&[0x83, 0xC4, 0xFE], "add sp,-0x2"),
+        (&[0xC7, 0x06, 0x00, 0x02, 0x34, 0x12], "mov word [0x200],0x1234"),
+        (&[0xB4, 0x4C], "mov ah,0x4C"),
+        (&[0x8E, 0xD8], "mov ds,ax"),
+        (&[0x8D, 0x36, 0x10, 0x00], "lea si,[0x10]"),
+        (&[0xA1, 0x00, 0x01], "mov ax,[0x100]"),
+        (&[0xC4, 0x5E, 0x06], "les bx,[bp+0x6]"),
+        (&[0x86, 0xC4], "xchg ah,al"),
+        (&[0xE8, 0x10, 0x00], "call 0x13"),
+        (&[0xEB, 0xFE], "jmp short 0x0"),
+        (&[0x74, 0x05], "jz 0x7"),
+        (&[0xE9, 0x00, 0x01], "jmp 0x103"),
+        (&[0x9A, 0x78, 0x56, 0x34, 0x12], "call 0x1234:0x5678"),
+        (&[0xFF, 0x1E, 0x00, 0x02], "call far [0x200]"),
+        (&[0xF3, 0xA4], "rep movsb"),
+        (&[0xF2, 0xAE], "repne scasb"),
+        (&[0xF0, 0x87, 0x07], "lock xchg [bx],ax"),
+        (&[0xD1, 0xE0], "shl ax,0x1"),
+        (&[0xD3, 0x2F], "shr word [bx],cl"),
+        (&[0xF7, 0xF3], "div bx"),
+        (&[0xF6, 0x26, 0x00, 0x01], "mul byte [0x100]"),
+        (&[0xCD, 0x21], "int 0x21"),
+        (&[0xC2, 0x04, 0x00], "ret 0x4"),
+        (&[0xCB], "retf"),
+        (&[0xE4, 0x60], "in al,0x60"),
+        (&[0xEE], "out dx,al"),
+        (&[0xD7], "xlatb"),
+        (&[0x1E], "push ds"),
+        (&[0x9C], "pushf"),
+        (&[0x4F], "dec di"),
+        (&[0xD8, 0xC1], "esc 0x0,cx"),
+        (&[0x0F], "db 0xF"),
+    ];
+
+    #[test]
+    fn decodes_the_corpus() {
+        for (bytes, text) in CORPUS {
+            let ins = parse_byte_code(&mut ByteStream::new(bytes.to_vec())).unwrap();
+            assert_eq!(ins.to_string(), *text, "{bytes:02X?}");
+            assert_eq!(ins.bytes, *bytes, "{text}");
+        }
+    }
+
+    #[test]
+    fn truncated_instructions_are_an_error() {
+        for bytes in [&[0x81, 0x3E, 0x34, 0x12, 0x78][..], &[0x9A, 0x78], &[0xF3]] {
+            assert!(parse_byte_code(&mut ByteStream::new(bytes.to_vec())).is_err(), "{bytes:02X?}");
+        }
+    }
 }
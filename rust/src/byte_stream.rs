@@ -1,120 +1,291 @@
+use std::{io::SeekFrom, ops::Range};
+
+use crate::error::Error;
+
+/// <p>Defines a read of a primitive integer in the given byte order, advancing the position past it.</p>
+macro_rules! typed_read {
+    ($($name:ident: $ty:ty = $from:ident;)*) => {
+        $(
+            pub fn $name(&mut self) -> Result<$ty, Error> {
+                const N: usize = std::mem::size_of::<$ty>();
+                let mut raw = [0; N];
+                raw.copy_from_slice(self.range(self.pos, N)?);
+                self.pos += N;
+                Ok(<$ty>::$from(raw))
+            }
+        )*
+    };
+}
+
 pub struct ByteStream {
     buf: Vec<u8>,
     skip: Vec<u8>,
+    /// Where <code>buf</code> starts in the stream this one was sliced from, so errors can name the real offset.
+    base: usize,
     pub pos: usize,
 }
 
 impl ByteStream {
     pub fn new(buf: Vec<u8>) -> Self {
-        Self { buf, skip: Vec::new(), pos: 0 }
+        Self { buf, skip: Vec::new(), base: 0, pos: 0 }
+    }
+
+    /// <p>The whole underlying buffer, regardless of the current position.</p>
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf
     }
 
     pub fn available(&self) -> bool {
         self.pos < self.buf.len()
     }
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+    /// <p>How many bytes are left from the current position.</p>
+    pub fn remaining(&self) -> usize {
+        self.buf.len().saturating_sub(self.pos)
+    }
+    /// <p>Where this stream starts in the one it was sliced from.  Zero for a stream made with <code>new</code>.</p>
+    pub fn base(&self) -> usize {
+        self.base
+    }
 
-    pub fn check_reserved(&mut self, byte_count: usize) -> bool {
-        let cp = self.pos;
-        for _ in 0..byte_count {
-            if self.buf[self.pos] != 0 {
-                self.pos = cp + byte_count;
-                return false;
-            } else {
-                self.pos += 1;
+    /// <p>Moves the position like <code>std::io::Seek</code>, returning the new one.  Seeking past the end is allowed;
+    /// seeking before the start isn't.</p>
+    pub fn seek(&mut self, to: SeekFrom) -> Result<usize, Error> {
+        let target = match to {
+            SeekFrom::Start(n) => n as i128,
+            SeekFrom::End(n) => self.buf.len() as i128 + n as i128,
+            SeekFrom::Current(n) => self.pos as i128 + n as i128,
+        };
+        match usize::try_from(target).ok() {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
             }
+            None => Err(Error::InvalidSeek { offset: self.base + self.pos, to }),
         }
-        true
+    }
+
+    /// <p>A new stream over <code>range</code> of this one, positioned at its start.  Offsets in errors from the new
+    /// stream still count from the start of this one.</p>
+    /// <p>The bytes are copied, so the new stream doesn't borrow this one; slice only as much as gets parsed.</p>
+    pub fn slice(&self, range: Range<usize>) -> Result<ByteStream, Error> {
+        if range.end < range.start {
+            return Err(Error::InvalidRange { start: self.base + range.start, end: self.base + range.end });
+        }
+        let bytes = self.range(range.start, range.end - range.start)?;
+        Ok(ByteStream { buf: bytes.to_vec(), skip: Vec::new(), base: self.base + range.start, pos: 0 })
+    }
+
+    /// <p>The <code>n</code> bytes at <code>pos</code>, or <code>Error::Truncated</code> if the buffer ends first.</p>
+    fn range(&self, pos: usize, n: usize) -> Result<&[u8], Error> {
+        pos.checked_add(n)
+            .and_then(|end| self.buf.get(pos..end))
+            .ok_or(Error::Truncated { offset: self.base + pos, needed: n })
+    }
+
+    pub fn check_reserved(&mut self, byte_count: usize) -> Result<bool, Error> {
+        let reserved = self.range(self.pos, byte_count)?.iter().all(|b| *b == 0);
+        self.pos += byte_count;
+        Ok(reserved)
     }
 
     //pub fn skip_bytes_at(&mut self, bytes: &mut Vec<u8>) {
     //    self.skip.append(bytes);
     //}
     // Use the top one if Vec values are not needed when using this function.
-    pub fn skip_bytes_at(&mut self, bytes: &Vec<u8>) {
-        let mut copy = bytes.clone();
-        self.skip.append(&mut copy);
+    pub fn skip_bytes_at(&mut self, bytes: &[u8]) {
+        self.skip.extend_from_slice(bytes);
     }
 
-    pub fn read_byte(&mut self) -> u8 {
-        let byte = self.buf[self.pos];
+    pub fn read_byte(&mut self) -> Result<u8, Error> {
+        let byte = self.peek_byte()?;
         self.pos += 1;
-        byte
+        Ok(byte)
     }
-    pub fn peek_byte(&mut self) -> u8 {
-        self.buf[self.pos]
+    pub fn peek_byte(&self) -> Result<u8, Error> {
+        self.read_byte_at(self.pos)
     }
-    pub fn read_byte_at(&self, pos: usize) -> u8 {
-        self.buf[pos]
+    pub fn read_byte_at(&self, pos: usize) -> Result<u8, Error> {
+        Ok(self.range(pos, 1)?[0])
     }
-    pub fn read_bytes(&mut self, n: usize) -> Vec<u8> {
-        let mut ret = Vec::new();
-        for _ in 0..n {
-            ret.push(self.buf[self.pos]);
-            self.pos += 1;
-        }
-        ret
+    pub fn read_bytes(&mut self, n: usize) -> Result<Vec<u8>, Error> {
+        let ret = self.peek_bytes(n)?;
+        self.pos += n;
+        Ok(ret)
     }
-    pub fn peek_bytes(&mut self, n: usize) -> Vec<u8> {
-        let mut ret = Vec::new();
-        for i in 0..n {
-            ret.push(self.buf[self.pos + i]);
-        }
-        ret
+    pub fn peek_bytes(&self, n: usize) -> Result<Vec<u8>, Error> {
+        self.read_bytes_at(n, self.pos)
     }
-    pub fn read_bytes_at(&self, n: usize, pos: usize) -> Vec<u8> {
-        let mut ret = Vec::new();
-        for i in 0..n {
-            ret.push(self.buf[pos + i]);
-        }
-        ret
+    pub fn read_bytes_at(&self, n: usize, pos: usize) -> Result<Vec<u8>, Error> {
+        Ok(self.range(pos, n)?.to_vec())
     }
 
-    pub fn read_sbyte(&mut self) -> i8 {
-        let byte = self.buf[self.pos] as i8;
-        self.pos += 1;
-        byte
+    pub fn read_sbyte(&mut self) -> Result<i8, Error> {
+        Ok(self.read_byte()? as i8)
     }
 
-    pub fn read_word(&mut self) -> u16 {
-        let b1 = self.read_byte() as u16;
-        let b2 = self.read_byte() as u16;
-        b2 << 8 | b1
+    pub fn read_word(&mut self) -> Result<u16, Error> {
+        let word = self.peek_word()?;
+        self.pos += 2;
+        Ok(word)
     }
-    pub fn peek_word(&self) -> u16 {
-        ((self.buf[self.pos + 1] as u16) << 8) | (self.buf[self.pos] as u16)
+    pub fn peek_word(&self) -> Result<u16, Error> {
+        self.read_word_at(self.pos)
     }
-    pub fn read_word_at(&self, pos: usize) -> u16 {
-        ((self.buf[pos + 1] as u16) << 8) | (self.buf[pos] as u16)
+    pub fn read_word_at(&self, pos: usize) -> Result<u16, Error> {
+        let bytes = self.range(pos, 2)?;
+        Ok(((bytes[1] as u16) << 8) | (bytes[0] as u16))
     }
 
-    pub fn read_sword(&mut self) -> i16 {
-        let b1 = self.read_byte() as u16;
-        let b2 = self.read_byte() as u16;
-        (b2 << 8 | b1) as i16
+    pub fn read_sword(&mut self) -> Result<i16, Error> {
+        Ok(self.read_word()? as i16)
+    }
+
+    pub fn read_dword(&mut self) -> Result<u32, Error> {
+        self.read_u32_le()
+    }
+    pub fn read_dword_at(&self, pos: usize) -> Result<u32, Error> {
+        let bytes = self.range(pos, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
-    pub fn read_dword(&mut self) -> u32 {
-        let w1 = self.read_word() as u32;
-        let w2 = self.read_word() as u32;
-        w2 << 16 | w1
+    typed_read! {
+        read_u16_le: u16 = from_le_bytes;
+        read_u16_be: u16 = from_be_bytes;
+        read_i16_le: i16 = from_le_bytes;
+        read_i16_be: i16 = from_be_bytes;
+        read_u32_le: u32 = from_le_bytes;
+        read_u32_be: u32 = from_be_bytes;
+        read_i32_le: i32 = from_le_bytes;
+        read_i32_be: i32 = from_be_bytes;
+        read_u64_le: u64 = from_le_bytes;
+        read_u64_be: u64 = from_be_bytes;
+        read_i64_le: i64 = from_le_bytes;
+        read_i64_be: i64 = from_be_bytes;
     }
 
-    pub fn read_string(&mut self, len: usize) -> String {
-        String::from_utf8_lossy(&self.read_bytes(len)).to_string()
+    pub fn read_string(&mut self, len: usize) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(&self.read_bytes(len)?).to_string())
     }
-    pub fn read_string_from_to(&self, from: usize, to: usize) -> String {
-        String::from_utf8_lossy(&self.buf[from..to]).to_string()
+    pub fn read_string_from_to(&self, from: usize, to: usize) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(self.range(from, to.saturating_sub(from))?).to_string())
     }
 
-    pub fn replace_byte(&mut self, offset: usize, b: u8) {
+    pub fn replace_byte(&mut self, offset: usize, b: u8) -> Result<(), Error> {
+        self.range(offset, 1)?;
         self.buf[offset] = b;
+        Ok(())
     }
-    pub fn replace_word(&mut self, offset: usize, w: u16) {
+    pub fn replace_word(&mut self, offset: usize, w: u16) -> Result<(), Error> {
+        self.range(offset, 2)?;
         self.buf[offset] = (w & 0xFF) as u8;
         self.buf[offset + 1] = (w >> 8) as u8;
+        Ok(())
+    }
+
+    /// <p>The first position at or after <code>from</code> holding <code>byte</code>.</p>
+    pub fn find_byte(&self, from: usize, byte: u8) -> Option<usize> {
+        self.buf.get(from..)?.iter().position(|b| *b == byte).map(|i| from + i)
+    }
+    /// <p>The last position before <code>before</code> holding <code>byte</code>.</p>
+    pub fn rfind_byte(&self, before: usize, byte: u8) -> Option<usize> {
+        self.buf[..before.min(self.buf.len())].iter().rposition(|b| *b == byte)
+    }
+
+    /// <p>The first position at or after <code>from</code> where <code>needle</code> starts.</p>
+    pub fn find_bytes(&self, from: usize, needle: &[u8]) -> Option<usize> {
+        self.find_pattern(from, &exact(needle))
+    }
+    /// <p>The last position where <code>needle</code> starts and ends before <code>before</code>.</p>
+    pub fn rfind_bytes(&self, before: usize, needle: &[u8]) -> Option<usize> {
+        self.rfind_pattern(before, &exact(needle))
+    }
+
+    /// <p>The first position at or after <code>from</code> matching <code>pattern</code>, where <code>None</code>
+    /// matches any byte.  See <code>parse_pattern</code> for writing one as text.</p>
+    pub fn find_pattern(&self, from: usize, pattern: &[Option<u8>]) -> Option<usize> {
+        if pattern.is_empty() {
+            return (from <= self.buf.len()).then_some(from);
+        }
+        self.buf.get(from..)?.windows(pattern.len()).position(|w| matches(w, pattern)).map(|i| from + i)
+    }
+    /// <p>The last position matching <code>pattern</code> with the match ending before <code>before</code>.</p>
+    pub fn rfind_pattern(&self, before: usize, pattern: &[Option<u8>]) -> Option<usize> {
+        let haystack = &self.buf[..before.min(self.buf.len())];
+        if pattern.is_empty() {
+            return Some(haystack.len());
+        }
+        haystack.windows(pattern.len()).rposition(|w| matches(w, pattern))
+    }
+
+    /// <p>Every position matching <code>pattern</code>, overlapping matches included.</p>
+    pub fn find_all(&self, pattern: &[Option<u8>]) -> Vec<usize> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        self.buf
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, w)| matches(w, pattern))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+fn exact(needle: &[u8]) -> Vec<Option<u8>> {
+    needle.iter().copied().map(Some).collect()
+}
+
+fn matches(window: &[u8], pattern: &[Option<u8>]) -> bool {
+    window.iter().zip(pattern).all(|(b, p)| p.is_none_or(|p| p == *b))
+}
+
+/// <p>Reads a search pattern written as hex bytes separated by spaces, with <code>?</code> or <code>??</code> for a
+/// byte that can be anything: <code>"55 8B EC ?? 83"</code>.  Returns <code>None</code> if a byte isn't valid
+/// hex.</p>
+pub fn parse_pattern(text: &str) -> Option<Vec<Option<u8>>> {
+    text.split_whitespace()
+        .map(|token| match token {
+            "?" | "??" => Some(None),
+            hex => u8::from_str_radix(hex, 16).ok().map(Some),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_forwards_and_backwards() {
+        let bst = ByteStream::new(vec![0x55, 0x8B, 0xEC, 0x55, 0x8B, 0xEC, 0x83, 0x00]);
+        assert_eq!(bst.find_byte(1, 0x55), Some(3));
+        assert_eq!(bst.rfind_byte(3, 0x55), Some(0));
+        assert_eq!(bst.find_bytes(1, &[0x55, 0x8B]), Some(3));
+        assert_eq!(bst.rfind_bytes(5, &[0x55, 0x8B]), Some(3));
+        assert_eq!(bst.rfind_bytes(4, &[0x55, 0x8B]), Some(0));
+        assert_eq!(bst.find_bytes(9, &[0x55]), None);
+
+        let pattern = parse_pattern("55 8B ?? ? 8B").unwrap();
+        assert_eq!(pattern, [Some(0x55), Some(0x8B), None, None, Some(0x8B)]);
+        assert_eq!(bst.find_pattern(0, &pattern), Some(0));
+        assert_eq!(bst.find_all(&parse_pattern("8b ??").unwrap()), [1, 4]);
+        assert_eq!(parse_pattern("55 8G"), None);
     }
 
-    pub fn find_first_byte_from(&self, pos: usize, to_find: u8) -> usize {
-        self.buf[pos..].binary_search(&to_find).unwrap() + pos
+    #[test]
+    fn reads_past_the_end_are_an_error() {
+        let mut bst = ByteStream::new(vec![1, 2, 3]);
+        assert_eq!(bst.read_word().unwrap(), 0x0201);
+        assert!(bst.read_word().is_err());
+        assert!(bst.read_word_at(2).is_err());
+        assert!(bst.slice(2..4).is_err());
+        assert!(matches!(bst.slice(Range { start: 2, end: 1 }), Err(Error::InvalidRange { start: 2, end: 1 })));
+        assert_eq!(bst.slice(1..3).unwrap().as_slice(), &[2, 3]);
     }
 }
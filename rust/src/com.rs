@@ -0,0 +1,101 @@
+use crate::{
+    apis::dos::{Startup, MEMORY_TOP_SEGMENT},
+    byte_operation::{
+        cfg::ControlFlowGraph,
+        trace::{trace_code, Disassembly},
+        x86_16::Cpu,
+    },
+    error::Error,
+    memory::Memory,
+};
+
+/// <p>Where a .COM image goes in its segment: right after the PSP, which shares the segment.</p>
+pub const COM_ORIGIN: u16 = 0x100;
+/// <p>The biggest image that fits in one segment after the PSP and still leaves DOS room for the word it pushes.</p>
+pub const MAX_COM_SIZE: usize = 0xFF00 - 2;
+
+/// <p>Signatures of the other formats.  A file starting with one is a header, or a piece of a file, rather than code
+/// meant to run from its first byte.</p>
+const OTHER_SIGNATURES: [&[u8]; 6] = [b"MZ", b"ZM", b"NE", b"PE\0\0", b"LE", b"LX"];
+
+/// <p>A headerless DOS program: the whole file is code and data, loaded at <code>PSP:0100</code> with every segment
+/// register at the PSP.  There is nothing in one to tell it apart from any other file, so it is only read as one
+/// when asked to, or when its name ends in .COM.</p>
+pub struct Com;
+
+impl Com {
+    /// <p>Whether <code>bytes</code> could be a .COM image: it fits in a segment and doesn't start with the signature
+    /// of another format.</p>
+    pub fn fits(bytes: &[u8]) -> bool {
+        !bytes.is_empty()
+            && bytes.len() <= MAX_COM_SIZE
+            && !OTHER_SIGNATURES.iter().any(|signature| bytes.starts_with(signature))
+    }
+
+    /// <p>Disassembles the image by following control flow from its first byte.  Addresses are offsets in the
+    /// file; add <code>COM_ORIGIN</code> for the offset in CS.</p>
+    pub fn disassemble(bytes: &[u8]) -> Disassembly {
+        trace_code(bytes, &[0], false)
+    }
+    /// <p>Basic blocks and functions of the image, traced from its first byte.</p>
+    pub fn control_flow_graph(bytes: &[u8]) -> ControlFlowGraph {
+        ControlFlowGraph::build(&Self::disassemble(bytes), &[0], false)
+    }
+
+    /// <p>Loads the program the way DOS does: the environment and PSP are written, the image goes at
+    /// <code>PSP:0100</code>, and a zero word is pushed at the top of the segment so a plain <code>ret</code> ends up
+    /// at the <code>int 20h</code> at the start of the PSP.</p>
+    pub fn load(bytes: &[u8], mem: &mut Memory, cpu: &mut Cpu, startup: &Startup) -> Result<(), Error> {
+        if !Self::fits(bytes) {
+            return Err(Error::InvalidHeader { field: "COM image" });
+        }
+        startup.write(mem, MEMORY_TOP_SEGMENT);
+        let psp = startup.psp_segment;
+        mem.write_bytes(psp, COM_ORIGIN, bytes);
+        mem.write_word(psp, 0xFFFE, 0);
+        startup.start(cpu, (psp, COM_ORIGIN), (psp, 0xFFFE));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_rejects_other_formats() {
+        assert!(Com::fits(&[0xB4, 0x4C, 0xCD, 0x21]));
+        assert!(!Com::fits(&[]));
+        assert!(!Com::fits(&vec![0x90; MAX_COM_SIZE + 1]));
+        for signature in [&b"MZ\x90\x00"[..], b"ZM\x90\x00", b"NE\x05\x0A", b"PE\0\0\x4C\x01", b"LE\0\0", b"LX\0\0"] {
+            assert!(!Com::fits(signature), "{signature:?}");
+        }
+        // "PE" alone is push ax / inc bp
+        assert!(Com::fits(b"PE\x90\xC3"));
+    }
+
+    #[test]
+    fn load_writes_the_psp_and_starts_at_0100() {
+        let (mut memory, mut cpu) = (Memory::new(), Cpu::new());
+        let mut startup = Startup::new("C:\\HELLO.COM");
+        startup.command_tail = " /x".to_string();
+        Com::load(&[0xCD, 0x20], &mut memory, &mut cpu, &startup).unwrap();
+
+        let psp = startup.psp_segment;
+        assert_eq!((cpu.cs, cpu.ds, cpu.es, cpu.ss), (psp, psp, psp, psp));
+        assert_eq!((cpu.ip, cpu.sp), (COM_ORIGIN, 0xFFFE));
+        assert_eq!(memory.read_bytes(psp, COM_ORIGIN, 2), [0xCD, 0x20]);
+        assert_eq!(memory.read_word(psp, 0xFFFE), 0);
+
+        assert_eq!(memory.read_bytes(psp, 0x00, 2), [0xCD, 0x20]);
+        assert_eq!(memory.read_word(psp, 0x02), MEMORY_TOP_SEGMENT);
+        assert_eq!(memory.read_bytes(psp, 0x18, 6), [1, 1, 1, 0, 2, 0xFF]);
+        assert_eq!(memory.read_word(psp, 0x2C), startup.environment_segment());
+        assert_eq!(memory.read_bytes(psp, 0x50, 3), [0xCD, 0x21, 0xCB]);
+        assert_eq!(memory.read_bytes(psp, 0x5D, 11), [b' '; 11]);
+        assert_eq!(memory.read_bytes(psp, 0x80, 5), [3, b' ', b'/', b'x', b'\r']);
+
+        let environment = memory.read_bytes(startup.environment_segment(), 0, 25);
+        assert_eq!(environment, b"PATH=C:\\\0\0\x01\0C:\\HELLO.COM\0");
+    }
+}
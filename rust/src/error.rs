@@ -0,0 +1,78 @@
+use std::{
+    fmt,
+    io::{self, SeekFrom},
+};
+
+use crate::executable::Signature;
+
+/// <p>Everything that can go wrong reading, decoding or running an executable.</p>
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// <p>A read of <code>needed</code> bytes at <code>offset</code> ran past the end of the data.</p>
+    Truncated { offset: usize, needed: usize },
+    /// <p>A seek from <code>offset</code> would have gone before the start of the data.</p>
+    InvalidSeek { offset: usize, to: SeekFrom },
+    /// <p>A range of the data that ends before it starts.</p>
+    InvalidRange { start: usize, end: usize },
+    /// <p>The signature at <code>offset</code> isn't one this crate reads.</p>
+    BadMagic { offset: usize, found: u16 },
+    /// <p>A known executable format that isn't supported yet.</p>
+    UnsupportedFormat(Signature),
+    /// <p>A header field holds a value that can't be right.</p>
+    InvalidHeader { field: &'static str },
+    /// <p>A relative virtual address that no part of the file is mapped to.</p>
+    UnmappedRva { rva: u32 },
+    /// <p>Resource data that doesn't have the layout its type calls for.</p>
+    InvalidResource { reason: &'static str },
+    /// <p>There is no resource of that type and id, like an icon a group refers to that isn't in the file.</p>
+    ResourceNotFound { resource: String },
+    /// <p>The emulator has no implementation for the instruction at <code>addr</code>.</p>
+    UnsupportedOpcode { addr: usize, byte: u8 },
+    /// <p>An interrupt service the emulated API doesn't provide.</p>
+    UnsupportedInterrupt { vector: u8, function: u8 },
+    /// <p>An instruction was given an operand it can't take, such as an immediate destination.</p>
+    InvalidOperand { addr: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Truncated { offset, needed } => {
+                write!(f, "data ends before the {needed} byte(s) needed at offset 0x{offset:X}")
+            }
+            Error::InvalidSeek { offset, to } => write!(f, "can't seek {to:?} from offset 0x{offset:X}"),
+            Error::InvalidRange { start, end } => write!(f, "range 0x{start:X}..0x{end:X} ends before it starts"),
+            Error::BadMagic { offset, found } => {
+                let bytes = found.to_le_bytes();
+                write!(f, "unknown signature {:?} at offset 0x{offset:X}", String::from_utf8_lossy(&bytes))
+            }
+            Error::UnsupportedFormat(signature) => write!(f, "{signature:?} executables are not supported"),
+            Error::InvalidHeader { field } => write!(f, "invalid header field: {field}"),
+            Error::UnmappedRva { rva } => write!(f, "RVA 0x{rva:X} isn't mapped to the file"),
+            Error::InvalidResource { reason } => write!(f, "invalid resource: {reason}"),
+            Error::ResourceNotFound { resource } => write!(f, "no resource {resource}"),
+            Error::UnsupportedOpcode { addr, byte } => write!(f, "unsupported opcode 0x{byte:02X} at 0x{addr:X}"),
+            Error::UnsupportedInterrupt { vector, function } => {
+                write!(f, "unsupported interrupt 0x{vector:02X}, function 0x{function:02X}")
+            }
+            Error::InvalidOperand { addr } => write!(f, "invalid operand for the instruction at 0x{addr:X}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
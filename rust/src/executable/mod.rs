@@ -1,17 +1,39 @@
-use std::{fs::File, io::{Error, Read}, path::Path};
+use std::{any::Any, fs::File, io::Read, path::Path};
 
 use ne::NewExecutable;
+use pe::PortableExecutable;
+use resource::version::VersionInfo;
 
-use crate::{byte_stream::ByteStream, mz::MZ};
+use crate::{
+    apis::dos::Startup,
+    byte_operation::{
+        cfg::ControlFlowGraph,
+        nasm::{com_to_nasm, mz_to_nasm},
+        trace::Disassembly,
+        x86_16::Cpu,
+    },
+    byte_stream::ByteStream,
+    com::Com,
+    error::Error,
+    memory::Memory,
+    mz::MZ,
+};
 
 pub mod ne;
+pub mod pe;
+pub mod resource;
 
 pub enum InteruptChange {
     None,
     String(u16, u16),
+    /// The program ended, with this exit code.
+    Exit(u8),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Signature {
+    /// A headerless .COM program.
+    COM,
     MZ,
     NE,
     LE,
@@ -20,37 +42,124 @@ pub enum Signature {
 }
 
 pub trait ExecutableType {
-    fn signature(&self) -> Signature where Self: Sized;
-    fn read(bst: &mut ByteStream) -> Self where Self: Sized;
+    fn signature(&self) -> Signature;
+    fn read(bst: &mut ByteStream) -> Result<Self, Error> where Self: Sized;
+    /// <p>For getting the concrete header back out of <code>Executable::executable</code>.</p>
+    fn as_any(&self) -> &dyn Any;
 }
 
 pub struct Executable {
-    pub header: MZ,
-    pub executable: Box<dyn ExecutableType>,
+    /// The DOS header, or <code>None</code> for a .COM program.
+    pub header: Option<MZ>,
+    /// The format the DOS header points to, or <code>None</code> for a plain DOS program.
+    pub executable: Option<Box<dyn ExecutableType>>,
+    /// The whole file, kept around for anything that reads past the headers.
+    pub bst: ByteStream,
 }
 
 impl Executable {
+    /// <p>Reads an executable file.  An MZ file without an extended header it can find is a plain DOS program with
+    /// no <code>executable</code>.  A file without an MZ signature is only read as a .COM program when its name ends
+    /// in .COM; use <code>read_com</code> for one named anything else.</p>
     pub fn read<P: AsRef<Path>>(file_name: P) -> Result<Executable, Error> {
-        match File::open(file_name) {
-            Ok(mut file) => {
-                let mut buf = Vec::new();
-                file.read_to_end(&mut buf).unwrap();
-                let mut bst = ByteStream::new(buf);
-                let mz = MZ::read(&mut bst);
-                let exe_magic = bst.peek_word().to_ne_bytes();
-                let executable = Box::new(match &exe_magic {
-                    b"NE" => {
-                        NewExecutable::read(&mut bst)
-                    }
-                    &_ => {
-                        return Err(Error::last_os_error())
-                    }
-                });
-                Ok(Executable { header: mz, executable })
-            }
-            Err(e) => {
-                Err(e)
+        let named_com = file_name.as_ref().extension().is_some_and(|e| e.eq_ignore_ascii_case("com"));
+        let mut bst = ByteStream::new(Self::read_file(file_name)?);
+        let magic = bst.peek_bytes(2).unwrap_or_default();
+        if named_com && magic != b"MZ" && magic != b"ZM" && Com::fits(bst.as_slice()) {
+            return Ok(Executable { header: None, executable: None, bst });
+        }
+        let mz = MZ::read(&mut bst)?;
+        let Some(offset) = mz.extension_offset(&bst) else {
+            return Ok(Executable { header: Some(mz), executable: None, bst });
+        };
+        bst.pos = offset;
+        let exe_magic = bst.peek_word()?;
+        let executable: Box<dyn ExecutableType> = match &exe_magic.to_le_bytes() {
+            b"NE" => Box::new(NewExecutable::read(&mut bst)?),
+            b"PE" => Box::new(PortableExecutable::read(&mut bst)?),
+            b"LE" => return Err(Error::UnsupportedFormat(Signature::LE)),
+            b"LX" => return Err(Error::UnsupportedFormat(Signature::LX)),
+            &_ => {
+                return Err(Error::BadMagic { offset: bst.pos, found: exe_magic })
             }
+        };
+        Ok(Executable { header: Some(mz), executable: Some(executable), bst })
+    }
+
+    /// <p>Reads a file as a .COM program, whatever it is named.  Fails if it can't be one.</p>
+    pub fn read_com<P: AsRef<Path>>(file_name: P) -> Result<Executable, Error> {
+        let buf = Self::read_file(file_name)?;
+        if !Com::fits(&buf) {
+            return Err(Error::InvalidHeader { field: "COM image" });
+        }
+        Ok(Executable { header: None, executable: None, bst: ByteStream::new(buf) })
+    }
+
+    fn read_file<P: AsRef<Path>>(file_name: P) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        File::open(file_name)?.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn signature(&self) -> Signature {
+        match (&self.header, &self.executable) {
+            (_, Some(executable)) => executable.signature(),
+            (Some(_), None) => Signature::MZ,
+            (None, None) => Signature::COM,
+        }
+    }
+
+    /// <p>The NE header, if this is an NE file.</p>
+    pub fn new_executable(&self) -> Option<&NewExecutable> {
+        self.executable.as_ref()?.as_any().downcast_ref()
+    }
+
+    /// <p>The PE headers, if this is a PE file.</p>
+    pub fn portable_executable(&self) -> Option<&PortableExecutable> {
+        self.executable.as_ref()?.as_any().downcast_ref()
+    }
+
+    /// <p>The version resource, for the formats that have resources.</p>
+    pub fn version_info(&self) -> Result<Option<VersionInfo>, Error> {
+        if let Some(ne) = self.new_executable() {
+            return ne.version_info(&self.bst);
+        }
+        match self.portable_executable() {
+            Some(pe) => pe.version_info(&self.bst),
+            None => Ok(None),
+        }
+    }
+
+    /// <p>The DOS program's code, traced from its entry point.  For NE and PE files that is the DOS stub.</p>
+    pub fn disassemble(&self) -> Disassembly {
+        match &self.header {
+            Some(mz) => mz.disassemble(&self.bst),
+            None => Com::disassemble(self.bst.as_slice()),
+        }
+    }
+    /// <p>Basic blocks and functions of the DOS program's code.</p>
+    pub fn control_flow_graph(&self) -> ControlFlowGraph {
+        match &self.header {
+            Some(mz) => mz.control_flow_graph(&self.bst),
+            None => Com::control_flow_graph(self.bst.as_slice()),
+        }
+    }
+
+    /// <p>Loads the DOS program into <code>mem</code> and sets up <code>cpu</code> to run it.  For NE and PE files
+    /// that is the DOS stub.  See <code>MZ::load</code> and <code>Com::load</code>.</p>
+    pub fn load(&self, mem: &mut Memory, cpu: &mut Cpu, startup: &Startup) -> Result<(), Error> {
+        match &self.header {
+            Some(mz) => mz.load(&self.bst, mem, cpu, startup),
+            None => Com::load(self.bst.as_slice(), mem, cpu, startup),
+        }
+    }
+
+    /// <p>The whole file as NASM source that assembles back into it.  See <code>mz_to_nasm</code> and
+    /// <code>com_to_nasm</code>.</p>
+    pub fn to_nasm(&self) -> String {
+        match &self.header {
+            Some(mz) => mz_to_nasm(mz, &self.bst),
+            None => com_to_nasm(self.bst.as_slice()),
         }
     }
 }
@@ -0,0 +1,69 @@
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>Bundle segment indicator for a run of ordinals with no entries.</p>
+const UNUSED: u8 = 0x00;
+/// <p>Bundle segment indicator for constants exported by the module rather than addresses.</p>
+pub const CONSTANT_SEGMENT: u8 = 0xFE;
+/// <p>Bundle segment indicator for entries in movable segments, which each name their own segment.</p>
+const MOVABLE: u8 = 0xFF;
+
+/// <p>An entry point from the entry table.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub ordinal: u16,
+    /// The segment, counting from 1, or <code>CONSTANT_SEGMENT</code> with the value in <code>offset</code>.
+    pub segment: u8,
+    pub offset: u16,
+    /// Reached through an <code>int 3Fh</code> thunk so the segment can move, rather than directly.
+    pub movable: bool,
+    pub exported: bool,
+    /// Uses the global (shared) data segment.
+    pub shared_data: bool,
+    /// How many words of parameters to copy onto the stack when calling through a ring transition (OS/2).
+    pub parameter_words: u8,
+}
+
+/// <p>Reads the entry table at the current position, up to a zero count or <code>end</code>.  Entries are bundled
+/// by segment, and ordinals count up from 1 across the bundles, unused ones included.</p>
+pub fn read_entries(bst: &mut ByteStream, end: usize) -> Result<Vec<Entry>, Error> {
+    let mut entries = Vec::new();
+    let mut ordinal: u16 = 1;
+    while bst.pos < end {
+        let count = bst.read_byte()?;
+        if count == 0 {
+            break;
+        }
+        let indicator = bst.read_byte()?;
+        for _ in 0..count {
+            let entry = match indicator {
+                UNUSED => None,
+                MOVABLE => {
+                    let flags = bst.read_byte()?;
+                    // int 3Fh
+                    bst.read_word()?;
+                    let segment = bst.read_byte()?;
+                    Some(entry(ordinal, flags, segment, bst.read_word()?, true))
+                }
+                segment => {
+                    let flags = bst.read_byte()?;
+                    Some(entry(ordinal, flags, segment, bst.read_word()?, false))
+                }
+            };
+            entries.extend(entry);
+            ordinal = ordinal.wrapping_add(1);
+        }
+    }
+    Ok(entries)
+}
+
+fn entry(ordinal: u16, flags: u8, segment: u8, offset: u16, movable: bool) -> Entry {
+    Entry {
+        ordinal,
+        segment,
+        offset,
+        movable,
+        exported: flags & 1 != 0,
+        shared_data: flags & 2 != 0,
+        parameter_words: flags >> 3,
+    }
+}
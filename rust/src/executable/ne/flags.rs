@@ -0,0 +1,214 @@
+/// <p>How the automatic data segment (DGROUP) is shared, bits 0-1 of the program flags.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataSegmentType {
+    /// NOAUTODATA: there is no automatic data segment.
+    None,
+    /// SINGLEDATA: one data segment shared by every instance, as libraries have.
+    Single,
+    /// MULTIPLEDATA: each instance gets its own, as applications have.
+    Multiple,
+    Unknown,
+}
+
+/// <p>The low byte of the NE flag word.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgramFlags {
+    pub data: DataSegmentType,
+    /// The library's initialization routine is run for every process that loads it.
+    pub per_process_init: bool,
+    pub protected_mode_only: bool,
+    pub i8086: bool,
+    pub i286: bool,
+    pub i386: bool,
+    pub x87: bool,
+}
+
+impl ProgramFlags {
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            data: match bits & 0b11 {
+                0 => DataSegmentType::None,
+                1 => DataSegmentType::Single,
+                2 => DataSegmentType::Multiple,
+                _ => DataSegmentType::Unknown,
+            },
+            per_process_init: bits & (1 << 2) != 0,
+            protected_mode_only: bits & (1 << 3) != 0,
+            i8086: bits & (1 << 4) != 0,
+            i286: bits & (1 << 5) != 0,
+            i386: bits & (1 << 6) != 0,
+            x87: bits & (1 << 7) != 0,
+        }
+    }
+    pub fn bits(&self) -> u8 {
+        (match self.data {
+            DataSegmentType::None => 0,
+            DataSegmentType::Single => 1,
+            DataSegmentType::Multiple => 2,
+            DataSegmentType::Unknown => 3,
+        }) | (if self.per_process_init { 1 << 2 } else { 0 })
+            | (if self.protected_mode_only { 1 << 3 } else { 0 })
+            | (if self.i8086 { 1 << 4 } else { 0 })
+            | (if self.i286 { 1 << 5 } else { 0 })
+            | (if self.i386 { 1 << 6 } else { 0 })
+            | (if self.x87 { 1 << 7 } else { 0 })
+    }
+}
+
+/// <p>What kind of screen an application expects, bits 0-2 of the application flags.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApplicationType {
+    None,
+    /// A full-screen text program that can't run in a window.
+    FullScreen,
+    /// A text program that can run in a window.
+    WindowCompatible,
+    /// Uses the Windows or Presentation Manager API.
+    WindowApi,
+    Other(u8),
+}
+
+/// <p>The high byte of the NE flag word.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApplicationFlags {
+    pub application_type: ApplicationType,
+    pub os2_family: bool,
+    /// The linker reported errors, so the image may not load.
+    pub link_errors: bool,
+    pub non_conforming: bool,
+    /// A DLL or driver rather than an application.
+    pub library: bool,
+}
+
+impl ApplicationFlags {
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            application_type: match bits & 0b111 {
+                0 => ApplicationType::None,
+                1 => ApplicationType::FullScreen,
+                2 => ApplicationType::WindowCompatible,
+                3 => ApplicationType::WindowApi,
+                other => ApplicationType::Other(other),
+            },
+            os2_family: bits & (1 << 3) != 0,
+            link_errors: bits & (1 << 5) != 0,
+            non_conforming: bits & (1 << 6) != 0,
+            library: bits & (1 << 7) != 0,
+        }
+    }
+    pub fn bits(&self) -> u8 {
+        (match self.application_type {
+            ApplicationType::None => 0,
+            ApplicationType::FullScreen => 1,
+            ApplicationType::WindowCompatible => 2,
+            ApplicationType::WindowApi => 3,
+            ApplicationType::Other(other) => other & 0b111,
+        }) | (if self.os2_family { 1 << 3 } else { 0 })
+            | (if self.link_errors { 1 << 5 } else { 0 })
+            | (if self.non_conforming { 1 << 6 } else { 0 })
+            | (if self.library { 1 << 7 } else { 0 })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetOs {
+    Unknown,
+    Os2,
+    Windows,
+    /// European multitasking MS-DOS 4.x.
+    Dos4,
+    Windows386,
+    /// Borland Operating System Services.
+    Boss,
+    Other(u8),
+}
+
+impl TargetOs {
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => TargetOs::Unknown,
+            1 => TargetOs::Os2,
+            2 => TargetOs::Windows,
+            3 => TargetOs::Dos4,
+            4 => TargetOs::Windows386,
+            5 => TargetOs::Boss,
+            other => TargetOs::Other(other),
+        }
+    }
+}
+
+/// <p>The "other flags" byte after the target OS, mostly OS/2 specific.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdditionalFlags {
+    pub long_file_names: bool,
+    /// OS/2 2.x protected mode.
+    pub protected_mode: bool,
+    /// OS/2 2.x proportional fonts.
+    pub proportional_fonts: bool,
+    /// The file has a fast-load (gangload) area.
+    pub fast_load: bool,
+}
+
+impl AdditionalFlags {
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            long_file_names: bits & 1 != 0,
+            protected_mode: bits & (1 << 1) != 0,
+            proportional_fonts: bits & (1 << 2) != 0,
+            fast_load: bits & (1 << 3) != 0,
+        }
+    }
+}
+
+/// <p>The flag word of a segment table entry.  Bits the loader only sets at run time are left out.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentFlags {
+    /// A data segment rather than a code one.
+    pub data: bool,
+    /// The data is stored compressed as iterated records (OS/2).
+    pub iterated: bool,
+    pub movable: bool,
+    /// PURE: one copy can be shared between instances.
+    pub shareable: bool,
+    pub preload: bool,
+    /// Execute-only for a code segment, read-only for a data one.
+    pub read_only: bool,
+    /// Relocation records follow the segment data.
+    pub relocations: bool,
+    pub conforming: bool,
+    /// Descriptor privilege level (OS/2).
+    pub privilege_level: u8,
+    pub discardable: bool,
+}
+
+impl SegmentFlags {
+    pub fn from_bits(bits: u16) -> Self {
+        Self {
+            data: bits & 1 != 0,
+            iterated: bits & (1 << 3) != 0,
+            movable: bits & (1 << 4) != 0,
+            shareable: bits & (1 << 5) != 0,
+            preload: bits & (1 << 6) != 0,
+            read_only: bits & (1 << 7) != 0,
+            relocations: bits & (1 << 8) != 0,
+            conforming: bits & (1 << 9) != 0,
+            privilege_level: ((bits >> 10) & 0b11) as u8,
+            discardable: bits & (1 << 12) != 0,
+        }
+    }
+}
+
+/// <p>The flag word of a resource's name info.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResourceFlags {
+    pub movable: bool,
+    /// PURE: one copy can be shared between instances.
+    pub shareable: bool,
+    pub preload: bool,
+}
+
+impl ResourceFlags {
+    pub fn from_bits(bits: u16) -> Self {
+        Self { movable: bits & (1 << 4) != 0, shareable: bits & (1 << 5) != 0, preload: bits & (1 << 6) != 0 }
+    }
+}
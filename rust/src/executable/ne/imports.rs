@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use super::{
+    ordinals::ordinal_name,
+    segtable::{RelocationSource, RelocationTarget},
+    NewExecutable,
+};
+use crate::{byte_stream::ByteStream, error::Error, executable::resource::decode_ansi};
+
+/// <p>A place in the module's code or data that a relocation patches with an imported address.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixupSite {
+    pub segment: u16,
+    pub offset: u16,
+    pub source: RelocationSource,
+}
+
+/// <p>One function or variable imported from a module.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedFunction {
+    /// <code>None</code> when it is imported by name.
+    pub ordinal: Option<u16>,
+    /// The name it is imported by, or for an ordinal the name it is known to have.
+    pub name: Option<String>,
+    pub sites: Vec<FixupSite>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleImports {
+    pub module: String,
+    pub functions: Vec<ImportedFunction>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ImportKey {
+    Ordinal(u16),
+    Name(String),
+}
+
+/// <p>Reads a length-prefixed name at <code>at</code>.</p>
+pub(super) fn read_counted_name(bst: &ByteStream, at: usize) -> Result<String, Error> {
+    let len = bst.read_byte_at(at)? as usize;
+    Ok(decode_ansi(&bst.read_bytes_at(len, at + 1)?))
+}
+
+impl NewExecutable {
+    /// <p>A name from the imported names table, by its offset in the table.</p>
+    pub fn imported_name(&self, bst: &ByteStream, name_offset: u16) -> Result<String, Error> {
+        read_counted_name(bst, self.offset + self.imported_names_table_offset as usize + name_offset as usize)
+    }
+
+    /// <p>What every module in the module reference table is used for, found by going through the relocations of
+    /// every segment.  Each import lists the places it is patched in, with the whole chain followed for relocations
+    /// that aren't additive.</p>
+    pub fn imports(&self, bst: &ByteStream) -> Result<Vec<ModuleImports>, Error> {
+        let mut by_module: Vec<BTreeMap<ImportKey, Vec<FixupSite>>> =
+            vec![BTreeMap::new(); self.module_references.len()];
+        for (index, segment) in self.segments.iter().enumerate() {
+            let number = index as u16 + 1;
+            if segment.relocations.is_empty() {
+                continue;
+            }
+            let data = self.segment_data(bst, number)?;
+            for relocation in &segment.relocations {
+                let (module, key) = match relocation.target {
+                    RelocationTarget::ImportedOrdinal { module, ordinal } => (module, ImportKey::Ordinal(ordinal)),
+                    RelocationTarget::ImportedName { module, name_offset } => {
+                        (module, ImportKey::Name(self.imported_name(bst, name_offset)?))
+                    }
+                    _ => continue,
+                };
+                let functions = (module as usize)
+                    .checked_sub(1)
+                    .and_then(|i| by_module.get_mut(i))
+                    .ok_or(Error::InvalidHeader { field: "module reference index" })?;
+                functions.entry(key).or_default().extend(relocation.sources(data).into_iter().map(|offset| FixupSite {
+                    segment: number,
+                    offset,
+                    source: relocation.source,
+                }));
+            }
+        }
+
+        Ok(self
+            .module_references
+            .iter()
+            .zip(by_module)
+            .map(|(module, functions)| ModuleImports {
+                module: module.clone(),
+                functions: functions
+                    .into_iter()
+                    .map(|(key, sites)| match key {
+                        ImportKey::Ordinal(ordinal) => ImportedFunction {
+                            ordinal: Some(ordinal),
+                            name: ordinal_name(module, ordinal).map(str::to_string),
+                            sites,
+                        },
+                        ImportKey::Name(name) => ImportedFunction { ordinal: None, name: Some(name), sites },
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+}
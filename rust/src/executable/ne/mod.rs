@@ -1,14 +1,442 @@
+use std::any::Any;
+
+use entrytable::{Entry, CONSTANT_SEGMENT};
+use flags::{AdditionalFlags, ApplicationFlags, ProgramFlags, TargetOs};
+use names::EntryName;
+use restable::ResourceTable;
+use segtable::Segment;
+
 use super::{ExecutableType, Signature};
+use crate::{
+    byte_operation::{
+        cfg::ControlFlowGraph,
+        trace::{trace_code, Disassembly},
+    },
+    byte_stream::ByteStream,
+    error::Error,
+};
+
+pub mod entrytable;
+pub mod flags;
+pub mod imports;
+pub mod names;
+pub mod ordinals;
+pub mod restable;
+mod resources;
+pub mod segtable;
+
+pub use resources::Extraction;
 
+/// <p>The header Windows 1.x-3.x and OS/2 1.x add after the DOS stub.  Table offsets are relative to the start of
+/// this header, apart from <code>non_resident_names_table_offset</code>, which counts from the start of the file.</p>
 pub struct NewExecutable {
+    /// Where the header starts in the file, the <code>e_lfanew</code> of the DOS header.
+    pub offset: usize,
+    /// (major, minor)
+    pub linker_version: (u8, u8),
+    pub entry_table_offset: u16,
+    pub entry_table_length: u16,
+    pub crc: u32,
+    pub program_flags: ProgramFlags,
+    pub application_flags: ApplicationFlags,
+    /// One-based index into the segment table, 0 if there is none.
+    pub automatic_data_segment: u16,
+    pub initial_heap_size: u16,
+    pub initial_stack_size: u16,
+    pub cs: u16,
+    pub ip: u16,
+    pub ss: u16,
+    pub sp: u16,
+    pub segment_count: u16,
+    pub module_reference_count: u16,
+    pub non_resident_names_table_size: u16,
+    pub segment_table_offset: u16,
+    pub resource_table_offset: u16,
+    pub resident_names_table_offset: u16,
+    pub module_reference_table_offset: u16,
+    pub imported_names_table_offset: u16,
+    pub non_resident_names_table_offset: u32,
+    pub movable_entry_count: u16,
+    /// Segment and resource data offsets are stored in units of <code>1 << shift_count</code> bytes.
+    pub shift_count: u16,
+    pub resource_segment_count: u16,
+    pub target_os: TargetOs,
+    pub additional_flags: AdditionalFlags,
+    pub fast_load_offset: u16,
+    pub fast_load_length: u16,
+    pub min_code_swap_size: u16,
+    /// (major, minor)
+    pub expected_windows_version: (u8, u8),
+    /// Numbered from 1 everywhere else in the file, so segment <code>n</code> is <code>segments[n - 1]</code>.
+    pub segments: Vec<Segment>,
+    /// <code>None</code> if the file has no resource table.
+    pub resources: Option<ResourceTable>,
+    pub resident_names: Vec<EntryName>,
+    pub non_resident_names: Vec<EntryName>,
+    pub entries: Vec<Entry>,
+    /// The names of the modules this one imports from, which relocations number from 1.
+    pub module_references: Vec<String>,
+}
 
+/// <p>An entry point with the name it is exported under, if it has one.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    pub name: Option<String>,
+    /// The name is in the resident names table, so it can be looked up without reading the file again.
+    pub resident: bool,
+    pub entry: Entry,
+}
+
+impl NewExecutable {
+    /// <p>The program and application flags back as the flag word they were read from.</p>
+    pub fn flag_word(&self) -> u16 {
+        ((self.application_flags.bits() as u16) << 8) | self.program_flags.bits() as u16
+    }
+
+    /// <p>Segment <code>number</code>, counting from 1 as relocations and the entry table do.</p>
+    pub fn segment(&self, number: u16) -> Option<&Segment> {
+        self.segments.get((number as usize).checked_sub(1)?)
+    }
+
+    /// <p>The first resident name, which is the module's name.</p>
+    pub fn module_name(&self) -> Option<&str> {
+        self.resident_names.first().map(|n| n.name.as_str())
+    }
+    /// <p>The first non-resident name, which is the description the linker was given.</p>
+    pub fn description(&self) -> Option<&str> {
+        self.non_resident_names.first().map(|n| n.name.as_str())
+    }
+
+    /// <p>Every entry point, in ordinal order, with its name from either names table.</p>
+    pub fn exports(&self) -> Vec<Export> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let named = |names: &[EntryName]| {
+                    names.iter().skip(1).find(|n| n.ordinal == entry.ordinal).map(|n| n.name.clone())
+                };
+                let resident = named(&self.resident_names);
+                Export {
+                    resident: resident.is_some(),
+                    name: resident.or_else(|| named(&self.non_resident_names)),
+                    entry: *entry,
+                }
+            })
+            .collect()
+    }
+
+    /// <p>Offsets in segment <code>number</code> that code is known to start at: its entry points, and
+    /// <code>cs:ip</code> if the program starts there.</p>
+    pub fn entry_points(&self, number: u16) -> Vec<usize> {
+        let mut points: Vec<usize> = self
+            .entries
+            .iter()
+            .filter(|e| e.segment != CONSTANT_SEGMENT && e.segment as u16 == number)
+            .map(|e| e.offset as usize)
+            .collect();
+        if self.cs == number {
+            points.insert(0, self.ip as usize);
+        }
+        let mut seen = std::collections::BTreeSet::new();
+        points.retain(|p| seen.insert(*p));
+        points
+    }
+
+    /// <p>Disassembles segment <code>number</code> by following control flow from its entry points.  Far calls and
+    /// jumps aren't followed, since their targets are filled in by relocations.</p>
+    pub fn disassemble(&self, bst: &ByteStream, number: u16) -> Result<Disassembly, Error> {
+        Ok(trace_code(self.segment_data(bst, number)?, &self.entry_points(number), false))
+    }
+    /// <p>Basic blocks and functions of segment <code>number</code>, traced from its entry points.</p>
+    pub fn control_flow_graph(&self, bst: &ByteStream, number: u16) -> Result<ControlFlowGraph, Error> {
+        Ok(ControlFlowGraph::build(&self.disassemble(bst, number)?, &self.entry_points(number), false))
+    }
+
+    /// <p>The file data of segment <code>number</code>.</p>
+    pub fn segment_data<'a>(&self, bst: &'a ByteStream, number: u16) -> Result<&'a [u8], Error> {
+        match self.segment(number) {
+            Some(segment) => segment.data(bst, self.shift_count),
+            None => Err(Error::InvalidHeader { field: "segment number" }),
+        }
+    }
 }
 
 impl ExecutableType for NewExecutable {
-    fn signature(&self) -> Signature where Self: Sized {
+    fn signature(&self) -> Signature {
         Signature::NE
     }
-    fn read(bst: &mut crate::byte_stream::ByteStream) -> Self where Self: Sized {
-        NewExecutable {}
+    fn read(bst: &mut ByteStream) -> Result<Self, Error> where Self: Sized {
+        let offset = bst.pos;
+        let magic = bst.read_word()?;
+        if &magic.to_le_bytes() != b"NE" {
+            return Err(Error::BadMagic { offset, found: magic });
+        }
+        let linker_version = (bst.read_byte()?, bst.read_byte()?);
+        let entry_table_offset = bst.read_word()?;
+        let entry_table_length = bst.read_word()?;
+        let crc = bst.read_dword()?;
+        let program_flags = ProgramFlags::from_bits(bst.read_byte()?);
+        let application_flags = ApplicationFlags::from_bits(bst.read_byte()?);
+        let automatic_data_segment = bst.read_word()?;
+        let initial_heap_size = bst.read_word()?;
+        let initial_stack_size = bst.read_word()?;
+        let ip = bst.read_word()?;
+        let cs = bst.read_word()?;
+        let sp = bst.read_word()?;
+        let ss = bst.read_word()?;
+        let segment_count = bst.read_word()?;
+        let module_reference_count = bst.read_word()?;
+        let non_resident_names_table_size = bst.read_word()?;
+        let segment_table_offset = bst.read_word()?;
+        let resource_table_offset = bst.read_word()?;
+        let resident_names_table_offset = bst.read_word()?;
+        let module_reference_table_offset = bst.read_word()?;
+        let imported_names_table_offset = bst.read_word()?;
+        let non_resident_names_table_offset = bst.read_dword()?;
+        let movable_entry_count = bst.read_word()?;
+        let shift_count = bst.read_word()?;
+        let resource_segment_count = bst.read_word()?;
+        let target_os = TargetOs::from_byte(bst.read_byte()?);
+        let additional_flags = AdditionalFlags::from_bits(bst.read_byte()?);
+        let fast_load_offset = bst.read_word()?;
+        let fast_load_length = bst.read_word()?;
+        let min_code_swap_size = bst.read_word()?;
+        let minor = bst.read_byte()?;
+        let expected_windows_version = (bst.read_byte()?, minor);
+
+        if shift_count > 16 {
+            return Err(Error::InvalidHeader { field: "shift count" });
+        }
+
+        bst.pos = offset + segment_table_offset as usize;
+        let mut segments = Vec::with_capacity(segment_count as usize);
+        for _ in 0..segment_count {
+            segments.push(Segment::read(bst, shift_count)?);
+        }
+
+        // the table is empty when it runs straight into the resident names
+        let resources = if resource_table_offset != resident_names_table_offset {
+            bst.pos = offset + resource_table_offset as usize;
+            Some(ResourceTable::read(bst)?)
+        } else {
+            None
+        };
+
+        bst.pos = offset + resident_names_table_offset as usize;
+        let resident_names = names::read_names(bst, offset + module_reference_table_offset as usize)?;
+        bst.pos = non_resident_names_table_offset as usize;
+        let non_resident_names = names::read_names(bst, bst.pos + non_resident_names_table_size as usize)?;
+        bst.pos = offset + entry_table_offset as usize;
+        let entries = entrytable::read_entries(bst, bst.pos + entry_table_length as usize)?;
+
+        let imported_names = offset + imported_names_table_offset as usize;
+        let mut module_references = Vec::with_capacity(module_reference_count as usize);
+        for i in 0..module_reference_count as usize {
+            let name_offset = bst.read_word_at(offset + module_reference_table_offset as usize + i * 2)?;
+            module_references.push(imports::read_counted_name(bst, imported_names + name_offset as usize)?);
+        }
+
+        Ok(NewExecutable {
+            offset,
+            linker_version,
+            entry_table_offset,
+            entry_table_length,
+            crc,
+            program_flags,
+            application_flags,
+            automatic_data_segment,
+            initial_heap_size,
+            initial_stack_size,
+            cs,
+            ip,
+            ss,
+            sp,
+            segment_count,
+            module_reference_count,
+            non_resident_names_table_size,
+            segment_table_offset,
+            resource_table_offset,
+            resident_names_table_offset,
+            module_reference_table_offset,
+            imported_names_table_offset,
+            non_resident_names_table_offset,
+            movable_entry_count,
+            shift_count,
+            resource_segment_count,
+            target_os,
+            additional_flags,
+            fast_load_offset,
+            fast_load_length,
+            min_code_swap_size,
+            expected_windows_version,
+            segments,
+            resources,
+            resident_names,
+            non_resident_names,
+            entries,
+            module_references,
+        })
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flags::{ApplicationType, DataSegmentType};
+
+    /// <p>Where the header starts in <code>module()</code>, after a stand-in for the DOS stub.</p>
+    const HEADER: usize = 0x10;
+
+    /// <p>A library "DEMO" with one code segment, no resources, an import of KERNEL, a movable entry named in the
+    /// resident table, two unused ordinals, a fixed entry named in the non-resident table and a constant.</p>
+    fn module() -> Vec<u8> {
+        let mut file = vec![0; 0xC0];
+        let mut put = |at: usize, bytes: &[u8]| file[at..at + bytes.len()].copy_from_slice(bytes);
+        put(0, b"stub");
+        put(HEADER, b"NE");
+        put(HEADER + 0x02, &[5, 10]);
+        for (at, word) in [
+            (0x04, 0x0062),
+            (0x06, 21),
+            (0x08, 0x5678),
+            (0x0A, 0x1234),
+            (0x0C, 0x8392),
+            (0x0E, 2),
+            (0x10, 0x0400),
+            (0x12, 0x2000),
+            (0x14, 0x0010),
+            (0x16, 1),
+            (0x18, 0x0100),
+            (0x1A, 2),
+            (0x1C, 1),
+            (0x1E, 1),
+            (0x20, 22),
+            (0x22, 0x0040),
+            (0x24, 0x0048),
+            (0x26, 0x0048),
+            (0x28, 0x0058),
+            (0x2A, 0x005A),
+            (0x2C, 0x0087),
+            (0x30, 1),
+            (0x32, 4),
+            (0x38, 0x0010),
+            (0x3A, 0x0020),
+            (0x3E, 0x030A),
+        ] {
+            put(HEADER + at, &u16::to_le_bytes(word));
+        }
+        put(HEADER + 0x36, &[2, 0x08]);
+        // segment table: data at sector 0xA, 0x20 bytes of code
+        put(HEADER + 0x40, &[0x0A, 0x00, 0x20, 0x00, 0x10, 0x00, 0x20, 0x00]);
+        put(HEADER + 0x48, b"\x04DEMO\x00\x00\x05Alpha\x01\x00\x00");
+        put(HEADER + 0x58, &[0x01, 0x00]);
+        put(HEADER + 0x5A, b"\x00\x06KERNEL");
+        // entry table: a movable bundle, an unused one of two ordinals, a fixed one and a constant
+        put(
+            HEADER + 0x62,
+            &[
+                1, 0xFF, 0x03, 0xCD, 0x3F, 0x01, 0x10, 0x00, //
+                2, 0x00, //
+                1, 0x01, 0x09, 0x20, 0x00, //
+                1, 0xFE, 0x01, 0x34, 0x12, //
+                0,
+            ],
+        );
+        put(0x87, b"\x0BDemo module\x00\x00\x04Beta\x04\x00\x00");
+        put(0xA0, &[0xC3]);
+        file
+    }
+
+    fn read() -> NewExecutable {
+        let mut bst = ByteStream::new(module());
+        bst.pos = HEADER;
+        NewExecutable::read(&mut bst).unwrap()
+    }
+
+    #[test]
+    fn reads_every_header_field() {
+        let ne = read();
+        assert_eq!(ne.offset, HEADER);
+        assert_eq!(ne.linker_version, (5, 10));
+        assert_eq!((ne.entry_table_offset, ne.entry_table_length), (0x62, 21));
+        assert_eq!(ne.crc, 0x12345678);
+
+        assert_eq!(ne.program_flags.data, DataSegmentType::Multiple);
+        assert!(ne.program_flags.i8086 && ne.program_flags.x87);
+        assert!(!ne.program_flags.per_process_init && !ne.program_flags.protected_mode_only);
+        assert_eq!(ne.application_flags.application_type, ApplicationType::WindowApi);
+        assert!(ne.application_flags.library && !ne.application_flags.link_errors);
+        assert_eq!(ne.flag_word(), 0x8392);
+
+        assert_eq!(ne.automatic_data_segment, 2);
+        assert_eq!((ne.initial_heap_size, ne.initial_stack_size), (0x400, 0x2000));
+        assert_eq!((ne.cs, ne.ip), (1, 0x10));
+        assert_eq!((ne.ss, ne.sp), (2, 0x100));
+        assert_eq!((ne.segment_count, ne.module_reference_count), (1, 1));
+        assert_eq!(ne.non_resident_names_table_size, 22);
+        assert_eq!((ne.segment_table_offset, ne.resource_table_offset), (0x40, 0x48));
+        assert_eq!((ne.resident_names_table_offset, ne.module_reference_table_offset), (0x48, 0x58));
+        assert_eq!((ne.imported_names_table_offset, ne.non_resident_names_table_offset), (0x5A, 0x87));
+        assert_eq!((ne.movable_entry_count, ne.shift_count, ne.resource_segment_count), (1, 4, 0));
+        assert_eq!(ne.target_os, TargetOs::Windows);
+        assert!(ne.additional_flags.fast_load && !ne.additional_flags.long_file_names);
+        assert_eq!((ne.fast_load_offset, ne.fast_load_length, ne.min_code_swap_size), (0x10, 0x20, 0));
+        assert_eq!(ne.expected_windows_version, (3, 10));
+    }
+
+    #[test]
+    fn reads_the_name_tables() {
+        let ne = read();
+        assert_eq!(ne.module_name(), Some("DEMO"));
+        assert_eq!(ne.description(), Some("Demo module"));
+        let names = |names: &[EntryName]| names.iter().map(|n| (n.name.clone(), n.ordinal)).collect::<Vec<_>>();
+        assert_eq!(names(&ne.resident_names), [("DEMO".to_owned(), 0), ("Alpha".to_owned(), 1)]);
+        assert_eq!(names(&ne.non_resident_names), [("Demo module".to_owned(), 0), ("Beta".to_owned(), 4)]);
+        assert_eq!(ne.module_references, ["KERNEL"]);
+    }
+
+    #[test]
+    fn exports_follow_the_entry_bundles() {
+        let entry = |ordinal, segment, offset, movable, flags: u8| Entry {
+            ordinal,
+            segment,
+            offset,
+            movable,
+            exported: flags & 1 != 0,
+            shared_data: flags & 2 != 0,
+            parameter_words: flags >> 3,
+        };
+        let export = |name: Option<&str>, resident, entry| Export { name: name.map(str::to_owned), resident, entry };
+        // ordinals 2 and 3 are the unused bundle
+        assert_eq!(
+            read().exports(),
+            [
+                export(Some("Alpha"), true, entry(1, 1, 0x10, true, 0x03)),
+                export(Some("Beta"), false, entry(4, 1, 0x20, false, 0x09)),
+                export(None, false, entry(5, CONSTANT_SEGMENT, 0x1234, false, 0x01)),
+            ]
+        );
+        assert_eq!(read().entry_points(1), [0x10, 0x20]);
+    }
+
+    #[test]
+    fn rejects_other_signatures() {
+        let mut file = module();
+        file[HEADER..HEADER + 2].copy_from_slice(b"LE");
+        let mut bst = ByteStream::new(file);
+        bst.pos = HEADER;
+        assert!(matches!(NewExecutable::read(&mut bst), Err(Error::BadMagic { offset: HEADER, .. })));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_shift_count_past_16_is_invalid() {
+        let mut file = module();
+        file[HEADER + 0x32] = 17;
+        let mut bst = ByteStream::new(file);
+        bst.pos = HEADER;
+        assert!(matches!(NewExecutable::read(&mut bst), Err(Error::InvalidHeader { field: "shift count" })));
+    }
+}
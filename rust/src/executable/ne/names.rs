@@ -0,0 +1,24 @@
+use crate::{byte_stream::ByteStream, error::Error, executable::resource::decode_ansi};
+
+/// <p>An entry of the resident or non-resident names table.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryName {
+    pub name: String,
+    /// The entry table ordinal the name is for.  The first name of each table has ordinal 0: the module name in the
+    /// resident table, the description in the non-resident one.
+    pub ordinal: u16,
+}
+
+/// <p>Reads length-prefixed names, each followed by its ordinal, up to a zero length or <code>end</code>.</p>
+pub fn read_names(bst: &mut ByteStream, end: usize) -> Result<Vec<EntryName>, Error> {
+    let mut names = Vec::new();
+    while bst.pos < end {
+        let len = bst.read_byte()? as usize;
+        if len == 0 {
+            break;
+        }
+        let name = decode_ansi(&bst.read_bytes(len)?);
+        names.push(EntryName { name, ordinal: bst.read_word()? });
+    }
+    Ok(names)
+}
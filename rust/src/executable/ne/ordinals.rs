@@ -0,0 +1,222 @@
+//! <p>Names for the ordinals of the Windows 3.1 system modules, for imports that only give a number.</p>
+
+static KERNEL: &[(u16, &str)] = &[
+    (1, "FATALEXIT"), (2, "EXITKERNEL"), (3, "GETVERSION"), (4, "LOCALINIT"), (5, "LOCALALLOC"), (6, "LOCALREALLOC"),
+    (7, "LOCALFREE"), (8, "LOCALLOCK"), (9, "LOCALUNLOCK"), (10, "LOCALSIZE"), (11, "LOCALHANDLE"), (12, "LOCALFLAGS"),
+    (13, "LOCALCOMPACT"), (14, "LOCALNOTIFY"), (15, "GLOBALALLOC"), (16, "GLOBALREALLOC"), (17, "GLOBALFREE"),
+    (18, "GLOBALLOCK"), (19, "GLOBALUNLOCK"), (20, "GLOBALSIZE"), (21, "GLOBALHANDLE"), (22, "GLOBALFLAGS"),
+    (23, "LOCKSEGMENT"), (24, "UNLOCKSEGMENT"), (25, "GLOBALCOMPACT"), (26, "GLOBALFREEALL"), (27, "GETMODULENAME"),
+    (28, "GLOBALMASTERHANDLE"), (29, "YIELD"), (30, "WAITEVENT"), (31, "POSTEVENT"), (32, "SETPRIORITY"),
+    (33, "LOCKCURRENTTASK"), (34, "SETTASKQUEUE"), (35, "GETTASKQUEUE"), (36, "GETCURRENTTASK"), (37, "GETCURRENTPDB"),
+    (38, "SETTASKSIGNALPROC"), (41, "ENABLEDOS"), (42, "DISABLEDOS"), (45, "LOADMODULE"), (46, "FREEMODULE"),
+    (47, "GETMODULEHANDLE"), (48, "GETMODULEUSAGE"), (49, "GETMODULEFILENAME"), (50, "GETPROCADDRESS"),
+    (51, "MAKEPROCINSTANCE"), (52, "FREEPROCINSTANCE"), (53, "CALLPROCINSTANCE"), (54, "GETINSTANCEDATA"),
+    (55, "CATCH"), (56, "THROW"), (57, "GETPROFILEINT"), (58, "GETPROFILESTRING"), (59, "WRITEPROFILESTRING"),
+    (60, "FINDRESOURCE"), (61, "LOADRESOURCE"), (62, "LOCKRESOURCE"), (63, "FREERESOURCE"), (64, "ACCESSRESOURCE"),
+    (65, "SIZEOFRESOURCE"), (66, "ALLOCRESOURCE"), (67, "SETRESOURCEHANDLER"), (68, "INITATOMTABLE"), (69, "FINDATOM"),
+    (70, "ADDATOM"), (71, "DELETEATOM"), (72, "GETATOMNAME"), (73, "GETATOMHANDLE"), (74, "OPENFILE"),
+    (75, "OPENPATHNAME"), (76, "DELETEPATHNAME"), (77, "ANSINEXT"), (78, "ANSIPREV"), (79, "ANSIUPPER"),
+    (80, "ANSILOWER"), (81, "_LCLOSE"), (82, "_LREAD"), (83, "_LCREAT"), (84, "_LLSEEK"), (85, "_LOPEN"),
+    (86, "_LWRITE"), (87, "LSTRCMP"), (88, "LSTRCPY"), (89, "LSTRCAT"), (90, "LSTRLEN"), (91, "INITTASK"),
+    (92, "GETTEMPDRIVE"), (93, "GETCODEHANDLE"), (94, "DEFINEHANDLETABLE"), (95, "LOADLIBRARY"), (96, "FREELIBRARY"),
+    (97, "GETTEMPFILENAME"), (98, "GETLASTDISKCHANGE"), (99, "GETLPERRMODE"), (100, "VALIDATECODESEGMENTS"),
+    (101, "NOHOOKDOSCALL"), (102, "DOS3CALL"), (103, "NETBIOSCALL"), (104, "GETCODEINFO"), (105, "GETEXEVERSION"),
+    (106, "SETSWAPAREASIZE"), (107, "SETERRORMODE"), (108, "SWITCHSTACKTO"), (109, "SWITCHSTACKBACK"),
+    (110, "PATCHCODEHANDLE"), (111, "GLOBALWIRE"), (112, "GLOBALUNWIRE"), (113, "__AHSHIFT"), (114, "__AHINCR"),
+    (115, "OUTPUTDEBUGSTRING"), (116, "INITLIB"), (117, "OLDYIELD"), (118, "GETTASKQUEUEDS"), (119, "GETTASKQUEUEES"),
+    (120, "UNDEFDYNLINK"), (121, "LOCALSHRINK"), (122, "ISTASKLOCKED"), (123, "KBDRST"), (124, "ENABLEKERNEL"),
+    (125, "DISABLEKERNEL"), (126, "MEMORYFREED"), (127, "GETPRIVATEPROFILEINT"), (128, "GETPRIVATEPROFILESTRING"),
+    (129, "WRITEPRIVATEPROFILESTRING"), (130, "FILECDR"), (131, "GETDOSENVIRONMENT"), (132, "GETWINFLAGS"),
+    (133, "GETEXEPTR"), (134, "GETWINDOWSDIRECTORY"), (135, "GETSYSTEMDIRECTORY"), (136, "GETDRIVETYPE"),
+    (137, "FATALAPPEXIT"), (138, "GETHEAPSPACES"), (139, "DOSIGNAL"), (140, "SETSIGHANDLER"), (141, "INITTASK1"),
+    (150, "DIRECTEDYIELD"), (151, "WINOLDAPCALL"), (152, "GETNUMTASKS"), (154, "GLOBALNOTIFY"), (155, "GETTASKDS"),
+    (156, "LIMITEMSPAGES"), (157, "GETCURPID"), (158, "ISWINOLDAPTASK"), (159, "GLOBALHANDLENORIP"), (160, "EMSCOPY"),
+    (161, "LOCALCOUNTFREE"), (162, "LOCALHEAPSIZE"), (163, "GLOBALLRUOLDEST"), (164, "GLOBALLRUNEWEST"),
+    (165, "A20PROC"), (166, "WINEXEC"), (167, "GETEXPWINVER"), (168, "DIRECTRESALLOC"), (169, "GETFREESPACE"),
+    (170, "ALLOCCSTODSALIAS"), (171, "ALLOCDSTOCSALIAS"), (172, "ALLOCALIAS"), (173, "__ROMBIOS"), (174, "__A000H"),
+    (175, "ALLOCSELECTOR"), (176, "FREESELECTOR"), (177, "PRESTOCHANGOSELECTOR"), (178, "__WINFLAGS"), (179, "__D000H"),
+    (180, "LONGPTRADD"), (181, "__B000H"), (182, "__B800H"), (183, "__0000H"), (184, "GLOBALDOSALLOC"),
+    (185, "GLOBALDOSFREE"), (186, "GETSELECTORBASE"), (187, "SETSELECTORBASE"), (188, "GETSELECTORLIMIT"),
+    (189, "SETSELECTORLIMIT"), (190, "__E000H"), (191, "GLOBALPAGELOCK"), (192, "GLOBALPAGEUNLOCK"), (193, "__0040H"),
+    (194, "__F000H"), (195, "__C000H"), (196, "SELECTORACCESSRIGHTS"), (197, "GLOBALFIX"), (198, "GLOBALUNFIX"),
+    (199, "SETHANDLECOUNT"), (200, "VALIDATEFREESPACES"), (201, "REPLACEINST"), (202, "REGISTERPTRACE"),
+    (203, "DEBUGBREAK"), (204, "SWAPRECORDING"), (205, "CVWBREAK"), (206, "ALLOCSELECTORARRAY"),
+    (207, "ISDBCSLEADBYTE"), (310, "LOCALHANDLEDELTA"), (311, "GETSETKERNELDOSPROC"), (314, "DEBUGDEFINESEGMENT"),
+    (315, "WRITEOUTPROFILES"), (316, "GETFREEMEMINFO"), (318, "FATALEXITHOOK"), (319, "FLUSHCACHEDFILEHANDLE"),
+    (320, "ISTASK"), (323, "ISROMMODULE"), (324, "LOGERROR"), (325, "LOGPARAMERROR"), (326, "ISROMFILE"),
+    (328, "_DEBUGOUTPUT"), (332, "THHOOK"), (334, "ISBADREADPTR"), (335, "ISBADWRITEPTR"), (336, "ISBADCODEPTR"),
+    (337, "ISBADSTRINGPTR"), (338, "HASGPHANDLER"), (339, "DIAGQUERY"), (340, "DIAGOUTPUT"), (341, "TOOLHELPHOOK"),
+    (343, "REGISTERWINOLDAPHOOK"), (344, "GETWINOLDAPHOOKS"), (345, "ISSHAREDSELECTOR"), (346, "ISBADHUGEREADPTR"),
+    (347, "ISBADHUGEWRITEPTR"), (348, "HMEMCPY"), (349, "_HREAD"), (350, "_HWRITE"), (353, "LSTRCPYN"),
+    (354, "GETAPPCOMPATFLAGS"), (355, "GETWINDEBUGINFO"), (356, "SETWINDEBUGINFO"),
+];
+
+static USER: &[(u16, &str)] = &[
+    (1, "MESSAGEBOX"), (2, "OLDEXITWINDOWS"), (3, "ENABLEOEMLAYER"), (4, "DISABLEOEMLAYER"), (5, "INITAPP"),
+    (6, "POSTQUITMESSAGE"), (7, "EXITWINDOWS"), (10, "SETTIMER"), (11, "SETSYSTEMTIMER"), (12, "KILLTIMER"),
+    (13, "GETTICKCOUNT"), (14, "GETTIMERRESOLUTION"), (15, "GETCURRENTTIME"), (16, "CLIPCURSOR"), (17, "GETCURSORPOS"),
+    (18, "SETCAPTURE"), (19, "RELEASECAPTURE"), (20, "SETDOUBLECLICKTIME"), (21, "GETDOUBLECLICKTIME"),
+    (22, "SETFOCUS"), (23, "GETFOCUS"), (24, "REMOVEPROP"), (25, "GETPROP"), (26, "SETPROP"), (27, "ENUMPROPS"),
+    (28, "CLIENTTOSCREEN"), (29, "SCREENTOCLIENT"), (30, "WINDOWFROMPOINT"), (31, "ISICONIC"), (32, "GETWINDOWRECT"),
+    (33, "GETCLIENTRECT"), (34, "ENABLEWINDOW"), (35, "ISWINDOWENABLED"), (36, "GETWINDOWTEXT"), (37, "SETWINDOWTEXT"),
+    (38, "GETWINDOWTEXTLENGTH"), (39, "BEGINPAINT"), (40, "ENDPAINT"), (41, "CREATEWINDOW"), (42, "SHOWWINDOW"),
+    (43, "CLOSEWINDOW"), (44, "OPENICON"), (45, "BRINGWINDOWTOTOP"), (46, "GETPARENT"), (47, "ISWINDOW"),
+    (48, "ISCHILD"), (49, "ISWINDOWVISIBLE"), (50, "FINDWINDOW"), (52, "ANYPOPUP"), (53, "DESTROYWINDOW"),
+    (54, "ENUMWINDOWS"), (55, "ENUMCHILDWINDOWS"), (56, "MOVEWINDOW"), (57, "REGISTERCLASS"), (58, "GETCLASSNAME"),
+    (59, "SETACTIVEWINDOW"), (60, "GETACTIVEWINDOW"), (61, "SCROLLWINDOW"), (62, "SETSCROLLPOS"), (63, "GETSCROLLPOS"),
+    (64, "SETSCROLLRANGE"), (65, "GETSCROLLRANGE"), (66, "GETDC"), (67, "GETWINDOWDC"), (68, "RELEASEDC"),
+    (69, "SETCURSOR"), (70, "SETCURSORPOS"), (71, "SHOWCURSOR"), (72, "SETRECT"), (73, "SETRECTEMPTY"),
+    (74, "COPYRECT"), (75, "ISRECTEMPTY"), (76, "PTINRECT"), (77, "OFFSETRECT"), (78, "INFLATERECT"),
+    (79, "INTERSECTRECT"), (80, "UNIONRECT"), (81, "FILLRECT"), (82, "INVERTRECT"), (83, "FRAMERECT"), (84, "DRAWICON"),
+    (85, "DRAWTEXT"), (87, "DIALOGBOX"), (88, "ENDDIALOG"), (89, "CREATEDIALOG"), (90, "ISDIALOGMESSAGE"),
+    (91, "GETDLGITEM"), (92, "SETDLGITEMTEXT"), (93, "GETDLGITEMTEXT"), (94, "SETDLGITEMINT"), (95, "GETDLGITEMINT"),
+    (96, "CHECKRADIOBUTTON"), (97, "CHECKDLGBUTTON"), (98, "ISDLGBUTTONCHECKED"), (99, "DLGDIRSELECT"),
+    (100, "DLGDIRLIST"), (101, "SENDDLGITEMMESSAGE"), (102, "ADJUSTWINDOWRECT"), (103, "MAPDIALOGRECT"),
+    (104, "MESSAGEBEEP"), (105, "FLASHWINDOW"), (106, "GETKEYSTATE"), (107, "DEFWINDOWPROC"), (108, "GETMESSAGE"),
+    (109, "PEEKMESSAGE"), (110, "POSTMESSAGE"), (111, "SENDMESSAGE"), (112, "WAITMESSAGE"), (113, "TRANSLATEMESSAGE"),
+    (114, "DISPATCHMESSAGE"), (115, "REPLYMESSAGE"), (116, "POSTAPPMESSAGE"), (118, "REGISTERWINDOWMESSAGE"),
+    (119, "GETMESSAGEPOS"), (120, "GETMESSAGETIME"), (121, "SETWINDOWSHOOK"), (122, "CALLWINDOWPROC"),
+    (123, "CALLMSGFILTER"), (124, "UPDATEWINDOW"), (125, "INVALIDATERECT"), (126, "INVALIDATERGN"),
+    (127, "VALIDATERECT"), (128, "VALIDATERGN"), (129, "GETCLASSWORD"), (130, "SETCLASSWORD"), (131, "GETCLASSLONG"),
+    (132, "SETCLASSLONG"), (133, "GETWINDOWWORD"), (134, "SETWINDOWWORD"), (135, "GETWINDOWLONG"),
+    (136, "SETWINDOWLONG"), (137, "OPENCLIPBOARD"), (138, "CLOSECLIPBOARD"), (139, "EMPTYCLIPBOARD"),
+    (140, "GETCLIPBOARDOWNER"), (141, "SETCLIPBOARDDATA"), (142, "GETCLIPBOARDDATA"), (143, "COUNTCLIPBOARDFORMATS"),
+    (144, "ENUMCLIPBOARDFORMATS"), (145, "REGISTERCLIPBOARDFORMAT"), (146, "GETCLIPBOARDFORMATNAME"),
+    (147, "SETCLIPBOARDVIEWER"), (148, "GETCLIPBOARDVIEWER"), (149, "CHANGECLIPBOARDCHAIN"), (150, "LOADMENU"),
+    (151, "CREATEMENU"), (152, "DESTROYMENU"), (153, "CHANGEMENU"), (154, "CHECKMENUITEM"), (155, "ENABLEMENUITEM"),
+    (156, "GETSYSTEMMENU"), (157, "GETMENU"), (158, "SETMENU"), (159, "GETSUBMENU"), (160, "DRAWMENUBAR"),
+    (161, "GETMENUSTRING"), (162, "HILITEMENUITEM"), (163, "CREATECARET"), (164, "DESTROYCARET"), (165, "SETCARETPOS"),
+    (166, "HIDECARET"), (167, "SHOWCARET"), (168, "SETCARETBLINKTIME"), (169, "GETCARETBLINKTIME"),
+    (170, "ARRANGEICONICWINDOWS"), (171, "WINHELP"), (172, "SWITCHTOTHISWINDOW"), (173, "LOADCURSOR"),
+    (174, "LOADICON"), (175, "LOADBITMAP"), (176, "LOADSTRING"), (177, "LOADACCELERATORS"),
+    (178, "TRANSLATEACCELERATOR"), (179, "GETSYSTEMMETRICS"), (180, "GETSYSCOLOR"), (181, "SETSYSCOLORS"),
+    (182, "KILLSYSTEMTIMER"), (183, "GETCARETPOS"), (184, "QUERYSENDMESSAGE"), (185, "GRAYSTRING"),
+    (186, "SWAPMOUSEBUTTON"), (187, "ENDMENU"), (188, "SETSYSMODALWINDOW"), (189, "GETSYSMODALWINDOW"),
+    (190, "GETUPDATERECT"), (191, "CHILDWINDOWFROMPOINT"), (192, "INSENDMESSAGE"), (193, "ISCLIPBOARDFORMATAVAILABLE"),
+    (194, "DLGDIRSELECTCOMBOBOX"), (195, "DLGDIRLISTCOMBOBOX"), (196, "TABBEDTEXTOUT"), (197, "GETTABBEDTEXTEXTENT"),
+    (198, "CASCADECHILDWINDOWS"), (199, "TILECHILDWINDOWS"), (200, "OPENCOMM"), (201, "SETCOMMSTATE"),
+    (202, "GETCOMMSTATE"), (203, "GETCOMMERROR"), (204, "READCOMM"), (205, "WRITECOMM"), (206, "TRANSMITCOMMCHAR"),
+    (207, "CLOSECOMM"), (208, "SETCOMMEVENTMASK"), (209, "GETCOMMEVENTMASK"), (210, "SETCOMMBREAK"),
+    (211, "CLEARCOMMBREAK"), (212, "UNGETCOMMCHAR"), (213, "BUILDCOMMDCB"), (214, "ESCAPECOMMFUNCTION"),
+    (215, "FLUSHCOMM"), (216, "USERSEEUSERDO"), (217, "LOOKUPMENUHANDLE"), (218, "DIALOGBOXINDIRECT"),
+    (219, "CREATEDIALOGINDIRECT"), (220, "LOADMENUINDIRECT"), (221, "SCROLLDC"), (222, "GETKEYBOARDSTATE"),
+    (223, "SETKEYBOARDSTATE"), (224, "GETWINDOWTASK"), (225, "ENUMTASKWINDOWS"), (226, "LOCKINPUT"),
+    (227, "GETNEXTDLGGROUPITEM"), (228, "GETNEXTDLGTABITEM"), (229, "GETTOPWINDOW"), (230, "GETNEXTWINDOW"),
+    (231, "GETSYSTEMDEBUGSTATE"), (232, "SETWINDOWPOS"), (233, "SETPARENT"), (234, "UNHOOKWINDOWSHOOK"),
+    (235, "DEFHOOKPROC"), (236, "GETCAPTURE"), (237, "GETUPDATERGN"), (238, "EXCLUDEUPDATERGN"),
+    (239, "DIALOGBOXPARAM"), (240, "DIALOGBOXINDIRECTPARAM"), (241, "CREATEDIALOGPARAM"),
+    (242, "CREATEDIALOGINDIRECTPARAM"), (243, "GETDIALOGBASEUNITS"), (244, "EQUALRECT"),
+    (245, "ENABLECOMMNOTIFICATION"), (246, "EXITWINDOWSEXEC"), (247, "GETCURSOR"), (248, "GETOPENCLIPBOARDWINDOW"),
+    (249, "GETASYNCKEYSTATE"), (250, "GETMENUSTATE"), (251, "SENDDRIVERMESSAGE"), (252, "OPENDRIVER"),
+    (253, "CLOSEDRIVER"), (254, "GETDRIVERMODULEHANDLE"), (255, "DEFDRIVERPROC"), (256, "GETDRIVERINFO"),
+    (257, "GETNEXTDRIVER"), (258, "MAPWINDOWPOINTS"), (259, "BEGINDEFERWINDOWPOS"), (260, "DEFERWINDOWPOS"),
+    (261, "ENDDEFERWINDOWPOS"), (262, "GETWINDOW"), (263, "GETMENUITEMCOUNT"), (264, "GETMENUITEMID"),
+    (265, "SHOWOWNEDPOPUPS"), (266, "SETMESSAGEQUEUE"), (267, "SHOWSCROLLBAR"), (268, "GLOBALADDATOM"),
+    (269, "GLOBALDELETEATOM"), (270, "GLOBALFINDATOM"), (271, "GLOBALGETATOMNAME"), (272, "ISZOOMED"),
+    (277, "GETDLGCTRLID"), (278, "GETDESKTOPHWND"), (281, "GETSYSCOLORBRUSH"), (282, "SELECTPALETTE"),
+    (283, "REALIZEPALETTE"), (284, "GETFREESYSTEMRESOURCES"), (286, "GETDESKTOPWINDOW"), (287, "GETLASTACTIVEPOPUP"),
+    (288, "GETMESSAGEEXTRAINFO"), (290, "REDRAWWINDOW"), (291, "SETWINDOWSHOOKEX"), (292, "UNHOOKWINDOWSHOOKEX"),
+    (293, "CALLNEXTHOOKEX"), (294, "LOCKWINDOWUPDATE"), (308, "DEFDLGPROC"), (319, "SCROLLWINDOWEX"),
+    (324, "FILLWINDOW"), (325, "PAINTRECT"), (326, "GETCONTROLBRUSH"), (331, "ENABLEHARDWAREINPUT"), (332, "USERYIELD"),
+    (333, "ISUSERIDLE"), (334, "GETQUEUESTATUS"), (335, "GETINPUTSTATE"), (358, "ISMENU"), (359, "GETDCEX"),
+    (366, "DCHOOK"), (368, "COPYICON"), (369, "COPYCURSOR"), (370, "GETWINDOWPLACEMENT"), (371, "SETWINDOWPLACEMENT"),
+    (373, "SUBTRACTRECT"), (400, "FINALUSERINIT"), (402, "GETPRIORITYCLIPBOARDFORMAT"), (403, "UNREGISTERCLASS"),
+    (404, "GETCLASSINFO"), (406, "CREATECURSOR"), (407, "CREATEICON"), (408, "CREATECURSORICONINDIRECT"),
+    (410, "INSERTMENU"), (411, "APPENDMENU"), (412, "REMOVEMENU"), (413, "DELETEMENU"), (414, "MODIFYMENU"),
+    (415, "CREATEPOPUPMENU"), (416, "TRACKPOPUPMENU"), (417, "GETMENUCHECKMARKDIMENSIONS"), (418, "SETMENUITEMBITMAPS"),
+    (420, "_WSPRINTF"), (421, "WVSPRINTF"), (422, "DLGDIRSELECTEX"), (423, "DLGDIRSELECTCOMBOBOXEX"), (430, "LSTRCMP"),
+    (431, "ANSIUPPER"), (432, "ANSILOWER"), (433, "ISCHARALPHA"), (434, "ISCHARALPHANUMERIC"), (435, "ISCHARUPPER"),
+    (436, "ISCHARLOWER"), (437, "ANSIUPPERBUFF"), (438, "ANSILOWERBUFF"), (445, "DEFFRAMEPROC"),
+    (447, "DEFMDICHILDPROC"), (451, "TRANSLATEMDISYSACCEL"), (452, "CREATEWINDOWEX"), (454, "ADJUSTWINDOWRECTEX"),
+    (455, "GETICONID"), (456, "LOADICONHANDLER"), (457, "DESTROYICON"), (458, "DESTROYCURSOR"), (459, "DUMPICON"),
+    (460, "GETINTERNALWINDOWPOS"), (461, "SETINTERNALWINDOWPOS"), (462, "CALCCHILDSCROLL"), (463, "SCROLLCHILDREN"),
+    (464, "DRAGOBJECT"), (465, "DRAGDETECT"), (466, "DRAWFOCUSRECT"), (470, "STRINGFUNC"), (471, "LSTRCMPI"),
+    (472, "ANSINEXT"), (473, "ANSIPREV"), (480, "GETUSERLOCALOBJTYPE"), (481, "HARDWARE_EVENT"),
+    (482, "ENABLESCROLLBAR"), (483, "SYSTEMPARAMETERSINFO"),
+];
+
+static GDI: &[(u16, &str)] = &[
+    (1, "SETBKCOLOR"), (2, "SETBKMODE"), (3, "SETMAPMODE"), (4, "SETROP2"), (5, "SETRELABS"), (6, "SETPOLYFILLMODE"),
+    (7, "SETSTRETCHBLTMODE"), (8, "SETTEXTCHARACTEREXTRA"), (9, "SETTEXTCOLOR"), (10, "SETTEXTJUSTIFICATION"),
+    (11, "SETWINDOWORG"), (12, "SETWINDOWEXT"), (13, "SETVIEWPORTORG"), (14, "SETVIEWPORTEXT"), (15, "OFFSETWINDOWORG"),
+    (16, "SCALEWINDOWEXT"), (17, "OFFSETVIEWPORTORG"), (18, "SCALEVIEWPORTEXT"), (19, "LINETO"), (20, "MOVETO"),
+    (21, "EXCLUDECLIPRECT"), (22, "INTERSECTCLIPRECT"), (23, "ARC"), (24, "ELLIPSE"), (25, "FLOODFILL"), (26, "PIE"),
+    (27, "RECTANGLE"), (28, "ROUNDRECT"), (29, "PATBLT"), (30, "SAVEDC"), (31, "SETPIXEL"), (32, "OFFSETCLIPRGN"),
+    (33, "TEXTOUT"), (34, "BITBLT"), (35, "STRETCHBLT"), (36, "POLYGON"), (37, "POLYLINE"), (38, "ESCAPE"),
+    (39, "RESTOREDC"), (40, "FILLRGN"), (41, "FRAMERGN"), (42, "INVERTRGN"), (43, "PAINTRGN"), (44, "SELECTCLIPRGN"),
+    (45, "SELECTOBJECT"), (47, "COMBINERGN"), (48, "CREATEBITMAP"), (49, "CREATEBITMAPINDIRECT"),
+    (50, "CREATEBRUSHINDIRECT"), (51, "CREATECOMPATIBLEBITMAP"), (52, "CREATECOMPATIBLEDC"), (53, "CREATEDC"),
+    (54, "CREATEELLIPTICRGN"), (55, "CREATEELLIPTICRGNINDIRECT"), (56, "CREATEFONT"), (57, "CREATEFONTINDIRECT"),
+    (58, "CREATEHATCHBRUSH"), (60, "CREATEPATTERNBRUSH"), (61, "CREATEPEN"), (62, "CREATEPENINDIRECT"),
+    (63, "CREATEPOLYGONRGN"), (64, "CREATERECTRGN"), (65, "CREATERECTRGNINDIRECT"), (66, "CREATESOLIDBRUSH"),
+    (67, "DPTOLP"), (68, "DELETEDC"), (69, "DELETEOBJECT"), (70, "ENUMFONTS"), (71, "ENUMOBJECTS"), (72, "EQUALRGN"),
+    (73, "EXCLUDEVISRECT"), (74, "GETBITMAPBITS"), (75, "GETBKCOLOR"), (76, "GETBKMODE"), (77, "GETCLIPBOX"),
+    (78, "GETCURRENTPOSITION"), (79, "GETDCORG"), (80, "GETDEVICECAPS"), (81, "GETMAPMODE"), (82, "GETOBJECT"),
+    (83, "GETPIXEL"), (84, "GETPOLYFILLMODE"), (85, "GETROP2"), (86, "GETRELABS"), (87, "GETSTOCKOBJECT"),
+    (88, "GETSTRETCHBLTMODE"), (89, "GETTEXTCHARACTEREXTRA"), (90, "GETTEXTCOLOR"), (91, "GETTEXTEXTENT"),
+    (92, "GETTEXTFACE"), (93, "GETTEXTMETRICS"), (94, "GETVIEWPORTEXT"), (95, "GETVIEWPORTORG"), (96, "GETWINDOWEXT"),
+    (97, "GETWINDOWORG"), (98, "INTERSECTVISRECT"), (99, "LPTODP"), (100, "LINEDDA"), (101, "OFFSETRGN"),
+    (102, "OFFSETVISRGN"), (103, "PTVISIBLE"), (104, "RECTVISIBLE"), (105, "SELECTVISRGN"), (106, "SETBITMAPBITS"),
+    (117, "SETDCORG"), (119, "ADDFONTRESOURCE"), (121, "DEATH"), (122, "RESURRECTION"), (123, "PLAYMETAFILE"),
+    (124, "GETMETAFILE"), (125, "CREATEMETAFILE"), (126, "CLOSEMETAFILE"), (127, "DELETEMETAFILE"), (128, "MULDIV"),
+    (129, "SAVEVISRGN"), (130, "RESTOREVISRGN"), (131, "INQUIREVISRGN"), (132, "SETENVIRONMENT"),
+    (133, "GETENVIRONMENT"), (134, "GETRGNBOX"), (136, "REMOVEFONTRESOURCE"), (148, "SETBRUSHORG"),
+    (149, "GETBRUSHORG"), (150, "UNREALIZEOBJECT"), (151, "COPYMETAFILE"), (153, "CREATEIC"), (154, "GETNEARESTCOLOR"),
+    (156, "CREATEDISCARDABLEBITMAP"), (159, "GETMETAFILEBITS"), (160, "SETMETAFILEBITS"), (161, "PTINREGION"),
+    (162, "GETBITMAPDIMENSION"), (163, "SETBITMAPDIMENSION"), (172, "SETRECTRGN"), (173, "GETCLIPRGN"),
+    (175, "ENUMMETAFILE"), (176, "PLAYMETAFILERECORD"), (179, "GETDCSTATE"), (180, "SETDCSTATE"), (181, "RECTINREGION"),
+    (190, "SETDCHOOK"), (191, "GETDCHOOK"), (192, "SETHOOKFLAGS"), (193, "SETBOUNDSRECT"), (194, "GETBOUNDSRECT"),
+    (196, "SETMETAFILEBITSBETTER"), (330, "ENUMFONTFAMILIES"), (332, "GETKERNINGPAIRS"), (345, "GETTEXTALIGN"),
+    (346, "SETTEXTALIGN"), (348, "CHORD"), (349, "SETMAPPERFLAGS"), (350, "GETCHARWIDTH"), (351, "EXTTEXTOUT"),
+    (360, "CREATEPALETTE"), (361, "GDISELECTPALETTE"), (362, "GDIREALIZEPALETTE"), (363, "GETPALETTEENTRIES"),
+    (364, "SETPALETTEENTRIES"), (365, "REALIZEDEFAULTPALETTE"), (366, "UPDATECOLORS"), (367, "ANIMATEPALETTE"),
+    (368, "RESIZEPALETTE"), (370, "GETNEARESTPALETTEINDEX"), (372, "EXTFLOODFILL"), (373, "SETSYSTEMPALETTEUSE"),
+    (374, "GETSYSTEMPALETTEUSE"), (375, "GETSYSTEMPALETTEENTRIES"), (376, "RESETDC"), (377, "STARTDOC"),
+    (378, "ENDDOC"), (379, "STARTPAGE"), (380, "ENDPAGE"), (381, "SETABORTPROC"), (382, "ABORTDOC"),
+    (410, "ISVALIDMETAFILE"), (411, "GETCURLOGFONT"), (412, "ISDCCURRENTPALETTE"), (439, "STRETCHDIBITS"),
+    (440, "SETDIBITS"), (441, "GETDIBITS"), (442, "CREATEDIBITMAP"), (443, "SETDIBITSTODEVICE"),
+    (444, "CREATEROUNDRECTRGN"), (445, "CREATEDIBPATTERNBRUSH"), (450, "POLYPOLYGON"), (451, "CREATEPOLYPOLYGONRGN"),
+    (462, "ISGDIOBJECT"), (468, "GETBITMAPDIMENSIONEX"), (469, "GETBRUSHORGEX"), (470, "GETCURRENTPOSITIONEX"),
+    (471, "GETTEXTEXTENTPOINT"), (472, "GETVIEWPORTEXTEX"), (473, "GETVIEWPORTORGEX"), (474, "GETWINDOWEXTEX"),
+    (475, "GETWINDOWORGEX"), (476, "OFFSETVIEWPORTORGEX"), (477, "OFFSETWINDOWORGEX"), (478, "SETBITMAPDIMENSIONEX"),
+    (479, "SETVIEWPORTEXTEX"), (480, "SETVIEWPORTORGEX"), (481, "SETWINDOWEXTEX"), (482, "SETWINDOWORGEX"),
+    (483, "MOVETOEX"), (484, "SCALEVIEWPORTEXTEX"), (485, "SCALEWINDOWEXTEX"), (486, "GETASPECTRATIOFILTEREX"),
+];
+
+/// <p>The name <code>module</code> exports as <code>ordinal</code>, if it is one of KERNEL, USER or GDI and the
+/// ordinal is known.  Module names are matched ignoring case, since not every linker uppercases them.</p>
+pub fn ordinal_name(module: &str, ordinal: u16) -> Option<&'static str> {
+    let is = |names: &[&str]| names.iter().any(|n| module.eq_ignore_ascii_case(n));
+    let table = if is(&["KERNEL", "KRNL286", "KRNL386"]) {
+        KERNEL
+    } else if is(&["USER"]) {
+        USER
+    } else if is(&["GDI"]) {
+        GDI
+    } else {
+        return None;
+    };
+    table.binary_search_by_key(&ordinal, |(o, _)| *o).ok().map(|i| table[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_are_sorted_for_binary_search() {
+        for table in [KERNEL, USER, GDI] {
+            assert!(table.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+    }
+
+    #[test]
+    fn module_names_match_ignoring_case() {
+        assert_eq!(ordinal_name("USER", 1), Some("MESSAGEBOX"));
+        assert_eq!(ordinal_name("User", 1), Some("MESSAGEBOX"));
+        assert_eq!(ordinal_name("krnl386", 3), Some("GETVERSION"));
+        assert_eq!(ordinal_name("gdi", 1), Some("SETBKCOLOR"));
+        assert_eq!(ordinal_name("USER32", 1), None);
+        assert_eq!(ordinal_name("KERNEL", 0), None);
+    }
+}
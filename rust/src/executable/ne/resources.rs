@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    restable::{NameInfo, ResourceId, ResourceType},
+    NewExecutable,
+};
+use crate::{
+    byte_stream::ByteStream,
+    error::Error,
+    executable::resource::{
+        accelerator::AcceleratorTable, dialog::Dialog, image, menu::Menu, string_table::StringTable,
+        version::VersionInfo,
+    },
+};
+
+impl NewExecutable {
+    /// <p>A resource of a predefined type.</p>
+    pub fn resource(&self, resource_type: ResourceType, id: &ResourceId) -> Result<&NameInfo, Error> {
+        self.resources
+            .as_ref()
+            .and_then(|r| r.find(resource_type, id))
+            .ok_or_else(|| Error::ResourceNotFound { resource: format!("{resource_type:?} {id}") })
+    }
+
+    /// <p>An RT_GROUP_ICON and the RT_ICONs it lists, as an .ico file.</p>
+    pub fn icon_file(&self, bst: &ByteStream, group: &ResourceId) -> Result<Vec<u8>, Error> {
+        let entries = image::read_group(self.resource(ResourceType::GroupIcon, group)?.data(bst)?, false)?;
+        let images = self.group_images(bst, ResourceType::Icon, &entries)?;
+        Ok(image::icon_file(&entries, &images))
+    }
+
+    /// <p>An RT_GROUP_CURSOR and the RT_CURSORs it lists, as a .cur file.</p>
+    pub fn cursor_file(&self, bst: &ByteStream, group: &ResourceId) -> Result<Vec<u8>, Error> {
+        let entries = image::read_group(self.resource(ResourceType::GroupCursor, group)?.data(bst)?, true)?;
+        let images = self.group_images(bst, ResourceType::Cursor, &entries)?;
+        image::cursor_file(&entries, &images)
+    }
+
+    /// <p>An RT_BITMAP as a .bmp file.</p>
+    pub fn bitmap_file(&self, bst: &ByteStream, id: &ResourceId) -> Result<Vec<u8>, Error> {
+        image::bitmap_file(self.resource(ResourceType::Bitmap, id)?.data(bst)?)
+    }
+
+    /// <p>Every RT_STRING bundle, merged into one table in id order.</p>
+    pub fn string_table(&self, bst: &ByteStream) -> Result<StringTable, Error> {
+        let mut strings = Vec::new();
+        for bundle in self.resources.iter().flat_map(|r| r.of_type(ResourceType::String)) {
+            let ResourceId::Integer(id) = bundle.id else { continue };
+            strings.extend(StringTable::read(bundle.data(bst)?, id)?.strings);
+        }
+        strings.sort_by_key(|(id, _)| *id);
+        Ok(StringTable { strings })
+    }
+
+    /// <p>String <code>id</code> of the string table, as <code>LoadString</code> would find it.</p>
+    pub fn string(&self, bst: &ByteStream, id: u16) -> Result<Option<String>, Error> {
+        let bundle = self.resource(ResourceType::String, &ResourceId::Integer(id / 16 + 1))?;
+        let table = StringTable::read(bundle.data(bst)?, id / 16 + 1)?;
+        Ok(table.strings.into_iter().find(|(i, _)| *i == id).map(|(_, text)| text))
+    }
+
+    pub fn menu(&self, bst: &ByteStream, id: &ResourceId) -> Result<Menu, Error> {
+        Menu::read(self.resource(ResourceType::Menu, id)?.data(bst)?)
+    }
+
+    pub fn dialog(&self, bst: &ByteStream, id: &ResourceId) -> Result<Dialog, Error> {
+        Dialog::read(self.resource(ResourceType::Dialog, id)?.data(bst)?)
+    }
+
+    pub fn accelerators(&self, bst: &ByteStream, id: &ResourceId) -> Result<AcceleratorTable, Error> {
+        AcceleratorTable::read(self.resource(ResourceType::Accelerator, id)?.data(bst)?)
+    }
+
+    /// <p>The first RT_VERSION resource, if there is one.</p>
+    pub fn version_info(&self, bst: &ByteStream) -> Result<Option<VersionInfo>, Error> {
+        match self.resources.iter().flat_map(|r| r.of_type(ResourceType::Version)).next() {
+            Some(resource) => Ok(Some(VersionInfo::read_ne(resource.data(bst)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// <p>The menus, dialogs, accelerator tables and strings as an .rc script, for reading or diffing.  A resource
+    /// that doesn't decode is left as a comment saying why.</p>
+    pub fn to_rc(&self, bst: &ByteStream) -> String {
+        let mut rc = String::new();
+        let Some(table) = &self.resources else { return rc };
+        for (type_info, resource) in table.resources() {
+            let text = match type_info.resource_type() {
+                Some(ResourceType::Menu) => resource.data(bst).and_then(Menu::read).map(|m| m.to_string()),
+                Some(ResourceType::Dialog) => resource.data(bst).and_then(Dialog::read).map(|d| d.to_string()),
+                Some(ResourceType::Accelerator) => {
+                    resource.data(bst).and_then(AcceleratorTable::read).map(|a| a.to_string())
+                }
+                _ => continue,
+            };
+            let name = match &resource.id {
+                ResourceId::Integer(id) => id.to_string(),
+                ResourceId::Name(name) => name.clone(),
+            };
+            match text {
+                Ok(text) => rc += &format!("{name} {text}\n"),
+                Err(e) => rc += &format!("// {name}: {e}\n\n"),
+            }
+        }
+        match self.string_table(bst) {
+            Ok(strings) if !strings.strings.is_empty() => rc += &strings.to_string(),
+            Ok(_) => {}
+            Err(e) => rc += &format!("// STRINGTABLE: {e}\n"),
+        }
+        rc
+    }
+
+    fn group_images<'a>(
+        &self,
+        bst: &'a ByteStream,
+        resource_type: ResourceType,
+        entries: &[image::GroupEntry],
+    ) -> Result<Vec<&'a [u8]>, Error> {
+        entries.iter().map(|e| self.resource(resource_type, &ResourceId::Integer(e.id))?.data(bst)).collect()
+    }
+
+    /// <p>Writes every resource into <code>dir</code>, named after its type and id, and returns the paths written.
+    /// Icon and cursor groups become .ico and .cur files, and so do icons and cursors no group uses; bitmaps become
+    /// .bmp files.  Everything else, and anything that fails to convert, is written as the raw bytes in a .bin
+    /// file.  A resource whose data isn't in the file is skipped and reported, so one bad entry doesn't stop the
+    /// rest; only failing to write is an error.</p>
+    pub fn extract_resources(&self, bst: &ByteStream, dir: &Path) -> Result<Extraction, Error> {
+        let mut extraction = Extraction::default();
+        let Some(table) = &self.resources else { return Ok(extraction) };
+        fs::create_dir_all(dir)?;
+
+        let mut grouped = BTreeSet::new();
+        for (resource_type, cursor) in [(ResourceType::GroupIcon, false), (ResourceType::GroupCursor, true)] {
+            for group in table.of_type(resource_type) {
+                if let Ok(entries) = group.data(bst).and_then(|data| image::read_group(data, cursor)) {
+                    let member = if cursor { ResourceType::Cursor } else { ResourceType::Icon };
+                    grouped.extend(entries.iter().map(|e| (member as u16, e.id)));
+                }
+            }
+        }
+
+        for (type_info, resource) in table.resources() {
+            let resource_type = type_info.resource_type();
+            let converted = match resource_type {
+                Some(ResourceType::GroupIcon) => Some((self.icon_file(bst, &resource.id), "ico")),
+                Some(ResourceType::GroupCursor) => Some((self.cursor_file(bst, &resource.id), "cur")),
+                Some(ResourceType::Bitmap) => Some((resource.data(bst).and_then(image::bitmap_file), "bmp")),
+                Some(t @ (ResourceType::Icon | ResourceType::Cursor)) => {
+                    if matches!(resource.id, ResourceId::Integer(id) if grouped.contains(&(t as u16, id))) {
+                        continue;
+                    }
+                    Some(match t {
+                        ResourceType::Icon => (resource.data(bst).and_then(image::single_icon_file), "ico"),
+                        _ => (resource.data(bst).and_then(image::single_cursor_file), "cur"),
+                    })
+                }
+                _ => None,
+            };
+            let type_name = match resource_type {
+                Some(t) => format!("{t:?}"),
+                None => type_info.type_id.to_string(),
+            };
+            let (bytes, extension) = match (converted, resource.data(bst)) {
+                (Some((Ok(bytes), extension)), _) => (bytes, extension),
+                (_, Ok(data)) => (data.to_vec(), "bin"),
+                (_, Err(e)) => {
+                    extraction.skipped.push((format!("{type_name} {}", resource.id), e));
+                    continue;
+                }
+            };
+            let name = format!("{}_{}.{extension}", file_safe(&type_name), file_safe(&resource.id.to_string()));
+            let path = dir.join(name);
+            fs::write(&path, bytes)?;
+            extraction.written.push(path);
+        }
+        Ok(extraction)
+    }
+}
+
+/// <p>What <code>extract_resources</code> did with each resource.</p>
+#[derive(Debug, Default)]
+pub struct Extraction {
+    /// The files written.
+    pub written: Vec<PathBuf>,
+    /// The resources that couldn't be read, by type and id, with why.
+    pub skipped: Vec<(String, Error)>,
+}
+
+fn file_safe(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
@@ -0,0 +1,273 @@
+use std::{fmt, io::SeekFrom, ops::Range};
+
+use super::flags::ResourceFlags;
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>The predefined resource types, numbered the way they are stored in the table, with the high bit set.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ResourceType {
+    Cursor = 0x8001,
+    Bitmap,
+    Icon,
+    Menu,
+    Dialog,
+    String,
+    FontDir,
+    Font,
+    Accelerator,
+    RCData,
+    MessageTable,
+    GroupCursor,
+    GroupIcon = 0x800E,
+    Version = 0x8010,
+    DLGInclude,
+    PlugPlay = 0x8013,
+    VXD,
+    AniCursor,
+    AniIcon,
+    HTML,
+    Manifest,
+}
+
+impl ResourceType {
+    pub fn from_word(word: u16) -> Option<Self> {
+        use ResourceType::*;
+        Some(match word {
+            0x8001 => Cursor,
+            0x8002 => Bitmap,
+            0x8003 => Icon,
+            0x8004 => Menu,
+            0x8005 => Dialog,
+            0x8006 => String,
+            0x8007 => FontDir,
+            0x8008 => Font,
+            0x8009 => Accelerator,
+            0x800A => RCData,
+            0x800B => MessageTable,
+            0x800C => GroupCursor,
+            0x800E => GroupIcon,
+            0x8010 => Version,
+            0x8011 => DLGInclude,
+            0x8013 => PlugPlay,
+            0x8014 => VXD,
+            0x8015 => AniCursor,
+            0x8016 => AniIcon,
+            0x8017 => HTML,
+            0x8018 => Manifest,
+            _ => return None,
+        })
+    }
+}
+
+/// <p>A resource type or name, either a number or a string.</p>
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceId {
+    /// The number without the high bit that marks it as one.
+    Integer(u16),
+    Name(String),
+}
+
+impl ResourceId {
+    /// <p>Reads the word naming a type or resource.  With the high bit set it is a number, otherwise it is the offset
+    /// from the start of the resource table of a length-prefixed string.</p>
+    fn read(bst: &mut ByteStream, table_start: usize) -> Result<Self, Error> {
+        let word = bst.read_word()?;
+        if word & 0x8000 != 0 {
+            return Ok(ResourceId::Integer(word & 0x7FFF));
+        }
+        let at = table_start + word as usize;
+        let len = bst.read_byte_at(at)? as usize;
+        Ok(ResourceId::Name(bst.read_string_from_to(at + 1, at + 1 + len)?))
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceId::Integer(id) => write!(f, "{id}"),
+            ResourceId::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// <p>Where one resource is and what it is called.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameInfo {
+    /// File offset of the data, already shifted by the table's alignment shift.
+    pub offset: usize,
+    /// Length of the data, already shifted by the table's alignment shift, so it may include padding.
+    pub length: usize,
+    pub flags: ResourceFlags,
+    pub id: ResourceId,
+}
+
+impl NameInfo {
+    fn read(bst: &mut ByteStream, table_start: usize, align_shift: u16) -> Result<Self, Error> {
+        let offset = (bst.read_word()? as usize) << align_shift;
+        let length = (bst.read_word()? as usize) << align_shift;
+        let flags = ResourceFlags::from_bits(bst.read_word()?);
+        let id = ResourceId::read(bst, table_start)?;
+        // the handle and usage count Windows fills in at run time; files saved from memory can have them set
+        bst.seek(SeekFrom::Current(4))?;
+        Ok(NameInfo { offset, length, flags, id })
+    }
+
+    pub fn data_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.length
+    }
+
+    /// <p>The resource's raw bytes, straight out of the file.</p>
+    pub fn data<'a>(&self, bst: &'a ByteStream) -> Result<&'a [u8], Error> {
+        bst.as_slice()
+            .get(self.data_range())
+            .ok_or(Error::Truncated { offset: self.offset, needed: self.length })
+    }
+}
+
+/// <p>All the resources of one type.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeInfo {
+    pub type_id: ResourceId,
+    pub resources: Vec<NameInfo>,
+}
+
+impl TypeInfo {
+    fn read(bst: &mut ByteStream, table_start: usize, align_shift: u16) -> Result<Self, Error> {
+        let type_id = ResourceId::read(bst, table_start)?;
+        let count = bst.read_word()?;
+        // where Windows keeps the resource loader function at run time
+        bst.seek(SeekFrom::Current(4))?;
+        let mut resources = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            resources.push(NameInfo::read(bst, table_start, align_shift)?);
+        }
+        Ok(TypeInfo { type_id, resources })
+    }
+
+    /// <p>The predefined type this is, if it is one.</p>
+    pub fn resource_type(&self) -> Option<ResourceType> {
+        match self.type_id {
+            ResourceId::Integer(id) => ResourceType::from_word(id | 0x8000),
+            ResourceId::Name(_) => None,
+        }
+    }
+}
+
+pub struct ResourceTable {
+    /// Resource offsets and lengths are stored in units of <code>1 << align_shift</code> bytes.
+    pub align_shift: u16,
+    pub types: Vec<TypeInfo>,
+}
+
+impl ResourceTable {
+    /// <p>Reads the table starting at the current position, up to the zero word that ends the type list.  Names are
+    /// looked up where their offsets point instead of reading the string list after it, which some linkers leave
+    /// out.</p>
+    pub fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        let table_start = bst.pos;
+        let align_shift = bst.read_word()?;
+        if align_shift > 16 {
+            return Err(Error::InvalidHeader { field: "resource alignment shift" });
+        }
+        let mut types = Vec::new();
+        while bst.peek_word()? != 0 {
+            types.push(TypeInfo::read(bst, table_start, align_shift)?);
+        }
+        bst.read_word()?;
+        Ok(ResourceTable { align_shift, types })
+    }
+
+    /// <p>Every resource with the type it is filed under.</p>
+    pub fn resources(&self) -> impl Iterator<Item = (&TypeInfo, &NameInfo)> {
+        self.types.iter().flat_map(|t| t.resources.iter().map(move |r| (t, r)))
+    }
+
+    /// <p>The resources of a predefined type.</p>
+    pub fn of_type(&self, resource_type: ResourceType) -> impl Iterator<Item = &NameInfo> {
+        self.types.iter().filter(move |t| t.resource_type() == Some(resource_type)).flat_map(|t| &t.resources)
+    }
+
+    pub fn find(&self, resource_type: ResourceType, id: &ResourceId) -> Option<&NameInfo> {
+        self.of_type(resource_type).find(|r| &r.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// <p>Where the table starts in <code>file()</code>, so name offsets relative to it can be told apart from file
+    /// offsets.</p>
+    const TABLE: usize = 0x10;
+
+    /// <p>A table with a 16-byte alignment shift, holding bitmap 7 and a resource HELLO of type MYTYPE, with the
+    /// run-time fields filled in as a file saved from memory would have them.</p>
+    fn file() -> Vec<u8> {
+        let mut file = vec![0; 0x70];
+        let mut table = vec![0x04, 0x00];
+        table.extend_from_slice(&[0x02, 0x80, 0x01, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+        table.extend_from_slice(&[0x05, 0x00, 0x01, 0x00, 0x30, 0x00, 0x07, 0x80, 0xAA, 0xAA, 0xAA, 0xAA]);
+        table.extend_from_slice(&[0x2C, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        table.extend_from_slice(&[0x06, 0x00, 0x01, 0x00, 0x40, 0x00, 0x33, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        table.extend_from_slice(&[0x00, 0x00]);
+        table.extend_from_slice(b"\x06MYTYPE\x05HELLO\x00");
+        file[TABLE..TABLE + table.len()].copy_from_slice(&table);
+        file[0x50..0x60].fill(0xB0);
+        file[0x60..0x65].copy_from_slice(b"hello");
+        file
+    }
+
+    fn read(file: Vec<u8>) -> Result<(ResourceTable, ByteStream), Error> {
+        let mut bst = ByteStream::new(file);
+        bst.pos = TABLE;
+        Ok((ResourceTable::read(&mut bst)?, bst))
+    }
+
+    #[test]
+    fn reads_integer_and_named_types_and_ids() {
+        let (table, _) = read(file()).unwrap();
+        assert_eq!(table.align_shift, 4);
+        assert_eq!(table.types.len(), 2);
+
+        let bitmaps = &table.types[0];
+        assert_eq!((&bitmaps.type_id, bitmaps.resource_type()), (&ResourceId::Integer(2), Some(ResourceType::Bitmap)));
+        let bitmap = &bitmaps.resources[0];
+        assert_eq!(bitmap.id, ResourceId::Integer(7));
+        assert!(bitmap.flags.movable && bitmap.flags.shareable && !bitmap.flags.preload);
+
+        let custom = &table.types[1];
+        assert_eq!((&custom.type_id, custom.resource_type()), (&ResourceId::Name("MYTYPE".to_owned()), None));
+        assert_eq!(custom.resources[0].id, ResourceId::Name("HELLO".to_owned()));
+        assert!(custom.resources[0].flags.preload);
+
+        assert_eq!(table.resources().count(), 2);
+        assert_eq!(table.find(ResourceType::Bitmap, &ResourceId::Integer(7)), Some(bitmap));
+        assert_eq!(table.find(ResourceType::Bitmap, &ResourceId::Integer(8)), None);
+    }
+
+    #[test]
+    fn offsets_and_lengths_are_shifted() {
+        let (table, bst) = read(file()).unwrap();
+        let bitmap = &table.types[0].resources[0];
+        assert_eq!(bitmap.data_range(), 0x50..0x60);
+        assert_eq!(bitmap.data(&bst).unwrap(), &[0xB0; 16]);
+        let hello = &table.types[1].resources[0];
+        assert_eq!(&hello.data(&bst).unwrap()[..6], b"hello\0");
+    }
+
+    #[test]
+    fn data_past_the_end_is_an_error() {
+        let mut file = file();
+        file.truncate(0x68);
+        let (table, bst) = read(file).unwrap();
+        assert!(matches!(table.types[1].resources[0].data(&bst), Err(Error::Truncated { offset: 0x60, needed: 0x10 })));
+    }
+
+    #[test]
+    fn an_alignment_shift_past_16_is_invalid() {
+        let mut file = file();
+        file[TABLE] = 17;
+        assert!(matches!(read(file), Err(Error::InvalidHeader { field: "resource alignment shift" })));
+    }
+}
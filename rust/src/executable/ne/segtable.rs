@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use super::flags::SegmentFlags;
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>What a relocation writes at its source.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelocationSource {
+    /// The low byte of the target offset.
+    LowByte,
+    /// The target's segment (a selector in protected mode).
+    Segment,
+    /// A 16:16 far pointer, offset first.
+    FarPointer,
+    /// The target's 16-bit offset.
+    Offset,
+    /// A 16:32 far pointer (OS/2).
+    Pointer48,
+    /// The target's 32-bit offset (OS/2).
+    Offset32,
+    Other(u8),
+}
+
+impl RelocationSource {
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => RelocationSource::LowByte,
+            2 => RelocationSource::Segment,
+            3 => RelocationSource::FarPointer,
+            5 => RelocationSource::Offset,
+            11 => RelocationSource::Pointer48,
+            13 => RelocationSource::Offset32,
+            other => RelocationSource::Other(other),
+        }
+    }
+
+    /// <p>How many bytes are written at the source.</p>
+    pub fn size(&self) -> usize {
+        match self {
+            RelocationSource::LowByte => 1,
+            RelocationSource::Segment | RelocationSource::Offset => 2,
+            RelocationSource::FarPointer | RelocationSource::Offset32 => 4,
+            RelocationSource::Pointer48 => 6,
+            RelocationSource::Other(_) => 2,
+        }
+    }
+}
+
+/// <p>What a relocation points at.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelocationTarget {
+    /// An offset in one of this module's fixed segments, numbered from 1.
+    Internal { segment: u8, offset: u16 },
+    /// An entry point in one of this module's movable segments, found through the entry table.
+    InternalMovable { ordinal: u16 },
+    /// An export of another module by ordinal.  <code>module</code> indexes the module reference table from 1.
+    ImportedOrdinal { module: u16, ordinal: u16 },
+    /// An export of another module by name.  <code>name_offset</code> is into the imported names table.
+    ImportedName { module: u16, name_offset: u16 },
+    /// A floating-point fixup the OS patches in for machines without an x87.
+    OsFixup { fixup: u16 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    pub source: RelocationSource,
+    /// The target is added to what is already at the source.  Otherwise the source holds the offset of the next
+    /// place to patch, ending at 0xFFFF.
+    pub additive: bool,
+    /// Where in the segment the (first) source is.
+    pub offset: u16,
+    pub target: RelocationTarget,
+}
+
+impl Relocation {
+    pub fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        let source = RelocationSource::from_byte(bst.read_byte()?);
+        let flags = bst.read_byte()?;
+        let offset = bst.read_word()?;
+        let target = match flags & 0b11 {
+            0 => {
+                let segment = bst.read_byte()?;
+                bst.read_byte()?;
+                let offset = bst.read_word()?;
+                match segment {
+                    0xFF => RelocationTarget::InternalMovable { ordinal: offset },
+                    _ => RelocationTarget::Internal { segment, offset },
+                }
+            }
+            1 => RelocationTarget::ImportedOrdinal { module: bst.read_word()?, ordinal: bst.read_word()? },
+            2 => RelocationTarget::ImportedName { module: bst.read_word()?, name_offset: bst.read_word()? },
+            _ => {
+                let fixup = bst.read_word()?;
+                bst.read_word()?;
+                RelocationTarget::OsFixup { fixup }
+            }
+        };
+        Ok(Relocation { source, additive: flags & 0b100 != 0, offset, target })
+    }
+
+    /// <p>Every offset in <code>data</code> this relocation patches.  An additive one patches only its own offset; any
+    /// other is followed down the chain stored in the segment until 0xFFFF, or until it loops or runs off the end.</p>
+    pub fn sources(&self, data: &[u8]) -> Vec<u16> {
+        let mut sources = vec![self.offset];
+        if self.additive {
+            return sources;
+        }
+        let mut at = self.offset as usize;
+        while let Some(next) = data.get(at..at + 2).map(|w| u16::from_le_bytes([w[0], w[1]])) {
+            if next == 0xFFFF || sources.contains(&next) {
+                break;
+            }
+            sources.push(next);
+            at = next as usize;
+        }
+        sources
+    }
+}
+
+/// <p>An entry of the segment table, with the relocations stored after its data.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    /// Where the data starts, in units of the header's shift count.  0 if the segment has no data in the file.
+    pub sector: u16,
+    /// The length of the data in the file; 0 in the table means 64K.
+    pub length: u32,
+    pub flags: SegmentFlags,
+    /// How much memory to allocate for it; 0 in the table means 64K.
+    pub min_alloc: u32,
+    pub relocations: Vec<Relocation>,
+}
+
+impl Segment {
+    /// <p>Reads one segment table entry and, if it says it has any, its relocation records.  The position is left
+    /// after the table entry.</p>
+    pub fn read(bst: &mut ByteStream, shift_count: u16) -> Result<Self, Error> {
+        let sector = bst.read_word()?;
+        let length = match bst.read_word()? {
+            0 if sector != 0 => 0x10000,
+            n => n as u32,
+        };
+        let flags = SegmentFlags::from_bits(bst.read_word()?);
+        let min_alloc = match bst.read_word()? {
+            0 => 0x10000,
+            n => n as u32,
+        };
+        let mut segment = Segment { sector, length, flags, min_alloc, relocations: Vec::new() };
+
+        if flags.relocations && sector != 0 {
+            let table_end = bst.pos;
+            bst.pos = segment.data_range(shift_count).end;
+            let count = bst.read_word()?;
+            for _ in 0..count {
+                segment.relocations.push(Relocation::read(bst)?);
+            }
+            bst.pos = table_end;
+        }
+        Ok(segment)
+    }
+
+    /// <p>Where the segment's data is in the file.</p>
+    pub fn data_range(&self, shift_count: u16) -> Range<usize> {
+        let start = (self.sector as usize) << shift_count;
+        start..start + self.length as usize
+    }
+
+    pub fn data<'a>(&self, bst: &'a ByteStream, shift_count: u16) -> Result<&'a [u8], Error> {
+        let range = self.data_range(shift_count);
+        bst.as_slice().get(range.clone()).ok_or(Error::Truncated { offset: range.start, needed: range.len() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// <p>Data starts are in units of 16 bytes.</p>
+    const SHIFT_COUNT: u16 = 4;
+
+    /// <p>A table of two segments: one with data at sector 2 and a relocation of each target kind after it, and one
+    /// without data in the file.  The chain of the far pointer runs through offsets 2 and 8, and the OS fixup's
+    /// chain points back at itself.</p>
+    fn file() -> Vec<u8> {
+        let mut file = vec![0; 0x60];
+        file[0x00..0x10].copy_from_slice(&[
+            0x02, 0x00, 0x10, 0x00, 0x50, 0x01, 0x00, 0x00, //
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x02,
+        ]);
+        let data = [0x08, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x0A, 0x00, 0xFF, 0xFF, 0xFF, 0xFF];
+        file[0x22..0x30].copy_from_slice(&data);
+        file[0x30..0x5A].copy_from_slice(&[
+            0x05, 0x00, //
+            0x03, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x01, // far pointer to 1:0100
+            0x02, 0x00, 0x0E, 0x00, 0xFF, 0x00, 0x03, 0x00, // segment of movable entry 3
+            0x05, 0x05, 0x06, 0x00, 0x01, 0x00, 0x37, 0x00, // offset of module 1's ordinal 0x37, additive
+            0x00, 0x02, 0x0C, 0x00, 0x02, 0x00, 0x10, 0x00, // low byte of module 2's name at 0x10
+            0x0D, 0x03, 0x0A, 0x00, 0x01, 0x00, 0x00, 0x00, // 32-bit offset of OS fixup 1
+        ]);
+        file
+    }
+
+    fn read() -> (Segment, Segment) {
+        let mut bst = ByteStream::new(file());
+        let first = Segment::read(&mut bst, SHIFT_COUNT).unwrap();
+        assert_eq!(bst.pos, 8);
+        (first, Segment::read(&mut bst, SHIFT_COUNT).unwrap())
+    }
+
+    #[test]
+    fn reads_table_entries() {
+        let (first, second) = read();
+        assert_eq!((first.sector, first.length, first.min_alloc), (2, 0x10, 0x10000));
+        assert!(first.flags.relocations && first.flags.preload && first.flags.movable && !first.flags.data);
+        assert_eq!(first.data_range(SHIFT_COUNT), 0x20..0x30);
+        assert_eq!(first.data(&ByteStream::new(file()), SHIFT_COUNT).unwrap()[2..4], [0x08, 0x00]);
+
+        // without data in the file, the relocation flag has nothing to go with
+        assert_eq!((second.sector, second.length, second.min_alloc), (0, 0, 0x200));
+        assert!(second.flags.data && second.flags.relocations && second.relocations.is_empty());
+    }
+
+    #[test]
+    fn reads_each_kind_of_relocation() {
+        let (first, _) = read();
+        let relocation = |source, additive, offset, target| Relocation { source, additive, offset, target };
+        assert_eq!(
+            first.relocations,
+            [
+                relocation(
+                    RelocationSource::FarPointer,
+                    false,
+                    2,
+                    RelocationTarget::Internal { segment: 1, offset: 0x100 }
+                ),
+                relocation(RelocationSource::Segment, false, 0xE, RelocationTarget::InternalMovable { ordinal: 3 }),
+                relocation(
+                    RelocationSource::Offset,
+                    true,
+                    6,
+                    RelocationTarget::ImportedOrdinal { module: 1, ordinal: 0x37 }
+                ),
+                relocation(
+                    RelocationSource::LowByte,
+                    false,
+                    0xC,
+                    RelocationTarget::ImportedName { module: 2, name_offset: 0x10 }
+                ),
+                relocation(RelocationSource::Offset32, false, 0xA, RelocationTarget::OsFixup { fixup: 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn sources_follow_the_chain_unless_additive() {
+        let (first, _) = read();
+        let data = first.data(&ByteStream::new(file()), SHIFT_COUNT).unwrap().to_vec();
+        let sources: Vec<Vec<u16>> = first.relocations.iter().map(|r| r.sources(&data)).collect();
+        assert_eq!(sources, [vec![2, 8], vec![0xE], vec![6], vec![0xC], vec![0xA]]);
+    }
+
+    #[test]
+    fn source_types_have_their_sizes() {
+        let sizes: Vec<(RelocationSource, usize)> =
+            [0, 2, 3, 5, 11, 13, 7].map(RelocationSource::from_byte).iter().map(|s| (*s, s.size())).collect();
+        assert_eq!(
+            sizes,
+            [
+                (RelocationSource::LowByte, 1),
+                (RelocationSource::Segment, 2),
+                (RelocationSource::FarPointer, 4),
+                (RelocationSource::Offset, 2),
+                (RelocationSource::Pointer48, 6),
+                (RelocationSource::Offset32, 4),
+                (RelocationSource::Other(7), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use super::{PortableExecutable, IMAGE_DIRECTORY_ENTRY_EXPORT};
+use crate::{byte_stream::ByteStream, error::Error};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    pub ordinal: u32,
+    pub name: Option<String>,
+    /// The address of the export, or of its forwarder string.
+    pub rva: u32,
+    /// <code>DLL.Function</code> or <code>DLL.#ordinal</code> when the export is forwarded to another DLL.
+    pub forwarder: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportDirectory {
+    /// The DLL's own name.
+    pub name: String,
+    pub time_date_stamp: u32,
+    /// (major, minor)
+    pub version: (u16, u16),
+    /// The ordinal of the first entry of the address table.
+    pub ordinal_base: u32,
+    /// In ordinal order, without the unused slots of the address table.
+    pub exports: Vec<Export>,
+}
+
+impl PortableExecutable {
+    /// <p>The export directory.  An export whose address falls inside the directory is a forwarder, and the
+    /// address is that of its string.</p>
+    pub fn exports(&self, bst: &ByteStream) -> Result<Option<ExportDirectory>, Error> {
+        let Some(directory) = self.data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT) else { return Ok(None) };
+        let at = directory.virtual_address;
+        let time_date_stamp = self.dword_at(bst, Self::rva_add(at, 4)?)?;
+        let version = (self.word_at(bst, Self::rva_add(at, 8)?)?, self.word_at(bst, Self::rva_add(at, 10)?)?);
+        let name = self.string_at(bst, self.dword_at(bst, Self::rva_add(at, 12)?)?)?;
+        let ordinal_base = self.dword_at(bst, Self::rva_add(at, 16)?)?;
+        let function_count = self.dword_at(bst, Self::rva_add(at, 20)?)?;
+        let name_count = self.dword_at(bst, Self::rva_add(at, 24)?)?;
+        let functions = self.dword_at(bst, Self::rva_add(at, 28)?)?;
+        let names = self.dword_at(bst, Self::rva_add(at, 32)?)?;
+        let name_ordinals = self.dword_at(bst, Self::rva_add(at, 36)?)?;
+
+        let directory_end = Self::rva_add(at, directory.size)?;
+        let ordinal =
+            |index: u32| ordinal_base.checked_add(index).ok_or(Error::InvalidHeader { field: "export ordinal base" });
+
+        let mut exports = Vec::new();
+        for i in 0..function_count.min(0x10000) {
+            let rva = self.dword_at(bst, Self::entry_rva(functions, i, 4)?)?;
+            if rva == 0 {
+                continue;
+            }
+            let forwarded = (at..directory_end).contains(&rva);
+            exports.push(Export {
+                ordinal: ordinal(i)?,
+                name: None,
+                rva,
+                forwarder: if forwarded { Some(self.string_at(bst, rva)?) } else { None },
+            });
+        }
+        for i in 0..name_count.min(0x10000) {
+            let index = self.word_at(bst, Self::entry_rva(name_ordinals, i, 2)?)? as u32;
+            let name = self.string_at(bst, self.dword_at(bst, Self::entry_rva(names, i, 4)?)?)?;
+            let ordinal = ordinal(index)?;
+            if let Some(export) = exports.iter_mut().find(|e| e.ordinal == ordinal) {
+                export.name.get_or_insert(name);
+            }
+        }
+        Ok(Some(ExportDirectory { name, time_date_stamp, version, ordinal_base, exports }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executable::pe::tests::{dwords, put, with_rdata, RDATA};
+
+    #[test]
+    fn exports_by_ordinal_with_names_and_forwarders() {
+        let mut data = vec![0; 0x400];
+        put(&mut data, RDATA, &dwords(&[0, 0x1234, 0x0002_0001, 0x2040, 5, 5, 2, 0x2050, 0x2068, 0x2070]));
+        put(&mut data, 0x2040, b"demo.dll\0");
+        put(&mut data, 0x2050, &dwords(&[0x1000, 0, 0x2080, 0x1010, 0x2098]));
+        put(&mut data, 0x2068, &dwords(&[0x20B0, 0x20B8]));
+        put(&mut data, 0x2070, &[0, 0, 2, 0]);
+        put(&mut data, 0x2080, b"KERNEL32.HeapAlloc\0");
+        put(&mut data, 0x2098, b"USER32.#12\0");
+        put(&mut data, 0x20B0, b"First\0");
+        put(&mut data, 0x20B8, b"Forward\0");
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_EXPORT, RDATA, 0x100)], &data);
+
+        let directory = pe.exports(&bst).unwrap().unwrap();
+        assert_eq!(directory.name, "demo.dll");
+        assert_eq!((directory.time_date_stamp, directory.version, directory.ordinal_base), (0x1234, (1, 2), 5));
+        let export = |ordinal, name: Option<&str>, rva, forwarder: Option<&str>| Export {
+            ordinal,
+            name: name.map(str::to_string),
+            rva,
+            forwarder: forwarder.map(str::to_string),
+        };
+        assert_eq!(
+            directory.exports,
+            [
+                export(5, Some("First"), 0x1000, None),
+                export(7, Some("Forward"), 0x2080, Some("KERNEL32.HeapAlloc")),
+                export(8, None, 0x1010, None),
+                export(9, None, 0x2098, Some("USER32.#12")),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_export_directory() {
+        let (pe, bst) = with_rdata(false, &[], &[0; 0x10]);
+        assert_eq!(pe.exports(&bst).unwrap(), None);
+    }
+}
@@ -0,0 +1,97 @@
+/// <p>The CPU an image is built for.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Machine {
+    Unknown,
+    I386,
+    Amd64,
+    Arm,
+    ArmThumb2,
+    Arm64,
+    Ia64,
+    Other(u16),
+}
+
+impl Machine {
+    pub fn from_word(word: u16) -> Self {
+        match word {
+            0x0000 => Machine::Unknown,
+            0x014C => Machine::I386,
+            0x8664 => Machine::Amd64,
+            0x01C0 => Machine::Arm,
+            0x01C4 => Machine::ArmThumb2,
+            0xAA64 => Machine::Arm64,
+            0x0200 => Machine::Ia64,
+            other => Machine::Other(other),
+        }
+    }
+}
+
+/// <p>What an image needs to run under.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Unknown,
+    Native,
+    WindowsGui,
+    WindowsCui,
+    Os2Cui,
+    PosixCui,
+    Windows9xDriver,
+    WindowsCeGui,
+    EfiApplication,
+    EfiBootServiceDriver,
+    EfiRuntimeDriver,
+    EfiRom,
+    Xbox,
+    WindowsBootApplication,
+    Other(u16),
+}
+
+impl Subsystem {
+    pub fn from_word(word: u16) -> Self {
+        match word {
+            0 => Subsystem::Unknown,
+            1 => Subsystem::Native,
+            2 => Subsystem::WindowsGui,
+            3 => Subsystem::WindowsCui,
+            5 => Subsystem::Os2Cui,
+            7 => Subsystem::PosixCui,
+            8 => Subsystem::Windows9xDriver,
+            9 => Subsystem::WindowsCeGui,
+            10 => Subsystem::EfiApplication,
+            11 => Subsystem::EfiBootServiceDriver,
+            12 => Subsystem::EfiRuntimeDriver,
+            13 => Subsystem::EfiRom,
+            14 => Subsystem::Xbox,
+            16 => Subsystem::WindowsBootApplication,
+            other => Subsystem::Other(other),
+        }
+    }
+}
+
+// COFF file header characteristics
+pub const IMAGE_FILE_RELOCS_STRIPPED: u16 = 0x0001;
+pub const IMAGE_FILE_EXECUTABLE_IMAGE: u16 = 0x0002;
+pub const IMAGE_FILE_LARGE_ADDRESS_AWARE: u16 = 0x0020;
+pub const IMAGE_FILE_32BIT_MACHINE: u16 = 0x0100;
+pub const IMAGE_FILE_DEBUG_STRIPPED: u16 = 0x0200;
+pub const IMAGE_FILE_SYSTEM: u16 = 0x1000;
+pub const IMAGE_FILE_DLL: u16 = 0x2000;
+
+// optional header DLL characteristics
+pub const IMAGE_DLLCHARACTERISTICS_HIGH_ENTROPY_VA: u16 = 0x0020;
+pub const IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE: u16 = 0x0040;
+pub const IMAGE_DLLCHARACTERISTICS_FORCE_INTEGRITY: u16 = 0x0080;
+pub const IMAGE_DLLCHARACTERISTICS_NX_COMPAT: u16 = 0x0100;
+pub const IMAGE_DLLCHARACTERISTICS_NO_SEH: u16 = 0x0400;
+pub const IMAGE_DLLCHARACTERISTICS_GUARD_CF: u16 = 0x4000;
+pub const IMAGE_DLLCHARACTERISTICS_TERMINAL_SERVER_AWARE: u16 = 0x8000;
+
+// section characteristics
+pub const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+pub const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+pub const IMAGE_SCN_CNT_UNINITIALIZED_DATA: u32 = 0x0000_0080;
+pub const IMAGE_SCN_MEM_DISCARDABLE: u32 = 0x0200_0000;
+pub const IMAGE_SCN_MEM_SHARED: u32 = 0x1000_0000;
+pub const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+pub const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+pub const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
@@ -0,0 +1,250 @@
+use super::{
+    PortableExecutable, IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT,
+    IMAGE_DIRECTORY_ENTRY_IMPORT,
+};
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>One function imported from a DLL.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedFunction {
+    /// Set when it is imported by ordinal.
+    pub ordinal: Option<u16>,
+    /// Set when it is imported by name.
+    pub name: Option<String>,
+    /// Where the loader should look in the DLL's export name table first.
+    pub hint: Option<u16>,
+    /// The import address table slot the loader fills in.
+    pub iat_rva: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedDll {
+    pub name: String,
+    /// Non-zero for a bound import: the timestamp of the DLL the addresses were bound against.
+    pub time_date_stamp: u32,
+    pub functions: Vec<ImportedFunction>,
+}
+
+/// <p>A DLL named in the bound import directory, with the DLLs it forwards to.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundImport {
+    pub name: String,
+    pub time_date_stamp: u32,
+    /// (name, time stamp) of each forwarder reference.
+    pub forwarders: Vec<(String, u32)>,
+}
+
+/// <p>Import directories are lists of descriptors ended by an all-zero one.  This caps how many are read from
+/// damaged files that never end the list.</p>
+const MAX_DESCRIPTORS: usize = 0x10000;
+
+impl PortableExecutable {
+    /// <p>The import directory.</p>
+    pub fn imports(&self, bst: &ByteStream) -> Result<Vec<ImportedDll>, Error> {
+        let Some(directory) = self.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT) else { return Ok(Vec::new()) };
+        let mut dlls = Vec::new();
+        for i in 0..MAX_DESCRIPTORS as u32 {
+            let at = Self::entry_rva(directory.virtual_address, i, 20)?;
+            let lookup_table = self.dword_at(bst, at)?;
+            let time_date_stamp = self.dword_at(bst, Self::rva_add(at, 4)?)?;
+            let name = self.dword_at(bst, Self::rva_add(at, 12)?)?;
+            let address_table = self.dword_at(bst, Self::rva_add(at, 16)?)?;
+            if name == 0 && address_table == 0 {
+                break;
+            }
+            // old linkers left the lookup table out, so the address table holds the names until the DLL is bound
+            let thunks = if lookup_table != 0 { lookup_table } else { address_table };
+            dlls.push(ImportedDll {
+                name: self.string_at(bst, name)?,
+                time_date_stamp,
+                functions: self.thunks(bst, thunks, address_table)?,
+            });
+        }
+        Ok(dlls)
+    }
+
+    /// <p>The delay-load import directory.  Descriptors from before Visual C++ 7 hold virtual addresses instead of
+    /// RVAs, which the first bit of their attributes tells apart.</p>
+    pub fn delay_imports(&self, bst: &ByteStream) -> Result<Vec<ImportedDll>, Error> {
+        let Some(directory) = self.data_directory(IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT) else { return Ok(Vec::new()) };
+        let mut dlls = Vec::new();
+        for i in 0..MAX_DESCRIPTORS as u32 {
+            let at = Self::entry_rva(directory.virtual_address, i, 32)?;
+            let attributes = self.dword_at(bst, at)?;
+            let name = self.dword_at(bst, Self::rva_add(at, 4)?)?;
+            if name == 0 {
+                break;
+            }
+            let base = if attributes & 1 != 0 { 0 } else { self.optional_header.image_base as u32 };
+            let rva = |field: u32| field.wrapping_sub(base);
+            let address_table = rva(self.dword_at(bst, Self::rva_add(at, 12)?)?);
+            let name_table = rva(self.dword_at(bst, Self::rva_add(at, 16)?)?);
+            dlls.push(ImportedDll {
+                name: self.string_at(bst, rva(name))?,
+                time_date_stamp: self.dword_at(bst, Self::rva_add(at, 28)?)?,
+                functions: self.thunks(bst, name_table, address_table)?,
+            });
+        }
+        Ok(dlls)
+    }
+
+    /// <p>The bound import directory.  Names in it are offsets from the start of the directory.</p>
+    pub fn bound_imports(&self, bst: &ByteStream) -> Result<Vec<BoundImport>, Error> {
+        let Some(directory) = self.data_directory(IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT) else { return Ok(Vec::new()) };
+        let start = directory.virtual_address;
+        let mut bound = Vec::new();
+        let end = Self::rva_add(start, directory.size)?;
+        let mut at = start;
+        while at.checked_add(8).is_some_and(|next| next <= end) {
+            let time_date_stamp = self.dword_at(bst, at)?;
+            let name = self.word_at(bst, Self::rva_add(at, 4)?)?;
+            let forwarder_count = self.word_at(bst, Self::rva_add(at, 6)?)?;
+            if time_date_stamp == 0 && name == 0 {
+                break;
+            }
+            let mut forwarders = Vec::with_capacity(forwarder_count as usize);
+            for f in 1..=forwarder_count as u32 {
+                let entry = Self::entry_rva(at, f, 8)?;
+                let name_offset = self.word_at(bst, Self::rva_add(entry, 4)?)? as u32;
+                let name = self.string_at(bst, Self::rva_add(start, name_offset)?)?;
+                forwarders.push((name, self.dword_at(bst, entry)?));
+            }
+            let name = self.string_at(bst, Self::rva_add(start, name as u32)?)?;
+            bound.push(BoundImport { name, time_date_stamp, forwarders });
+            at = Self::entry_rva(at, forwarder_count as u32 + 1, 8)?;
+        }
+        Ok(bound)
+    }
+
+    /// <p>Reads a lookup table of thunks, each an ordinal when its top bit is set, otherwise the RVA of a hint and
+    /// name.</p>
+    fn thunks(&self, bst: &ByteStream, lookup_table: u32, address_table: u32) -> Result<Vec<ImportedFunction>, Error> {
+        let (size, ordinal_flag) = match self.is_64_bit() {
+            true => (8, 1 << 63),
+            false => (4, 1 << 31),
+        };
+        let mut functions = Vec::new();
+        for i in 0..MAX_DESCRIPTORS as u32 {
+            let thunk = self.address_at(bst, Self::entry_rva(lookup_table, i, size)?)?;
+            if thunk == 0 {
+                break;
+            }
+            let iat_rva = Self::entry_rva(address_table, i, size)?;
+            functions.push(match thunk & ordinal_flag {
+                0 => {
+                    let hint_name = thunk as u32;
+                    ImportedFunction {
+                        ordinal: None,
+                        name: Some(self.string_at(bst, Self::rva_add(hint_name, 2)?)?),
+                        hint: Some(self.word_at(bst, hint_name)?),
+                        iat_rva,
+                    }
+                }
+                _ => ImportedFunction { ordinal: Some(thunk as u16), name: None, hint: None, iat_rva },
+            });
+        }
+        Ok(functions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executable::pe::tests::{dwords, put, with_rdata, RDATA};
+
+    const IMAGE_BASE: u32 = 0x40_0000;
+
+    /// <p>Two DLLs' names, lookup tables and address tables, which the descriptors of each test point at.
+    /// KERNEL32.dll imports ExitProcess by name and ordinal 5; USER32.dll imports MessageBoxA by name.</p>
+    fn thunks(wide: bool) -> Vec<u8> {
+        let mut data = vec![0; 0x400];
+        put(&mut data, 0x2080, b"KERNEL32.dll\0");
+        put(&mut data, 0x2090, b"USER32.dll\0");
+        put(&mut data, 0x2200, b"\x07\x00ExitProcess\0");
+        put(&mut data, 0x2210, b"\x20\x00MessageBoxA\0");
+        let (kernel32, user32) = match wide {
+            true => (dwords(&[0x2200, 0, 5, 0x8000_0000, 0, 0]), dwords(&[0x2210, 0, 0, 0])),
+            false => (dwords(&[0x2200, 0x8000_0005, 0]), dwords(&[0x2210, 0])),
+        };
+        put(&mut data, 0x2100, &kernel32);
+        put(&mut data, 0x2140, &kernel32);
+        put(&mut data, 0x2160, &user32);
+        data
+    }
+
+    fn kernel32(size: u32) -> Vec<ImportedFunction> {
+        vec![
+            ImportedFunction { ordinal: None, name: Some("ExitProcess".to_string()), hint: Some(7), iat_rva: 0x2140 },
+            ImportedFunction { ordinal: Some(5), name: None, hint: None, iat_rva: 0x2140 + size },
+        ]
+    }
+    fn user32() -> Vec<ImportedFunction> {
+        let name = Some("MessageBoxA".to_string());
+        vec![ImportedFunction { ordinal: None, name, hint: Some(0x20), iat_rva: 0x2160 }]
+    }
+
+    #[test]
+    fn imports_by_name_and_ordinal() {
+        let mut data = thunks(false);
+        // the second descriptor has no lookup table, so the names come from the address table
+        put(&mut data, RDATA, &dwords(&[0x2100, 0, 0, 0x2080, 0x2140, 0, 0xFFFF_FFFF, 0, 0x2090, 0x2160]));
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_IMPORT, RDATA, 60)], &data);
+        assert_eq!(
+            pe.imports(&bst).unwrap(),
+            [
+                ImportedDll { name: "KERNEL32.dll".to_string(), time_date_stamp: 0, functions: kernel32(4) },
+                ImportedDll { name: "USER32.dll".to_string(), time_date_stamp: 0xFFFF_FFFF, functions: user32() },
+            ]
+        );
+    }
+
+    #[test]
+    fn pe32_plus_thunks_are_qwords() {
+        let mut data = thunks(true);
+        put(&mut data, RDATA, &dwords(&[0x2100, 0, 0, 0x2080, 0x2140]));
+        let (pe, bst) = with_rdata(true, &[(IMAGE_DIRECTORY_ENTRY_IMPORT, RDATA, 40)], &data);
+        let dlls = pe.imports(&bst).unwrap();
+        assert_eq!(dlls.len(), 1);
+        assert_eq!(dlls[0].functions, kernel32(8));
+    }
+
+    #[test]
+    fn delay_imports_hold_rvas_or_virtual_addresses() {
+        let mut data = thunks(false);
+        let va = |rva: u32| IMAGE_BASE + rva;
+        let mut descriptors = dwords(&[1, 0x2080, 0x2300, 0x2140, 0x2100, 0, 0, 0x1234]);
+        descriptors.extend(dwords(&[0, va(0x2090), va(0x2300), va(0x2160), va(0x2160), 0, 0, 0]));
+        put(&mut data, 0x2020, &descriptors);
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT, 0x2020, 96)], &data);
+        assert_eq!(
+            pe.delay_imports(&bst).unwrap(),
+            [
+                ImportedDll { name: "KERNEL32.dll".to_string(), time_date_stamp: 0x1234, functions: kernel32(4) },
+                ImportedDll { name: "USER32.dll".to_string(), time_date_stamp: 0, functions: user32() },
+            ]
+        );
+        assert!(pe.imports(&bst).unwrap().is_empty());
+    }
+
+    #[test]
+    fn bound_imports_list_their_forwarders() {
+        let mut data = vec![0; 0x400];
+        let at = 0x2380;
+        put(&mut data, at, &[0x11, 0x11, 0, 0, 0x20, 0, 1, 0]);
+        put(&mut data, at + 8, &[0x22, 0x22, 0, 0, 0x2D, 0, 0, 0]);
+        put(&mut data, at + 0x10, &[0x33, 0x33, 0, 0, 0x37, 0, 0, 0]);
+        put(&mut data, at + 0x20, b"KERNEL32.dll\0NTDLL.DLL\0USER32.dll\0");
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, at, 0x48)], &data);
+        assert_eq!(
+            pe.bound_imports(&bst).unwrap(),
+            [
+                BoundImport {
+                    name: "KERNEL32.dll".to_string(),
+                    time_date_stamp: 0x1111,
+                    forwarders: vec![("NTDLL.DLL".to_string(), 0x2222)],
+                },
+                BoundImport { name: "USER32.dll".to_string(), time_date_stamp: 0x3333, forwarders: Vec::new() },
+            ]
+        );
+    }
+}
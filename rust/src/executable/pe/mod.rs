@@ -0,0 +1,596 @@
+use std::any::Any;
+
+use flags::{Machine, Subsystem};
+use section::{Section, SectionView};
+
+use super::{ExecutableType, Signature};
+use crate::{byte_stream::ByteStream, error::Error};
+
+pub mod exports;
+pub mod flags;
+pub mod imports;
+pub mod relocations;
+pub mod resources;
+pub mod section;
+
+pub const IMAGE_DIRECTORY_ENTRY_EXPORT: usize = 0;
+pub const IMAGE_DIRECTORY_ENTRY_IMPORT: usize = 1;
+pub const IMAGE_DIRECTORY_ENTRY_RESOURCE: usize = 2;
+pub const IMAGE_DIRECTORY_ENTRY_EXCEPTION: usize = 3;
+pub const IMAGE_DIRECTORY_ENTRY_SECURITY: usize = 4;
+pub const IMAGE_DIRECTORY_ENTRY_BASERELOC: usize = 5;
+pub const IMAGE_DIRECTORY_ENTRY_DEBUG: usize = 6;
+pub const IMAGE_DIRECTORY_ENTRY_ARCHITECTURE: usize = 7;
+pub const IMAGE_DIRECTORY_ENTRY_GLOBALPTR: usize = 8;
+pub const IMAGE_DIRECTORY_ENTRY_TLS: usize = 9;
+pub const IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG: usize = 10;
+pub const IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT: usize = 11;
+pub const IMAGE_DIRECTORY_ENTRY_IAT: usize = 12;
+pub const IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT: usize = 13;
+pub const IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR: usize = 14;
+
+/// <p>The COFF file header that follows the <code>PE\0\0</code> signature.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileHeader {
+    pub machine: Machine,
+    pub number_of_sections: u16,
+    pub time_date_stamp: u32,
+    pub pointer_to_symbol_table: u32,
+    pub number_of_symbols: u32,
+    pub size_of_optional_header: u16,
+    /// <code>IMAGE_FILE_*</code> bits from <code>flags</code>.
+    pub characteristics: u16,
+}
+
+impl FileHeader {
+    fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        Ok(FileHeader {
+            machine: Machine::from_word(bst.read_word()?),
+            number_of_sections: bst.read_word()?,
+            time_date_stamp: bst.read_dword()?,
+            pointer_to_symbol_table: bst.read_dword()?,
+            number_of_symbols: bst.read_dword()?,
+            size_of_optional_header: bst.read_word()?,
+            characteristics: bst.read_word()?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionalHeaderKind {
+    /// 0x10B, 32-bit addresses.
+    Pe32,
+    /// 0x20B, 64-bit image base, stack and heap sizes.
+    Pe32Plus,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+/// <p>The optional header, which images always have.  PE32+ fields that are wider than in PE32 are read into the
+/// wider type either way.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionalHeader {
+    pub kind: OptionalHeaderKind,
+    /// (major, minor)
+    pub linker_version: (u8, u8),
+    pub size_of_code: u32,
+    pub size_of_initialized_data: u32,
+    pub size_of_uninitialized_data: u32,
+    pub address_of_entry_point: u32,
+    pub base_of_code: u32,
+    /// Only in PE32.
+    pub base_of_data: Option<u32>,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    /// (major, minor)
+    pub operating_system_version: (u16, u16),
+    /// (major, minor)
+    pub image_version: (u16, u16),
+    /// (major, minor)
+    pub subsystem_version: (u16, u16),
+    pub win32_version_value: u32,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub checksum: u32,
+    pub subsystem: Subsystem,
+    /// <code>IMAGE_DLLCHARACTERISTICS_*</code> bits from <code>flags</code>.
+    pub dll_characteristics: u16,
+    pub size_of_stack_reserve: u64,
+    pub size_of_stack_commit: u64,
+    pub size_of_heap_reserve: u64,
+    pub size_of_heap_commit: u64,
+    pub loader_flags: u32,
+    /// Indexed by the <code>IMAGE_DIRECTORY_ENTRY_*</code> constants.
+    pub data_directories: Vec<DataDirectory>,
+}
+
+impl OptionalHeader {
+    /// <p>Reads an optional header of <code>size</code> bytes.  Data directories past the end of it are left out
+    /// whatever the count in the header says.</p>
+    fn read(bst: &mut ByteStream, size: usize) -> Result<Self, Error> {
+        let start = bst.pos;
+        let kind = match bst.read_word()? {
+            0x10B => OptionalHeaderKind::Pe32,
+            0x20B => OptionalHeaderKind::Pe32Plus,
+            _ => return Err(Error::InvalidHeader { field: "optional header magic" }),
+        };
+        let wide = kind == OptionalHeaderKind::Pe32Plus;
+        let address = |bst: &mut ByteStream| -> Result<u64, Error> {
+            match wide {
+                true => bst.read_u64_le(),
+                false => Ok(bst.read_dword()? as u64),
+            }
+        };
+
+        let linker_version = (bst.read_byte()?, bst.read_byte()?);
+        let size_of_code = bst.read_dword()?;
+        let size_of_initialized_data = bst.read_dword()?;
+        let size_of_uninitialized_data = bst.read_dword()?;
+        let address_of_entry_point = bst.read_dword()?;
+        let base_of_code = bst.read_dword()?;
+        let base_of_data = if wide { None } else { Some(bst.read_dword()?) };
+        let image_base = address(bst)?;
+        let section_alignment = bst.read_dword()?;
+        let file_alignment = bst.read_dword()?;
+        let operating_system_version = (bst.read_word()?, bst.read_word()?);
+        let image_version = (bst.read_word()?, bst.read_word()?);
+        let subsystem_version = (bst.read_word()?, bst.read_word()?);
+        let win32_version_value = bst.read_dword()?;
+        let size_of_image = bst.read_dword()?;
+        let size_of_headers = bst.read_dword()?;
+        let checksum = bst.read_dword()?;
+        let subsystem = Subsystem::from_word(bst.read_word()?);
+        let dll_characteristics = bst.read_word()?;
+        let size_of_stack_reserve = address(bst)?;
+        let size_of_stack_commit = address(bst)?;
+        let size_of_heap_reserve = address(bst)?;
+        let size_of_heap_commit = address(bst)?;
+        let loader_flags = bst.read_dword()?;
+        let number_of_rva_and_sizes = bst.read_dword()? as usize;
+
+        let room = (start + size).saturating_sub(bst.pos) / 8;
+        let mut data_directories = Vec::with_capacity(number_of_rva_and_sizes.min(room));
+        for _ in 0..number_of_rva_and_sizes.min(room) {
+            data_directories.push(DataDirectory { virtual_address: bst.read_dword()?, size: bst.read_dword()? });
+        }
+
+        Ok(OptionalHeader {
+            kind,
+            linker_version,
+            size_of_code,
+            size_of_initialized_data,
+            size_of_uninitialized_data,
+            address_of_entry_point,
+            base_of_code,
+            base_of_data,
+            image_base,
+            section_alignment,
+            file_alignment,
+            operating_system_version,
+            image_version,
+            subsystem_version,
+            win32_version_value,
+            size_of_image,
+            size_of_headers,
+            checksum,
+            subsystem,
+            dll_characteristics,
+            size_of_stack_reserve,
+            size_of_stack_commit,
+            size_of_heap_reserve,
+            size_of_heap_commit,
+            loader_flags,
+            data_directories,
+        })
+    }
+}
+
+/// <p>The header Win32 and later executables add after the DOS stub.</p>
+pub struct PortableExecutable {
+    /// Where the <code>PE\0\0</code> signature is in the file, the <code>e_lfanew</code> of the DOS header.
+    pub offset: usize,
+    pub file_header: FileHeader,
+    pub optional_header: OptionalHeader,
+    pub sections: Vec<Section>,
+}
+
+impl PortableExecutable {
+    /// <p>A data directory that is present and not empty.</p>
+    pub fn data_directory(&self, index: usize) -> Option<DataDirectory> {
+        self.optional_header.data_directories.get(index).copied().filter(|d| d.virtual_address != 0 && d.size != 0)
+    }
+
+    pub fn is_64_bit(&self) -> bool {
+        self.optional_header.kind == OptionalHeaderKind::Pe32Plus
+    }
+
+    /// <p>Where <code>rva</code> is in the file.  The headers are mapped as they are in the file, and each section
+    /// from its virtual address for as long as it has file data.</p>
+    pub fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        if rva < self.optional_header.size_of_headers {
+            return Some(rva as usize);
+        }
+        self.sections
+            .iter()
+            .find(|s| s.contains_rva(rva))
+            .map(|s| s.pointer_to_raw_data as usize + (rva - s.virtual_address) as usize)
+    }
+
+    /// <p>The RVA file <code>offset</code> is loaded at, if it is loaded at all.</p>
+    pub fn offset_to_rva(&self, offset: usize) -> Option<u32> {
+        if offset < self.optional_header.size_of_headers as usize {
+            return Some(offset as u32);
+        }
+        self.sections.iter().find_map(|s| {
+            let delta = offset.checked_sub(s.pointer_to_raw_data as usize)?;
+            (delta < s.file_extent() as usize).then(|| s.virtual_address.checked_add(delta as u32)).flatten()
+        })
+    }
+
+    /// <p>A view of one section's file data.</p>
+    pub fn section_view(&self, bst: &ByteStream, section: &Section) -> Result<SectionView, Error> {
+        let start = section.pointer_to_raw_data as usize;
+        let end = (start + section.file_extent() as usize).min(bst.len());
+        Ok(SectionView { virtual_address: section.virtual_address, bst: bst.slice(start.min(end)..end)? })
+    }
+
+    /// <p>A view of every section, in table order.</p>
+    pub fn section_views(&self, bst: &ByteStream) -> Result<Vec<SectionView>, Error> {
+        self.sections.iter().map(|s| self.section_view(bst, s)).collect()
+    }
+
+    /// <p>A stream over <code>len</code> bytes of the file starting at <code>rva</code>.</p>
+    pub fn view_at_rva(&self, bst: &ByteStream, rva: u32, len: usize) -> Result<ByteStream, Error> {
+        let start = self.rva_to_offset(rva).ok_or(Error::UnmappedRva { rva })?;
+        bst.slice(start..start + len)
+    }
+
+    /// <p><code>rva + delta</code>, which is an error rather than a wraparound when the file's values run past the
+    /// end of the address space.</p>
+    pub(crate) fn rva_add(rva: u32, delta: u32) -> Result<u32, Error> {
+        rva.checked_add(delta).ok_or(Error::UnmappedRva { rva })
+    }
+    /// <p>The RVA of entry <code>index</code> in a table of <code>size</code>-byte entries at <code>rva</code>.</p>
+    pub(crate) fn entry_rva(rva: u32, index: u32, size: u32) -> Result<u32, Error> {
+        index.checked_mul(size).and_then(|delta| rva.checked_add(delta)).ok_or(Error::UnmappedRva { rva })
+    }
+
+    fn offset(&self, rva: u32) -> Result<usize, Error> {
+        self.rva_to_offset(rva).ok_or(Error::UnmappedRva { rva })
+    }
+    pub(crate) fn word_at(&self, bst: &ByteStream, rva: u32) -> Result<u16, Error> {
+        bst.read_word_at(self.offset(rva)?)
+    }
+    pub(crate) fn dword_at(&self, bst: &ByteStream, rva: u32) -> Result<u32, Error> {
+        bst.read_dword_at(self.offset(rva)?)
+    }
+    /// <p>A pointer-sized value: a dword in PE32, a qword in PE32+.</p>
+    pub(crate) fn address_at(&self, bst: &ByteStream, rva: u32) -> Result<u64, Error> {
+        match self.is_64_bit() {
+            true => Ok(self.dword_at(bst, rva)? as u64 | (self.dword_at(bst, Self::rva_add(rva, 4)?)? as u64) << 32),
+            false => Ok(self.dword_at(bst, rva)? as u64),
+        }
+    }
+    /// <p>The zero-terminated string at <code>rva</code>.</p>
+    pub(crate) fn string_at(&self, bst: &ByteStream, rva: u32) -> Result<String, Error> {
+        let start = self.offset(rva)?;
+        let end = bst.find_byte(start, 0).ok_or(Error::Truncated { offset: start, needed: 1 })?;
+        bst.read_string_from_to(start, end)
+    }
+}
+
+impl ExecutableType for PortableExecutable {
+    fn signature(&self) -> Signature {
+        Signature::PE
+    }
+    fn read(bst: &mut ByteStream) -> Result<Self, Error> where Self: Sized {
+        let offset = bst.pos;
+        let magic = bst.read_dword()?;
+        if &magic.to_le_bytes() != b"PE\0\0" {
+            return Err(Error::BadMagic { offset, found: magic as u16 });
+        }
+        let file_header = FileHeader::read(bst)?;
+        let optional_start = bst.pos;
+        let optional_header = OptionalHeader::read(bst, file_header.size_of_optional_header as usize)?;
+
+        bst.pos = optional_start + file_header.size_of_optional_header as usize;
+        let mut sections = Vec::with_capacity(file_header.number_of_sections as usize);
+        for _ in 0..file_header.number_of_sections {
+            sections.push(Section::read(bst)?);
+        }
+
+        Ok(PortableExecutable { offset, file_header, optional_header, sections })
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flags::*;
+
+    /// Where the fixtures put the <code>PE\0\0</code> signature, after a DOS header.
+    pub(super) const PE_OFFSET: usize = 0x40;
+    const HEADERS: usize = 0x200;
+
+    /// <p>An image with 16 data directories, of which <code>directories</code> are <code>(index, rva, size)</code>,
+    /// and a section for each <code>(name, rva, data)</code>, its data placed after the headers in table order.</p>
+    pub(super) fn image(wide: bool, directories: &[(usize, u32, u32)], sections: &[(&str, u32, &[u8])]) -> Vec<u8> {
+        let mut b = b"MZ".to_vec();
+        b.resize(PE_OFFSET, 0);
+        b.extend_from_slice(b"PE\0\0");
+        let (machine, characteristics, optional_size) = match wide {
+            true => (0x8664u16, IMAGE_FILE_EXECUTABLE_IMAGE | IMAGE_FILE_LARGE_ADDRESS_AWARE, 0xF0u16),
+            false => (0x014C, IMAGE_FILE_EXECUTABLE_IMAGE | IMAGE_FILE_32BIT_MACHINE, 0xE0),
+        };
+        for word in [machine, sections.len() as u16] {
+            b.extend_from_slice(&word.to_le_bytes());
+        }
+        for dword in [0x5F5E_1000u32, 0x1234, 5] {
+            b.extend_from_slice(&dword.to_le_bytes());
+        }
+        for word in [optional_size, characteristics] {
+            b.extend_from_slice(&word.to_le_bytes());
+        }
+
+        let address = |b: &mut Vec<u8>, value: u64| match wide {
+            true => b.extend_from_slice(&value.to_le_bytes()),
+            false => b.extend_from_slice(&(value as u32).to_le_bytes()),
+        };
+        b.extend_from_slice(&(if wide { 0x20Bu16 } else { 0x10B }).to_le_bytes());
+        b.extend_from_slice(&[14, 2]);
+        for dword in [0x200u32, 0x400, 0x80, 0x1010, 0x1000] {
+            b.extend_from_slice(&dword.to_le_bytes());
+        }
+        if !wide {
+            b.extend_from_slice(&0x2000u32.to_le_bytes());
+        }
+        address(&mut b, if wide { 0x1_4000_0000 } else { 0x40_0000 });
+        for dword in [0x1000u32, 0x200] {
+            b.extend_from_slice(&dword.to_le_bytes());
+        }
+        for word in [6u16, 0, 1, 2, 6, 1] {
+            b.extend_from_slice(&word.to_le_bytes());
+        }
+        let size_of_image = 0x1000 * (sections.len() as u32 + 1);
+        for dword in [0u32, size_of_image, HEADERS as u32, 0xABCD] {
+            b.extend_from_slice(&dword.to_le_bytes());
+        }
+        for word in [3u16, IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE | IMAGE_DLLCHARACTERISTICS_NX_COMPAT] {
+            b.extend_from_slice(&word.to_le_bytes());
+        }
+        for value in [0x10_0000, 0x1000, 0x20_0000, 0x2000] {
+            address(&mut b, value);
+        }
+        for dword in [0u32, 16] {
+            b.extend_from_slice(&dword.to_le_bytes());
+        }
+        for index in 0..16 {
+            let (rva, size) = directories.iter().find(|d| d.0 == index).map_or((0, 0), |d| (d.1, d.2));
+            b.extend_from_slice(&rva.to_le_bytes());
+            b.extend_from_slice(&size.to_le_bytes());
+        }
+
+        let mut raw = HEADERS;
+        for (name, rva, data) in sections {
+            let mut padded_name = name.as_bytes().to_vec();
+            padded_name.resize(8, 0);
+            b.extend_from_slice(&padded_name);
+            let raw_size = data.len().next_multiple_of(0x200);
+            for dword in [data.len() as u32, *rva, raw_size as u32, raw as u32, 0, 0] {
+                b.extend_from_slice(&dword.to_le_bytes());
+            }
+            b.extend_from_slice(&[0; 4]);
+            b.extend_from_slice(&(IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ).to_le_bytes());
+            raw += raw_size;
+        }
+        b.resize(HEADERS, 0);
+        for (_, _, data) in sections {
+            b.extend_from_slice(data);
+            b.resize(b.len().next_multiple_of(0x200), 0);
+        }
+        b
+    }
+
+    pub(super) fn read(bytes: Vec<u8>) -> Result<PortableExecutable, Error> {
+        let mut bst = ByteStream::new(bytes);
+        bst.pos = PE_OFFSET;
+        PortableExecutable::read(&mut bst)
+    }
+
+    /// Where the fixtures of the directory tests put their one data section.
+    pub(super) const RDATA: u32 = 0x2000;
+
+    /// <p>Copies <code>bytes</code> into the data of the section at <code>RDATA</code> so they load at
+    /// <code>rva</code>.</p>
+    pub(super) fn put(data: &mut [u8], rva: u32, bytes: &[u8]) {
+        let at = (rva - RDATA) as usize;
+        data[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+    pub(super) fn dwords(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// <p>An image whose only section holds <code>data</code> at <code>RDATA</code>, with the stream it was read
+    /// from.</p>
+    pub(super) fn with_rdata(
+        wide: bool,
+        directories: &[(usize, u32, u32)],
+        data: &[u8],
+    ) -> (PortableExecutable, ByteStream) {
+        let bytes = image(wide, directories, &[(".rdata", RDATA, data)]);
+        (read(bytes.clone()).unwrap(), ByteStream::new(bytes))
+    }
+
+    fn sections() -> Vec<(&'static str, u32, &'static [u8])> {
+        vec![(".text", 0x1000, &[0xC3; 0x10]), (".rdata", 0x2000, &[0; 0x300])]
+    }
+
+    #[test]
+    fn reads_the_coff_header() {
+        let pe = read(image(false, &[], &sections())).unwrap();
+        assert_eq!(pe.offset, PE_OFFSET);
+        assert_eq!(
+            pe.file_header,
+            FileHeader {
+                machine: Machine::I386,
+                number_of_sections: 2,
+                time_date_stamp: 0x5F5E_1000,
+                pointer_to_symbol_table: 0x1234,
+                number_of_symbols: 5,
+                size_of_optional_header: 0xE0,
+                characteristics: IMAGE_FILE_EXECUTABLE_IMAGE | IMAGE_FILE_32BIT_MACHINE,
+            }
+        );
+        assert_eq!(read(image(true, &[], &sections())).unwrap().file_header.machine, Machine::Amd64);
+    }
+
+    #[test]
+    fn reads_the_pe32_optional_header() {
+        let header = read(image(false, &[], &sections())).unwrap().optional_header;
+        assert_eq!(header.kind, OptionalHeaderKind::Pe32);
+        assert_eq!(header.linker_version, (14, 2));
+        assert_eq!(
+            (header.size_of_code, header.size_of_initialized_data, header.size_of_uninitialized_data),
+            (0x200, 0x400, 0x80)
+        );
+        assert_eq!((header.address_of_entry_point, header.base_of_code), (0x1010, 0x1000));
+        assert_eq!(header.base_of_data, Some(0x2000));
+        assert_eq!(header.image_base, 0x40_0000);
+        assert_eq!((header.section_alignment, header.file_alignment), (0x1000, 0x200));
+        assert_eq!(header.operating_system_version, (6, 0));
+        assert_eq!(header.image_version, (1, 2));
+        assert_eq!(header.subsystem_version, (6, 1));
+        assert_eq!(header.win32_version_value, 0);
+        assert_eq!((header.size_of_image, header.size_of_headers, header.checksum), (0x3000, 0x200, 0xABCD));
+        assert_eq!(header.subsystem, Subsystem::WindowsCui);
+        assert_eq!(
+            header.dll_characteristics,
+            IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE | IMAGE_DLLCHARACTERISTICS_NX_COMPAT
+        );
+        assert_eq!((header.size_of_stack_reserve, header.size_of_stack_commit), (0x10_0000, 0x1000));
+        assert_eq!((header.size_of_heap_reserve, header.size_of_heap_commit), (0x20_0000, 0x2000));
+        assert_eq!(header.loader_flags, 0);
+        assert_eq!(header.data_directories.len(), 16);
+    }
+
+    #[test]
+    fn pe32_plus_has_wide_fields_and_no_base_of_data() {
+        let pe = read(image(true, &[], &sections())).unwrap();
+        let header = &pe.optional_header;
+        assert!(pe.is_64_bit());
+        assert_eq!(pe.file_header.size_of_optional_header, 0xF0);
+        assert_eq!(header.kind, OptionalHeaderKind::Pe32Plus);
+        assert_eq!(header.base_of_code, 0x1000);
+        assert_eq!(header.base_of_data, None);
+        assert_eq!(header.image_base, 0x1_4000_0000);
+        assert_eq!((header.section_alignment, header.subsystem), (0x1000, Subsystem::WindowsCui));
+        assert_eq!((header.size_of_stack_reserve, header.size_of_heap_commit), (0x10_0000, 0x2000));
+        assert_eq!(header.data_directories.len(), 16);
+    }
+
+    #[test]
+    fn an_unknown_optional_header_magic_is_invalid() {
+        let mut bytes = image(false, &[], &sections());
+        bytes[PE_OFFSET + 24] = 0x07;
+        assert!(matches!(read(bytes), Err(Error::InvalidHeader { field: "optional header magic" })));
+        assert!(matches!(read(b"MZ".to_vec()), Err(Error::Truncated { .. })));
+    }
+
+    #[test]
+    fn reads_present_data_directories() {
+        let directories = [(IMAGE_DIRECTORY_ENTRY_IMPORT, 0x2000, 0x28), (IMAGE_DIRECTORY_ENTRY_DEBUG, 0x2100, 0)];
+        for wide in [false, true] {
+            let pe = read(image(wide, &directories, &sections())).unwrap();
+            let import = DataDirectory { virtual_address: 0x2000, size: 0x28 };
+            assert_eq!(pe.optional_header.data_directories[IMAGE_DIRECTORY_ENTRY_IMPORT], import);
+            assert_eq!(pe.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT), Some(import));
+            // empty and absent directories read as if they weren't there
+            assert_eq!(pe.data_directory(IMAGE_DIRECTORY_ENTRY_DEBUG), None);
+            assert_eq!(pe.data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT), None);
+            assert_eq!(pe.data_directory(16), None);
+        }
+    }
+
+    #[test]
+    fn data_directories_stop_at_the_end_of_the_optional_header() {
+        let mut bytes = image(false, &[(IMAGE_DIRECTORY_ENTRY_IMPORT, 0x2000, 0x28)], &[]);
+        // room for two directories, though the header still counts 16
+        bytes[PE_OFFSET + 20..PE_OFFSET + 22].copy_from_slice(&(0xE0u16 - 14 * 8).to_le_bytes());
+        let pe = read(bytes).unwrap();
+        assert_eq!(pe.optional_header.data_directories.len(), 2);
+        assert!(pe.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT).is_some());
+        assert!(pe.sections.is_empty());
+    }
+
+    #[test]
+    fn reads_the_section_table() {
+        let pe = read(image(true, &[], &sections())).unwrap();
+        assert_eq!(
+            pe.sections,
+            [
+                Section {
+                    name: ".text".to_string(),
+                    virtual_size: 0x10,
+                    virtual_address: 0x1000,
+                    size_of_raw_data: 0x200,
+                    pointer_to_raw_data: 0x200,
+                    pointer_to_relocations: 0,
+                    pointer_to_linenumbers: 0,
+                    number_of_relocations: 0,
+                    number_of_linenumbers: 0,
+                    characteristics: IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ,
+                },
+                Section {
+                    name: ".rdata".to_string(),
+                    virtual_size: 0x300,
+                    virtual_address: 0x2000,
+                    size_of_raw_data: 0x400,
+                    pointer_to_raw_data: 0x400,
+                    pointer_to_relocations: 0,
+                    pointer_to_linenumbers: 0,
+                    number_of_relocations: 0,
+                    number_of_linenumbers: 0,
+                    characteristics: IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rva_arithmetic_is_checked() {
+        assert_eq!(PortableExecutable::rva_add(0x1000, 4).unwrap(), 0x1004);
+        assert!(matches!(PortableExecutable::rva_add(0xFFFF_FFFE, 4), Err(Error::UnmappedRva { rva: 0xFFFF_FFFE })));
+        assert_eq!(PortableExecutable::entry_rva(0x2000, 3, 20).unwrap(), 0x2000 + 60);
+        assert!(PortableExecutable::entry_rva(0x2000, 0x1000_0000, 32).is_err());
+        assert!(PortableExecutable::entry_rva(0xFFFF_FF00, 0x10, 0x10).is_err());
+    }
+
+    #[test]
+    fn rvas_map_through_the_headers_and_sections() {
+        let bytes = image(false, &[], &sections());
+        let pe = read(bytes.clone()).unwrap();
+        assert_eq!(pe.rva_to_offset(0x40), Some(0x40));
+        assert_eq!(pe.rva_to_offset(0x1008), Some(0x208));
+        assert_eq!(pe.rva_to_offset(0x22FF), Some(0x6FF));
+        // past the section's virtual size, where it is zero-filled rather than read from the file
+        assert_eq!(pe.rva_to_offset(0x1010), None);
+        assert_eq!(pe.rva_to_offset(0x2300), None);
+        assert_eq!(pe.offset_to_rva(0x1FF), Some(0x1FF));
+        assert_eq!(pe.offset_to_rva(0x408), Some(0x2008));
+        assert_eq!(pe.offset_to_rva(0x210), None);
+
+        let bst = ByteStream::new(bytes);
+        assert_eq!(pe.view_at_rva(&bst, 0x1000, 2).unwrap().read_bytes_at(2, 0).unwrap(), [0xC3, 0xC3]);
+        assert!(matches!(pe.view_at_rva(&bst, 0x1800, 2), Err(Error::UnmappedRva { rva: 0x1800 })));
+        let views = pe.section_views(&bst).unwrap();
+        assert_eq!((views[1].virtual_address, views[1].bst.len()), (0x2000, 0x300));
+    }
+}
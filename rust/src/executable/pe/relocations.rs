@@ -0,0 +1,109 @@
+use super::{PortableExecutable, IMAGE_DIRECTORY_ENTRY_BASERELOC};
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>How a base relocation patches its address when the image is loaded somewhere other than its preferred
+/// base.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaseRelocationKind {
+    /// The high 16 bits of the difference.
+    High,
+    /// The low 16 bits of the difference.
+    Low,
+    /// All 32 bits.
+    HighLow,
+    /// The high 16 bits, rounded using the low half kept in the entry after it.
+    HighAdj(u16),
+    /// All 64 bits.
+    Dir64,
+    Other(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BaseRelocation {
+    pub rva: u32,
+    pub kind: BaseRelocationKind,
+}
+
+impl PortableExecutable {
+    /// <p>Every base relocation, in the order of the blocks.  The padding entries that keep blocks aligned are left
+    /// out.</p>
+    pub fn base_relocations(&self, bst: &ByteStream) -> Result<Vec<BaseRelocation>, Error> {
+        let Some(directory) = self.data_directory(IMAGE_DIRECTORY_ENTRY_BASERELOC) else { return Ok(Vec::new()) };
+        let end = Self::rva_add(directory.virtual_address, directory.size)?;
+        let mut relocations = Vec::new();
+        let mut block = directory.virtual_address;
+        while block.checked_add(8).is_some_and(|next| next <= end) {
+            let page = self.dword_at(bst, block)?;
+            let size = self.dword_at(bst, Self::rva_add(block, 4)?)?;
+            if size < 8 {
+                return Err(Error::InvalidHeader { field: "base relocation block size" });
+            }
+            let mut at = block + 8;
+            let block_end = block.saturating_add(size).min(end);
+            while at.checked_add(2).is_some_and(|next| next <= block_end) {
+                let entry = self.word_at(bst, at)?;
+                at += 2;
+                let rva = Self::rva_add(page, (entry & 0x0FFF) as u32)?;
+                let kind = match entry >> 12 {
+                    0 => continue,
+                    1 => BaseRelocationKind::High,
+                    2 => BaseRelocationKind::Low,
+                    3 => BaseRelocationKind::HighLow,
+                    4 => {
+                        let low = self.word_at(bst, at)?;
+                        at = Self::rva_add(at, 2)?;
+                        BaseRelocationKind::HighAdj(low)
+                    }
+                    10 => BaseRelocationKind::Dir64,
+                    other => BaseRelocationKind::Other(other as u8),
+                };
+                relocations.push(BaseRelocation { rva, kind });
+            }
+            block = Self::rva_add(block, size)?;
+        }
+        Ok(relocations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executable::pe::tests::{dwords, put, with_rdata, RDATA};
+
+    fn words(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn reads_each_block_without_the_padding() {
+        let mut data = vec![0; 0x100];
+        put(&mut data, RDATA, &dwords(&[0x1000, 16]));
+        put(&mut data, RDATA + 8, &words(&[0x3004, 0x4010, 0x8000, 0x0000]));
+        put(&mut data, RDATA + 16, &dwords(&[0x3000, 16]));
+        put(&mut data, RDATA + 24, &words(&[0xA008, 0x1002, 0x2FFE, 0x5006]));
+        let (pe, bst) = with_rdata(true, &[(IMAGE_DIRECTORY_ENTRY_BASERELOC, RDATA, 32)], &data);
+        let relocation = |rva, kind| BaseRelocation { rva, kind };
+        assert_eq!(
+            pe.base_relocations(&bst).unwrap(),
+            [
+                relocation(0x1004, BaseRelocationKind::HighLow),
+                relocation(0x1010, BaseRelocationKind::HighAdj(0x8000)),
+                relocation(0x3008, BaseRelocationKind::Dir64),
+                relocation(0x3002, BaseRelocationKind::High),
+                relocation(0x3FFE, BaseRelocationKind::Low),
+                relocation(0x3006, BaseRelocationKind::Other(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_block_shorter_than_its_header_is_invalid() {
+        let mut data = vec![0; 0x100];
+        put(&mut data, RDATA, &dwords(&[0x1000, 4]));
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_BASERELOC, RDATA, 16)], &data);
+        assert!(matches!(
+            pe.base_relocations(&bst),
+            Err(Error::InvalidHeader { field: "base relocation block size" })
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use super::{PortableExecutable, IMAGE_DIRECTORY_ENTRY_RESOURCE};
+use crate::{
+    byte_stream::ByteStream,
+    error::Error,
+    executable::{
+        ne::restable::{ResourceId, ResourceType},
+        resource::version::VersionInfo,
+    },
+};
+
+/// <p>A leaf of the resource tree, with the type, name and language directories it was found under.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resource {
+    pub type_id: ResourceId,
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub rva: u32,
+    pub size: u32,
+    pub code_page: u32,
+}
+
+impl Resource {
+    /// <p>The predefined type this is, if it is one.</p>
+    pub fn resource_type(&self) -> Option<ResourceType> {
+        match self.type_id {
+            ResourceId::Integer(id) => ResourceType::from_word(id | 0x8000),
+            ResourceId::Name(_) => None,
+        }
+    }
+
+    pub fn data(&self, pe: &PortableExecutable, bst: &ByteStream) -> Result<Vec<u8>, Error> {
+        let start = pe.rva_to_offset(self.rva).ok_or(Error::UnmappedRva { rva: self.rva })?;
+        bst.read_bytes_at(self.size as usize, start)
+    }
+}
+
+/// <p>The tree is type, name, language; anything deeper isn't a resource directory.</p>
+const LEVELS: usize = 3;
+
+impl PortableExecutable {
+    /// <p>Every resource in the resource directory tree, flattened in tree order.</p>
+    pub fn resources(&self, bst: &ByteStream) -> Result<Vec<Resource>, Error> {
+        let Some(directory) = self.data_directory(IMAGE_DIRECTORY_ENTRY_RESOURCE) else { return Ok(Vec::new()) };
+        let mut resources = Vec::new();
+        self.resource_directory(bst, directory.virtual_address, 0, &mut Vec::new(), &mut resources)?;
+        Ok(resources)
+    }
+
+    /// <p>The resources of a predefined type.</p>
+    pub fn resources_of_type(&self, bst: &ByteStream, resource_type: ResourceType) -> Result<Vec<Resource>, Error> {
+        Ok(self.resources(bst)?.into_iter().filter(|r| r.resource_type() == Some(resource_type)).collect())
+    }
+
+    /// <p>The first version resource, decoded.</p>
+    pub fn version_info(&self, bst: &ByteStream) -> Result<Option<VersionInfo>, Error> {
+        match self.resources_of_type(bst, ResourceType::Version)?.first() {
+            Some(resource) => Ok(Some(VersionInfo::read_pe(&resource.data(self, bst)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn resource_directory(
+        &self,
+        bst: &ByteStream,
+        root: u32,
+        offset: u32,
+        path: &mut Vec<ResourceId>,
+        resources: &mut Vec<Resource>,
+    ) -> Result<(), Error> {
+        let at = Self::rva_add(root, offset)?;
+        let named = self.word_at(bst, Self::rva_add(at, 12)?)? as u32;
+        let count = named + self.word_at(bst, Self::rva_add(at, 14)?)? as u32;
+        for i in 0..count {
+            let entry = Self::entry_rva(Self::rva_add(at, 16)?, i, 8)?;
+            let name = self.dword_at(bst, entry)?;
+            let target = self.dword_at(bst, Self::rva_add(entry, 4)?)?;
+            let id = match name & 0x8000_0000 {
+                0 => ResourceId::Integer(name as u16),
+                _ => {
+                    let string = Self::rva_add(root, name & 0x7FFF_FFFF)?;
+                    let len = self.word_at(bst, string)? as u32;
+                    let units = (0..len)
+                        .map(|c| self.word_at(bst, Self::entry_rva(Self::rva_add(string, 2)?, c, 2)?))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    ResourceId::Name(String::from_utf16_lossy(&units))
+                }
+            };
+            path.push(id);
+            match (target & 0x8000_0000 != 0, path.len()) {
+                (true, depth) if depth < LEVELS => {
+                    self.resource_directory(bst, root, target & 0x7FFF_FFFF, path, resources)?
+                }
+                (false, LEVELS) => {
+                    let data = Self::rva_add(root, target)?;
+                    resources.push(Resource {
+                        type_id: path[0].clone(),
+                        name: path[1].clone(),
+                        language: path[2].clone(),
+                        rva: self.dword_at(bst, data)?,
+                        size: self.dword_at(bst, Self::rva_add(data, 4)?)?,
+                        code_page: self.dword_at(bst, Self::rva_add(data, 8)?)?,
+                    });
+                }
+                _ => return Err(Error::InvalidResource { reason: "resource tree isn't type, name, language" }),
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executable::pe::tests::{dwords, put, with_rdata, RDATA};
+
+    /// <p>A named type DATA with resource 1 in English, and a version resource in German and English.</p>
+    fn tree() -> Vec<u8> {
+        let mut data = vec![0; 0x400];
+        let directory = |named: u32, ids: u32| dwords(&[0, 0, 0, named | ids << 16]);
+        put(&mut data, RDATA, &directory(1, 1));
+        put(&mut data, RDATA + 0x10, &dwords(&[0x8000_0100, 0x8000_0020, 16, 0x8000_0048]));
+        put(&mut data, RDATA + 0x20, &directory(0, 1));
+        put(&mut data, RDATA + 0x30, &dwords(&[1, 0x8000_0070]));
+        put(&mut data, RDATA + 0x48, &directory(0, 1));
+        put(&mut data, RDATA + 0x58, &dwords(&[1, 0x8000_0088]));
+        put(&mut data, RDATA + 0x70, &directory(0, 1));
+        put(&mut data, RDATA + 0x80, &dwords(&[0x409, 0xC0]));
+        put(&mut data, RDATA + 0x88, &directory(0, 2));
+        put(&mut data, RDATA + 0x98, &dwords(&[0x407, 0xD0, 0x409, 0xE0]));
+        put(&mut data, RDATA + 0xC0, &dwords(&[0x2200, 4, 1252, 0]));
+        put(&mut data, RDATA + 0xD0, &dwords(&[0x2210, 8, 0, 0]));
+        put(&mut data, RDATA + 0xE0, &dwords(&[0x2220, 2, 1200, 0]));
+        put(&mut data, RDATA + 0x100, b"\x04\x00D\x00A\x00T\x00A\x00");
+        put(&mut data, 0x2200, &[1, 2, 3, 4]);
+        data
+    }
+
+    #[test]
+    fn flattens_the_type_name_and_language_tree() {
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_RESOURCE, RDATA, 0x110)], &tree());
+        let resource = |type_id, language, rva, size, code_page| Resource {
+            type_id,
+            name: ResourceId::Integer(1),
+            language: ResourceId::Integer(language),
+            rva,
+            size,
+            code_page,
+        };
+        let data = ResourceId::Name("DATA".to_string());
+        let resources = pe.resources(&bst).unwrap();
+        assert_eq!(
+            resources,
+            [
+                resource(data, 0x409, 0x2200, 4, 1252),
+                resource(ResourceId::Integer(16), 0x407, 0x2210, 8, 0),
+                resource(ResourceId::Integer(16), 0x409, 0x2220, 2, 1200),
+            ]
+        );
+        assert_eq!(resources[0].resource_type(), None);
+        assert_eq!(resources[1].resource_type(), Some(ResourceType::Version));
+        assert_eq!(resources[0].data(&pe, &bst).unwrap(), [1, 2, 3, 4]);
+        assert_eq!(pe.resources_of_type(&bst, ResourceType::Version).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn leaves_must_be_three_levels_down() {
+        let mut data = tree();
+        // the language entry of DATA points at another directory
+        put(&mut data, RDATA + 0x84, &dwords(&[0x8000_0070]));
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_RESOURCE, RDATA, 0x110)], &data);
+        assert!(matches!(pe.resources(&bst), Err(Error::InvalidResource { .. })));
+
+        let mut data = tree();
+        // the version type leads straight to data
+        put(&mut data, RDATA + 0x1C, &dwords(&[0xC0]));
+        let (pe, bst) = with_rdata(false, &[(IMAGE_DIRECTORY_ENTRY_RESOURCE, RDATA, 0x110)], &data);
+        assert!(matches!(pe.resources(&bst), Err(Error::InvalidResource { .. })));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>An entry of the section table.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// Up to 8 bytes, without the zero padding.
+    pub name: String,
+    pub virtual_size: u32,
+    pub virtual_address: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    pub pointer_to_relocations: u32,
+    pub pointer_to_linenumbers: u32,
+    pub number_of_relocations: u16,
+    pub number_of_linenumbers: u16,
+    pub characteristics: u32,
+}
+
+impl Section {
+    pub fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        let raw_name = bst.read_bytes(8)?;
+        let name_len = raw_name.iter().position(|b| *b == 0).unwrap_or(raw_name.len());
+        Ok(Section {
+            name: String::from_utf8_lossy(&raw_name[..name_len]).to_string(),
+            virtual_size: bst.read_dword()?,
+            virtual_address: bst.read_dword()?,
+            size_of_raw_data: bst.read_dword()?,
+            pointer_to_raw_data: bst.read_dword()?,
+            pointer_to_relocations: bst.read_dword()?,
+            pointer_to_linenumbers: bst.read_dword()?,
+            number_of_relocations: bst.read_word()?,
+            number_of_linenumbers: bst.read_word()?,
+            characteristics: bst.read_dword()?,
+        })
+    }
+
+    /// <p>How much of the section's address range the file has data for.  The rest of it is zero-filled when
+    /// loaded.</p>
+    pub fn file_extent(&self) -> u32 {
+        match self.virtual_size {
+            0 => self.size_of_raw_data,
+            size => size.min(self.size_of_raw_data),
+        }
+    }
+
+    pub fn contains_rva(&self, rva: u32) -> bool {
+        rva.checked_sub(self.virtual_address).is_some_and(|delta| delta < self.file_extent())
+    }
+}
+
+/// <p>A section's file data next to the addresses it is loaded at.  The stream's positions count from the start of
+/// the section and errors from it give offsets in the file.</p>
+pub struct SectionView {
+    pub virtual_address: u32,
+    pub bst: ByteStream,
+}
+
+impl SectionView {
+    pub fn contains_rva(&self, rva: u32) -> bool {
+        rva.checked_sub(self.virtual_address).is_some_and(|delta| (delta as usize) < self.bst.len())
+    }
+
+    /// <p>Moves the stream to <code>rva</code>.</p>
+    pub fn seek_rva(&mut self, rva: u32) -> Result<(), Error> {
+        match self.contains_rva(rva) {
+            true => {
+                self.bst.pos = (rva - self.virtual_address) as usize;
+                Ok(())
+            }
+            false => Err(Error::UnmappedRva { rva }),
+        }
+    }
+
+    /// <p>The RVA the stream is at.</p>
+    pub fn rva(&self) -> u32 {
+        self.virtual_address + self.bst.pos as u32
+    }
+}
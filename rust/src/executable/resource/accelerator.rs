@@ -0,0 +1,127 @@
+use std::fmt;
+
+use crate::{byte_stream::ByteStream, error::Error};
+
+pub const FVIRTKEY: u8 = 0x01;
+pub const FNOINVERT: u8 = 0x02;
+pub const FSHIFT: u8 = 0x04;
+pub const FCONTROL: u8 = 0x08;
+pub const FALT: u8 = 0x10;
+/// Marks the last entry of the table.
+pub const FEND: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Accelerator {
+    pub flags: u8,
+    /// A virtual key code with <code>FVIRTKEY</code>, otherwise a character.
+    pub key: u16,
+    pub id: u16,
+}
+
+/// <p>An RT_ACCELERATOR resource.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceleratorTable {
+    pub entries: Vec<Accelerator>,
+}
+
+impl AcceleratorTable {
+    /// <p>Reads the 16-bit layout, five bytes an entry with no padding, until the entry with <code>FEND</code>.</p>
+    pub fn read(data: &[u8]) -> Result<Self, Error> {
+        let mut bst = ByteStream::new(data.to_vec());
+        let mut entries = Vec::new();
+        loop {
+            let entry = Accelerator { flags: bst.read_byte()?, key: bst.read_word()?, id: bst.read_word()? };
+            entries.push(entry);
+            if entry.flags & FEND != 0 {
+                return Ok(AcceleratorTable { entries });
+            }
+        }
+    }
+}
+
+/// <p>The names the Windows headers give virtual keys that aren't a letter or digit.</p>
+fn virtual_key_name(key: u16) -> Option<String> {
+    Some(match key {
+        0x08 => "VK_BACK".into(),
+        0x09 => "VK_TAB".into(),
+        0x0D => "VK_RETURN".into(),
+        0x1B => "VK_ESCAPE".into(),
+        0x20 => "VK_SPACE".into(),
+        0x21 => "VK_PRIOR".into(),
+        0x22 => "VK_NEXT".into(),
+        0x23 => "VK_END".into(),
+        0x24 => "VK_HOME".into(),
+        0x25 => "VK_LEFT".into(),
+        0x26 => "VK_UP".into(),
+        0x27 => "VK_RIGHT".into(),
+        0x28 => "VK_DOWN".into(),
+        0x2D => "VK_INSERT".into(),
+        0x2E => "VK_DELETE".into(),
+        0x70..=0x87 => format!("VK_F{}", key - 0x6F),
+        _ => return None,
+    })
+}
+
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let virtual_key = self.flags & FVIRTKEY != 0;
+        let key = match (virtual_key, self.key) {
+            (true, k @ (0x30..=0x39 | 0x41..=0x5A)) => format!("\"{}\"", k as u8 as char),
+            (true, k) => virtual_key_name(k).unwrap_or_else(|| k.to_string()),
+            (false, k @ 1..=26) => format!("\"^{}\"", (b'@' + k as u8) as char),
+            (false, k @ 0x21..=0x7E) if k != b'"' as u16 => format!("\"{}\"", k as u8 as char),
+            (false, k) => k.to_string(),
+        };
+        write!(f, "{key}, {}", self.id)?;
+        // a bare number is taken as a character code unless it is marked otherwise
+        if virtual_key {
+            write!(f, ", VIRTKEY")?;
+        } else if key.parse::<u16>().is_ok() {
+            write!(f, ", ASCII")?;
+        }
+        for (flag, name) in [(FNOINVERT, "NOINVERT"), (FSHIFT, "SHIFT"), (FCONTROL, "CONTROL"), (FALT, "ALT")] {
+            if self.flags & flag != 0 {
+                write!(f, ", {name}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// <p>An <code>ACCELERATORS</code> statement without the name in front.</p>
+impl fmt::Display for AcceleratorTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ACCELERATORS")?;
+        writeln!(f, "BEGIN")?;
+        for entry in &self.entries {
+            writeln!(f, "    {entry}")?;
+        }
+        writeln!(f, "END")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_entries_up_to_fend() {
+        let data = [
+            FVIRTKEY | FCONTROL, 0x4F, 0x00, 0x65, 0x00, // ctrl+O
+            0, 0x13, 0x00, 0x66, 0x00, //                   ^S
+            FVIRTKEY | FEND, 0x70, 0x00, 0x67, 0x00, //     F1
+            0xFF, 0xFF, //                                  past the end
+        ];
+        let table = AcceleratorTable::read(&data).unwrap();
+        assert_eq!(table.entries.len(), 3);
+        assert_eq!(
+            table.to_string(),
+            "ACCELERATORS\nBEGIN\n    \"O\", 101, VIRTKEY, CONTROL\n    \"^S\", 102\n    VK_F1, 103, VIRTKEY\nEND\n"
+        );
+    }
+
+    #[test]
+    fn a_table_without_fend_is_an_error() {
+        assert!(AcceleratorTable::read(&[FVIRTKEY, 0x4F, 0x00, 0x65, 0x00]).is_err());
+    }
+}
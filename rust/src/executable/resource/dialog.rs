@@ -0,0 +1,277 @@
+use std::fmt;
+
+use super::{read_ansi_string, rc_string};
+use crate::{byte_stream::ByteStream, error::Error, executable::ne::restable::ResourceId};
+
+pub const DS_SETFONT: u32 = 0x0040;
+
+/// <p>The window class of a control: one of the predefined ones stored as a single byte, or a registered name.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlClass {
+    Button,
+    Edit,
+    Static,
+    ListBox,
+    ScrollBar,
+    ComboBox,
+    /// A predefined class byte without a known name.
+    Other(u8),
+    Name(String),
+}
+
+impl ControlClass {
+    fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        let byte = bst.peek_byte()?;
+        if byte & 0x80 == 0 {
+            return Ok(ControlClass::Name(read_ansi_string(bst)?));
+        }
+        bst.read_byte()?;
+        Ok(match byte {
+            0x80 => ControlClass::Button,
+            0x81 => ControlClass::Edit,
+            0x82 => ControlClass::Static,
+            0x83 => ControlClass::ListBox,
+            0x84 => ControlClass::ScrollBar,
+            0x85 => ControlClass::ComboBox,
+            other => ControlClass::Other(other),
+        })
+    }
+}
+
+impl fmt::Display for ControlClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlClass::Button => write!(f, "\"BUTTON\""),
+            ControlClass::Edit => write!(f, "\"EDIT\""),
+            ControlClass::Static => write!(f, "\"STATIC\""),
+            ControlClass::ListBox => write!(f, "\"LISTBOX\""),
+            ControlClass::ScrollBar => write!(f, "\"SCROLLBAR\""),
+            ControlClass::ComboBox => write!(f, "\"COMBOBOX\""),
+            ControlClass::Other(byte) => write!(f, "0x{byte:02X}"),
+            ControlClass::Name(name) => write!(f, "{}", rc_string(name)),
+        }
+    }
+}
+
+/// <p>Reads a string that may instead be 0xFF and an ordinal word, as menu names and control text can be.</p>
+fn read_name_or_ordinal(bst: &mut ByteStream) -> Result<ResourceId, Error> {
+    if bst.peek_byte()? == 0xFF {
+        bst.read_byte()?;
+        return Ok(ResourceId::Integer(bst.read_word()?));
+    }
+    Ok(ResourceId::Name(read_ansi_string(bst)?))
+}
+
+fn rc_name(id: &ResourceId) -> String {
+    match id {
+        ResourceId::Integer(n) => n.to_string(),
+        ResourceId::Name(name) => rc_string(name),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Control {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub id: u16,
+    pub style: u32,
+    pub class: ControlClass,
+    pub text: ResourceId,
+    /// Creation data passed to the control's window procedure.
+    pub extra: Vec<u8>,
+}
+
+impl Control {
+    fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        let (x, y, width, height) = (bst.read_word()?, bst.read_word()?, bst.read_word()?, bst.read_word()?);
+        let id = bst.read_word()?;
+        let style = bst.read_dword()?;
+        let class = ControlClass::read(bst)?;
+        let text = read_name_or_ordinal(bst)?;
+        let extra_len = bst.read_byte()? as usize;
+        let extra = bst.read_bytes(extra_len)?;
+        Ok(Control { x, y, width, height, id, style, class, text, extra })
+    }
+}
+
+/// <p>An RT_DIALOG resource.  Coordinates are in dialog units.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dialog {
+    pub style: u32,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub menu: Option<ResourceId>,
+    /// A private window class for the dialog instead of the standard one.
+    pub class: Option<String>,
+    pub caption: String,
+    /// Point size and face name, present when the style has <code>DS_SETFONT</code>.
+    pub font: Option<(u16, String)>,
+    pub controls: Vec<Control>,
+}
+
+impl Dialog {
+    /// <p>Reads a 16-bit DLGTEMPLATE: the control count is a byte, strings are ANSI and there are no extended
+    /// styles or help ids.</p>
+    pub fn read(data: &[u8]) -> Result<Self, Error> {
+        let mut bst = ByteStream::new(data.to_vec());
+        let style = bst.read_dword()?;
+        let count = bst.read_byte()?;
+        let (x, y, width, height) = (bst.read_word()?, bst.read_word()?, bst.read_word()?, bst.read_word()?);
+        let menu = match read_name_or_ordinal(&mut bst)? {
+            ResourceId::Name(name) if name.is_empty() => None,
+            menu => Some(menu),
+        };
+        let class = Some(read_ansi_string(&mut bst)?).filter(|c| !c.is_empty());
+        let caption = read_ansi_string(&mut bst)?;
+        let font = match style & DS_SETFONT {
+            0 => None,
+            _ => Some((bst.read_word()?, read_ansi_string(&mut bst)?)),
+        };
+        let mut controls = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            controls.push(Control::read(&mut bst)?);
+        }
+        Ok(Dialog { style, x, y, width, height, menu, class, caption, font, controls })
+    }
+}
+
+/// <p>A <code>DIALOG</code> statement without the name in front.  Every control is written as a generic
+/// <code>CONTROL</code> so nothing about its class or style is lost.</p>
+impl fmt::Display for Dialog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DIALOG {}, {}, {}, {}", self.x, self.y, self.width, self.height)?;
+        writeln!(f, "STYLE 0x{:08X}", self.style)?;
+        if !self.caption.is_empty() {
+            writeln!(f, "CAPTION {}", rc_string(&self.caption))?;
+        }
+        if let Some(menu) = &self.menu {
+            writeln!(f, "MENU {}", rc_name(menu))?;
+        }
+        if let Some(class) = &self.class {
+            writeln!(f, "CLASS {}", rc_string(class))?;
+        }
+        if let Some((size, face)) = &self.font {
+            writeln!(f, "FONT {size}, {}", rc_string(face))?;
+        }
+        writeln!(f, "BEGIN")?;
+        for c in &self.controls {
+            writeln!(
+                f,
+                "    CONTROL {}, {}, {}, 0x{:08X}, {}, {}, {}, {}",
+                rc_name(&c.text),
+                c.id as i16,
+                c.class,
+                c.style,
+                c.x as i16,
+                c.y as i16,
+                c.width,
+                c.height
+            )?;
+        }
+        writeln!(f, "END")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// <p>A control template from its x, y, width, height and id, style, class and text.</p>
+    fn control(words: [u16; 5], style: u32, class: &[u8], text: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for word in words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(&style.to_le_bytes());
+        bytes.extend_from_slice(class);
+        bytes.extend_from_slice(text);
+        bytes
+    }
+
+    /// <p>A dialog with a font, menu 5 and four controls: a button, a static with icon 7 as its text, a registered
+    /// class with three bytes of creation data and a predefined class byte nothing names.</p>
+    fn about() -> Vec<u8> {
+        let mut data = (0x80C0_0000 | DS_SETFONT | 0x80).to_le_bytes().to_vec();
+        data.push(4);
+        for word in [10u16, 20, 200, 100] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(b"\xFF\x05\x00\x00About \"Demo\"\x00\x08\x00MS Sans Serif\x00");
+        data.extend(control([5, 5, 40, 14, 1], 0x5001_0001, b"\x80", b"OK\x00\x00"));
+        data.extend(control([5, 25, 20, 20, 0xFFFF], 0x5000_0003, b"\x82", b"\xFF\x07\x00\x00"));
+        data.extend(control([50, 5, 100, 12, 100], 0x5080_0080, b"msctls_trackbar\x00", b"\x00\x03\x01\x02\x03"));
+        data.extend(control([0, 0, 1, 1, 2], 0x5000_0000, b"\x86", b"x\x00\x00"));
+        data
+    }
+
+    #[test]
+    fn reads_the_template_and_its_controls() {
+        let dialog = Dialog::read(&about()).unwrap();
+        assert_eq!((dialog.x, dialog.y, dialog.width, dialog.height), (10, 20, 200, 100));
+        assert_eq!(dialog.menu, Some(ResourceId::Integer(5)));
+        assert_eq!((dialog.class, dialog.caption.as_str()), (None, "About \"Demo\""));
+        assert_eq!(dialog.font, Some((8, "MS Sans Serif".to_owned())));
+
+        let classes: Vec<&ControlClass> = dialog.controls.iter().map(|c| &c.class).collect();
+        assert_eq!(
+            classes,
+            [
+                &ControlClass::Button,
+                &ControlClass::Static,
+                &ControlClass::Name("msctls_trackbar".to_owned()),
+                &ControlClass::Other(0x86),
+            ]
+        );
+        assert_eq!(dialog.controls[1].text, ResourceId::Integer(7));
+        assert_eq!(dialog.controls[2].extra, [1, 2, 3]);
+        assert_eq!(dialog.controls[3].text, ResourceId::Name("x".to_owned()));
+    }
+
+    #[test]
+    fn renders_as_rc() {
+        assert_eq!(
+            Dialog::read(&about()).unwrap().to_string(),
+            "DIALOG 10, 20, 200, 100\n\
+             STYLE 0x80C000C0\n\
+             CAPTION \"About \"\"Demo\"\"\"\n\
+             MENU 5\n\
+             FONT 8, \"MS Sans Serif\"\n\
+             BEGIN\n    \
+             CONTROL \"OK\", 1, \"BUTTON\", 0x50010001, 5, 5, 40, 14\n    \
+             CONTROL 7, -1, \"STATIC\", 0x50000003, 5, 25, 20, 20\n    \
+             CONTROL \"\", 100, \"msctls_trackbar\", 0x50800080, 50, 5, 100, 12\n    \
+             CONTROL \"x\", 2, 0x86, 0x50000000, 0, 0, 1, 1\n\
+             END\n"
+        );
+    }
+
+    #[test]
+    fn the_font_is_only_there_with_ds_setfont() {
+        // no font, a named menu and a private class; the bytes after the caption are the control
+        let mut data = 0x8000_0000u32.to_le_bytes().to_vec();
+        data.push(1);
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(b"MAINMENU\x00DemoClass\x00\x00");
+        data.extend(control([1, 2, 3, 4, 5], 0, b"\x81", b"\x00\x00"));
+        let dialog = Dialog::read(&data).unwrap();
+        assert_eq!(dialog.font, None);
+        assert_eq!(dialog.menu, Some(ResourceId::Name("MAINMENU".to_owned())));
+        assert_eq!(dialog.class.as_deref(), Some("DemoClass"));
+        assert_eq!(dialog.controls[0].class, ControlClass::Edit);
+        let rc = dialog.to_string();
+        assert!(rc.contains("MENU \"MAINMENU\"\nCLASS \"DemoClass\"\nBEGIN\n"), "{rc}");
+        assert!(!rc.contains("CAPTION"));
+    }
+
+    #[test]
+    fn extra_bytes_past_the_end_are_an_error() {
+        let mut data = about();
+        data.truncate(data.len() - 4);
+        data.extend_from_slice(b"\x86\x00\x05\x01");
+        assert!(matches!(Dialog::read(&data), Err(Error::Truncated { needed: 5, .. })));
+    }
+}
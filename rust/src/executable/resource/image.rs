@@ -0,0 +1,233 @@
+use crate::{byte_stream::ByteStream, error::Error};
+
+const BITMAP_FILE_HEADER_SIZE: usize = 14;
+const ICON_DIR_SIZE: usize = 6;
+const ICON_DIR_ENTRY_SIZE: usize = 16;
+
+/// <p>One image of an RT_GROUP_ICON or RT_GROUP_CURSOR directory.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupEntry {
+    pub width: u16,
+    /// For a cursor this is the height of the DIB, which counts the AND mask too, so twice the cursor's height.
+    pub height: u16,
+    pub color_count: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+    pub size: u32,
+    /// The id of the RT_ICON or RT_CURSOR resource holding the image.
+    pub id: u16,
+}
+
+/// <p>Reads an RT_GROUP_ICON or RT_GROUP_CURSOR directory.  Cursor entries store the width and height as words
+/// instead of icons' bytes, colour count and reserved byte.</p>
+pub fn read_group(data: &[u8], cursor: bool) -> Result<Vec<GroupEntry>, Error> {
+    let mut bst = ByteStream::new(data.to_vec());
+    let _reserved = bst.read_word()?;
+    let kind = bst.read_word()?;
+    if kind != if cursor { 2 } else { 1 } {
+        return Err(Error::InvalidResource { reason: "group directory of the wrong type" });
+    }
+    let count = bst.read_word()?;
+    let mut entries = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let (width, height, color_count) = if cursor {
+            (bst.read_word()?, bst.read_word()?, 0)
+        } else {
+            let (width, height, color_count) = (bst.read_byte()?, bst.read_byte()?, bst.read_byte()?);
+            bst.read_byte()?;
+            (width as u16, height as u16, color_count)
+        };
+        entries.push(GroupEntry {
+            width,
+            height,
+            color_count,
+            planes: bst.read_word()?,
+            bit_count: bst.read_word()?,
+            size: bst.read_dword()?,
+            id: bst.read_word()?,
+        });
+    }
+    Ok(entries)
+}
+
+/// <p>Builds an .ico file from a group's entries and the RT_ICON data each one names, in the same order.</p>
+pub fn icon_file(entries: &[GroupEntry], images: &[&[u8]]) -> Vec<u8> {
+    let images: Vec<&[u8]> = entries.iter().zip(images).map(|(e, image)| trim(image, e.size as usize)).collect();
+    let mut file = directory(1, entries.len());
+    let mut offset = ICON_DIR_SIZE + ICON_DIR_ENTRY_SIZE * entries.len();
+    for (entry, image) in entries.iter().zip(&images) {
+        file.push(entry.width as u8);
+        file.push(entry.height as u8);
+        file.push(entry.color_count);
+        file.push(0);
+        file.extend_from_slice(&entry.planes.to_le_bytes());
+        file.extend_from_slice(&entry.bit_count.to_le_bytes());
+        file.extend_from_slice(&(image.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += image.len();
+    }
+    images.iter().for_each(|image| file.extend_from_slice(image));
+    file
+}
+
+/// <p>Builds a .cur file from a group's entries and the RT_CURSOR data each one names, in the same order.  Each
+/// RT_CURSOR starts with its hotspot, which moves into the file's directory entry.</p>
+pub fn cursor_file(entries: &[GroupEntry], images: &[&[u8]]) -> Result<Vec<u8>, Error> {
+    let mut hotspots = Vec::with_capacity(entries.len());
+    let mut dibs = Vec::with_capacity(entries.len());
+    for (entry, image) in entries.iter().zip(images) {
+        let image = trim(image, entry.size as usize);
+        if image.len() < 4 {
+            return Err(Error::InvalidResource { reason: "cursor without a hotspot" });
+        }
+        hotspots.push((&image[0..2], &image[2..4]));
+        dibs.push(&image[4..]);
+    }
+    let mut file = directory(2, entries.len());
+    let mut offset = ICON_DIR_SIZE + ICON_DIR_ENTRY_SIZE * entries.len();
+    for ((entry, (x, y)), dib) in entries.iter().zip(&hotspots).zip(&dibs) {
+        file.push(entry.width as u8);
+        file.push((entry.height / 2) as u8);
+        file.push(0);
+        file.push(0);
+        file.extend_from_slice(x);
+        file.extend_from_slice(y);
+        file.extend_from_slice(&(dib.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += dib.len();
+    }
+    dibs.iter().for_each(|dib| file.extend_from_slice(dib));
+    Ok(file)
+}
+
+/// <p>An .ico holding a single RT_ICON that no group refers to, with the directory entry made up from its DIB
+/// header.</p>
+pub fn single_icon_file(image: &[u8]) -> Result<Vec<u8>, Error> {
+    let entry = dib_entry(image)?;
+    Ok(icon_file(&[entry], &[image]))
+}
+
+/// <p>A .cur holding a single RT_CURSOR that no group refers to.</p>
+pub fn single_cursor_file(image: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut entry = dib_entry(image.get(4..).unwrap_or_default())?;
+    entry.height *= 2;
+    entry.size = image.len() as u32;
+    cursor_file(&[entry], &[image])
+}
+
+/// <p>Turns an RT_BITMAP, which is a packed DIB, into a .bmp by putting back the BITMAPFILEHEADER it was stored
+/// without.  Both BITMAPINFOHEADER and the OS/2 BITMAPCOREHEADER are understood.</p>
+pub fn bitmap_file(dib: &[u8]) -> Result<Vec<u8>, Error> {
+    let bst = ByteStream::new(dib.to_vec());
+    let header_size = bst.read_dword_at(0)? as usize;
+    let palette_size = match header_size {
+        12 => {
+            let bit_count = bst.read_word_at(10)?;
+            if bit_count <= 8 { 3 << bit_count } else { 0 }
+        }
+        40.. => {
+            let bit_count = bst.read_word_at(14)?;
+            let compression = bst.read_dword_at(16)?;
+            let colors_used = bst.read_dword_at(32)? as usize;
+            let colors = match colors_used {
+                0 if bit_count <= 8 => 1 << bit_count,
+                n => n,
+            };
+            // BI_BITFIELDS keeps its three masks after a plain BITMAPINFOHEADER
+            let masks = if header_size == 40 && compression == 3 { 12 } else { 0 };
+            colors * 4 + masks
+        }
+        _ => return Err(Error::InvalidResource { reason: "unknown bitmap header size" }),
+    };
+    let bits_offset = BITMAP_FILE_HEADER_SIZE + header_size + palette_size;
+    let mut file = Vec::with_capacity(BITMAP_FILE_HEADER_SIZE + dib.len());
+    file.extend_from_slice(b"BM");
+    file.extend_from_slice(&((BITMAP_FILE_HEADER_SIZE + dib.len()) as u32).to_le_bytes());
+    file.extend_from_slice(&[0; 4]);
+    file.extend_from_slice(&(bits_offset as u32).to_le_bytes());
+    file.extend_from_slice(dib);
+    Ok(file)
+}
+
+fn directory(kind: u16, count: usize) -> Vec<u8> {
+    let mut file = Vec::with_capacity(ICON_DIR_SIZE + ICON_DIR_ENTRY_SIZE * count);
+    file.extend_from_slice(&0u16.to_le_bytes());
+    file.extend_from_slice(&kind.to_le_bytes());
+    file.extend_from_slice(&(count as u16).to_le_bytes());
+    file
+}
+
+/// <p>Resource lengths are rounded up to the table's alignment, so the directory's size is the real one.</p>
+fn trim(image: &[u8], size: usize) -> &[u8] {
+    &image[..size.min(image.len())]
+}
+
+/// <p>A directory entry for an icon image, read from its BITMAPINFOHEADER.  The DIB height counts the AND mask as
+/// well as the image, so it is halved.</p>
+fn dib_entry(image: &[u8]) -> Result<GroupEntry, Error> {
+    let bst = ByteStream::new(image.to_vec());
+    if bst.read_dword_at(0)? < 40 {
+        return Err(Error::InvalidResource { reason: "icon without a BITMAPINFOHEADER" });
+    }
+    let width = bst.read_dword_at(4)? as u16;
+    let height = (bst.read_dword_at(8)? / 2) as u16;
+    let planes = bst.read_word_at(12)?;
+    let bit_count = bst.read_word_at(14)?;
+    let color_count = if bit_count < 8 { 1u8 << bit_count } else { 0 };
+    Ok(GroupEntry { width, height, color_count, planes, bit_count, size: image.len() as u32, id: 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// <p>A BITMAPINFOHEADER followed by <code>payload</code> bytes of palette and bits.</p>
+    fn dib(width: u32, height: u32, bit_count: u16, payload: usize) -> Vec<u8> {
+        let mut dib = vec![0; 40 + payload];
+        dib[0..4].copy_from_slice(&40u32.to_le_bytes());
+        dib[4..8].copy_from_slice(&width.to_le_bytes());
+        dib[8..12].copy_from_slice(&height.to_le_bytes());
+        dib[12..14].copy_from_slice(&1u16.to_le_bytes());
+        dib[14..16].copy_from_slice(&bit_count.to_le_bytes());
+        dib
+    }
+
+    #[test]
+    fn icon_group_becomes_an_ico() {
+        let group = [0, 0, 1, 0, 1, 0, 32, 32, 16, 0, 1, 0, 4, 0, 0xE8, 0x02, 0, 0, 7, 0];
+        let entries = read_group(&group, false).unwrap();
+        let expected =
+            GroupEntry { width: 32, height: 32, color_count: 16, planes: 1, bit_count: 4, size: 0x2E8, id: 7 };
+        assert_eq!(entries, [expected]);
+        assert!(read_group(&group, true).is_err());
+
+        // padded to the resource table's alignment
+        let image = dib(32, 64, 4, 0x2E8 - 40 + 8);
+        let file = icon_file(&entries, &[&image]);
+        assert_eq!(file.len(), 6 + 16 + 0x2E8);
+        assert_eq!(file[..6], [0, 0, 1, 0, 1, 0]);
+        assert_eq!(file[6..10], [32, 32, 16, 0]);
+        assert_eq!(file[14..22], [0xE8, 0x02, 0, 0, 22, 0, 0, 0]);
+        assert_eq!(file[22..], image[..0x2E8]);
+    }
+
+    #[test]
+    fn cursor_hotspot_moves_into_the_directory() {
+        let mut image = vec![5, 0, 9, 0];
+        image.extend(dib(32, 64, 1, 8 + 256));
+        let file = single_cursor_file(&image).unwrap();
+        assert_eq!(file[..6], [0, 0, 2, 0, 1, 0]);
+        assert_eq!(file[6..14], [32, 32, 0, 0, 5, 0, 9, 0]);
+        assert_eq!(file[22..], image[4..]);
+        assert!(single_cursor_file(&[5, 0]).is_err());
+    }
+
+    #[test]
+    fn bitmap_gets_a_file_header() {
+        let file = bitmap_file(&dib(8, 8, 4, 16 * 4 + 32)).unwrap();
+        assert_eq!(file[..2], *b"BM");
+        assert_eq!(u32::from_le_bytes(file[2..6].try_into().unwrap()) as usize, file.len());
+        assert_eq!(u32::from_le_bytes(file[10..14].try_into().unwrap()), 14 + 40 + 16 * 4);
+        assert!(bitmap_file(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+use super::{read_ansi_string, rc_string};
+use crate::{byte_stream::ByteStream, error::Error};
+
+pub const MF_GRAYED: u16 = 0x0001;
+pub const MF_INACTIVE: u16 = 0x0002;
+pub const MF_BITMAP: u16 = 0x0004;
+pub const MF_CHECKED: u16 = 0x0008;
+pub const MF_POPUP: u16 = 0x0010;
+pub const MF_MENUBARBREAK: u16 = 0x0020;
+pub const MF_MENUBREAK: u16 = 0x0040;
+/// Marks the last item of a menu or popup.
+pub const MF_END: u16 = 0x0080;
+pub const MF_HELP: u16 = 0x4000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    Command { id: u16, text: String, flags: u16 },
+    Separator,
+    Popup { text: String, flags: u16, items: Vec<MenuItem> },
+}
+
+/// <p>An RT_MENU resource.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Menu {
+    pub items: Vec<MenuItem>,
+}
+
+impl Menu {
+    /// <p>Reads a 16-bit menu template: a header of two zero words, then items with ANSI text.  Popups are followed
+    /// by their own items; the last item at each level has <code>MF_END</code> set.</p>
+    pub fn read(data: &[u8]) -> Result<Self, Error> {
+        let mut bst = ByteStream::new(data.to_vec());
+        let version = bst.read_word()?;
+        let header_size = bst.read_word()?;
+        if version != 0 {
+            return Err(Error::InvalidResource { reason: "menu template version" });
+        }
+        bst.pos += header_size as usize;
+        Ok(Menu { items: read_items(&mut bst, 0)? })
+    }
+}
+
+/// <p>Popups can't really be nested this deep, so anything deeper is a loop in bad data.</p>
+const MAX_DEPTH: usize = 32;
+
+fn read_items(bst: &mut ByteStream, depth: usize) -> Result<Vec<MenuItem>, Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::InvalidResource { reason: "menu nested too deeply" });
+    }
+    let mut items = Vec::new();
+    loop {
+        let flags = bst.read_word()?;
+        let item = if flags & MF_POPUP != 0 {
+            let text = read_ansi_string(bst)?;
+            MenuItem::Popup { text, flags, items: read_items(bst, depth + 1)? }
+        } else {
+            let id = bst.read_word()?;
+            let text = read_ansi_string(bst)?;
+            match (flags & !MF_END, id, text.is_empty()) {
+                (0, 0, true) => MenuItem::Separator,
+                _ => MenuItem::Command { id, text, flags },
+            }
+        };
+        items.push(item);
+        if flags & MF_END != 0 {
+            return Ok(items);
+        }
+    }
+}
+
+/// <p>The .rc options for the flags that have one.</p>
+fn options(flags: u16) -> String {
+    [
+        (MF_CHECKED, "CHECKED"),
+        (MF_GRAYED, "GRAYED"),
+        (MF_INACTIVE, "INACTIVE"),
+        (MF_MENUBARBREAK, "MENUBARBREAK"),
+        (MF_MENUBREAK, "MENUBREAK"),
+        (MF_HELP, "HELP"),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, name)| format!(", {name}"))
+    .collect()
+}
+
+fn write_items(f: &mut fmt::Formatter<'_>, items: &[MenuItem], indent: usize) -> fmt::Result {
+    let pad = "    ".repeat(indent);
+    writeln!(f, "{}BEGIN", "    ".repeat(indent - 1))?;
+    for item in items {
+        match item {
+            MenuItem::Command { id, text, flags } => {
+                writeln!(f, "{pad}MENUITEM {}, {id}{}", rc_string(text), options(*flags))?
+            }
+            MenuItem::Separator => writeln!(f, "{pad}MENUITEM SEPARATOR")?,
+            MenuItem::Popup { text, flags, items } => {
+                writeln!(f, "{pad}POPUP {}{}", rc_string(text), options(*flags))?;
+                write_items(f, items, indent + 1)?;
+            }
+        }
+    }
+    writeln!(f, "{}END", "    ".repeat(indent - 1))
+}
+
+/// <p>A <code>MENU</code> statement without the name in front.</p>
+impl fmt::Display for Menu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MENU")?;
+        write_items(f, &self.items, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_popups_and_separators() {
+        let mut data = vec![0, 0, 0, 0];
+        data.extend_from_slice(&(MF_POPUP | MF_END).to_le_bytes());
+        data.extend_from_slice(b"&File\0");
+        data.extend_from_slice(&MF_CHECKED.to_le_bytes());
+        data.extend_from_slice(&100u16.to_le_bytes());
+        data.extend_from_slice(b"&Open\0");
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(&MF_END.to_le_bytes());
+        data.extend_from_slice(&101u16.to_le_bytes());
+        data.extend_from_slice(b"E&xit\0");
+
+        let menu = Menu::read(&data).unwrap();
+        let MenuItem::Popup { items, .. } = &menu.items[0] else { panic!("{menu:?}") };
+        assert_eq!(items[1], MenuItem::Separator);
+        assert_eq!(
+            menu.to_string(),
+            "MENU\nBEGIN\n    POPUP \"&File\"\n    BEGIN\n        MENUITEM \"&Open\", 100, CHECKED\n        \
+             MENUITEM SEPARATOR\n        MENUITEM \"E&xit\", 101\n    END\nEND\n"
+        );
+    }
+
+    #[test]
+    fn rejects_other_template_versions() {
+        assert!(Menu::read(&[1, 0, 0, 0, 0x80, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+//! <p>Decoders for resource data.  The formats are the same whichever kind of executable the bytes came out of, so
+//! these only take the raw resource bytes.</p>
+
+use crate::{byte_stream::ByteStream, error::Error};
+
+pub mod accelerator;
+pub mod dialog;
+pub mod image;
+pub mod menu;
+pub mod string_table;
+pub mod version;
+
+/// <p>What Windows-1252 puts at 0x80-0x9F, where Latin-1 has control characters.  Unassigned ones stay as they
+/// are.</p>
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}', '\u{90}', '‘', '’',
+    '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// <p>Text in the ANSI code page 16-bit resources are stored in, taken to be Windows-1252.</p>
+pub fn decode_ansi(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => CP1252_HIGH[b as usize - 0x80],
+            _ => b as char,
+        })
+        .collect()
+}
+
+/// <p>Reads a zero-terminated ANSI string, leaving the position after the terminator.</p>
+pub(crate) fn read_ansi_string(bst: &mut ByteStream) -> Result<String, Error> {
+    let end = bst.find_byte(bst.pos, 0).ok_or(Error::Truncated { offset: bst.base() + bst.pos, needed: 1 })?;
+    let text = decode_ansi(&bst.read_bytes(end - bst.pos)?);
+    bst.pos += 1;
+    Ok(text)
+}
+
+/// <p>A string as a quoted .rc literal.  Quotes are doubled and control characters escaped.</p>
+pub fn rc_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted += "\"\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\r' => quoted += "\\r",
+            '\t' => quoted += "\\t",
+            c if (c as u32) < 0x20 => quoted += &format!("\\x{:02X}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+use super::{decode_ansi, rc_string};
+use crate::{byte_stream::ByteStream, error::Error};
+
+/// <p>One RT_STRING resource, a bundle of 16 strings.  The resource with id <code>n</code> holds strings
+/// <code>(n - 1) * 16</code> to <code>(n - 1) * 16 + 15</code>.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StringTable {
+    /// The strings that aren't empty, next to their ids.
+    pub strings: Vec<(u16, String)>,
+}
+
+impl StringTable {
+    /// <p>Reads a 16-bit bundle, where each string is a length byte and that many ANSI characters.</p>
+    pub fn read(data: &[u8], bundle_id: u16) -> Result<Self, Error> {
+        let mut bst = ByteStream::new(data.to_vec());
+        let first = bundle_id.wrapping_sub(1).wrapping_mul(16);
+        let mut strings = Vec::new();
+        for i in 0..16 {
+            // bundles are sometimes cut short after the last string that is used
+            if !bst.available() {
+                break;
+            }
+            let len = bst.read_byte()? as usize;
+            if len > 0 {
+                strings.push((first.wrapping_add(i), decode_ansi(&bst.read_bytes(len)?)));
+            }
+        }
+        Ok(StringTable { strings })
+    }
+}
+
+impl fmt::Display for StringTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "STRINGTABLE")?;
+        writeln!(f, "BEGIN")?;
+        for (id, text) in &self.strings {
+            writeln!(f, "    {id}, {}", rc_string(text))?;
+        }
+        writeln!(f, "END")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_bundle_cut_short() {
+        let table = StringTable::read(b"\x00\x05Hello\x00\x0aSay \"hi\"\x93\x94", 2).unwrap();
+        assert_eq!(table.strings, vec![(17, "Hello".to_string()), (19, "Say \"hi\"\u{201C}\u{201D}".to_string())]);
+        assert_eq!(
+            table.to_string(),
+            "STRINGTABLE\nBEGIN\n    17, \"Hello\"\n    19, \"Say \"\"hi\"\"\u{201C}\u{201D}\"\nEND\n"
+        );
+    }
+
+    #[test]
+    fn a_string_past_the_end_is_an_error() {
+        assert!(StringTable::read(b"\x05Hel", 1).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use super::decode_ansi;
+use crate::{byte_stream::ByteStream, error::Error};
+
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF04BD;
+
+/// <p>VS_FIXEDFILEINFO, the language-independent part of a version resource.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedFileInfo {
+    pub struct_version: u32,
+    /// Most significant part first, the way it is written: 1.2.3.4 is <code>[1, 2, 3, 4]</code>.
+    pub file_version: [u16; 4],
+    pub product_version: [u16; 4],
+    pub file_flags_mask: u32,
+    pub file_flags: u32,
+    pub file_os: u32,
+    pub file_type: u32,
+    pub file_subtype: u32,
+    pub file_date: u64,
+}
+
+impl FixedFileInfo {
+    fn read(value: &[u8]) -> Result<Self, Error> {
+        let mut bst = ByteStream::new(value.to_vec());
+        if bst.read_dword()? != FIXED_FILE_INFO_SIGNATURE {
+            return Err(Error::InvalidResource { reason: "VS_FIXEDFILEINFO signature" });
+        }
+        let struct_version = bst.read_dword()?;
+        let mut version = || -> Result<[u16; 4], Error> {
+            let (ms, ls) = (bst.read_dword()?, bst.read_dword()?);
+            Ok([(ms >> 16) as u16, ms as u16, (ls >> 16) as u16, ls as u16])
+        };
+        let file_version = version()?;
+        let product_version = version()?;
+        Ok(FixedFileInfo {
+            struct_version,
+            file_version,
+            product_version,
+            file_flags_mask: bst.read_dword()?,
+            file_flags: bst.read_dword()?,
+            file_os: bst.read_dword()?,
+            file_type: bst.read_dword()?,
+            file_subtype: bst.read_dword()?,
+            file_date: ((bst.read_dword()? as u64) << 32) | bst.read_dword()? as u64,
+        })
+    }
+}
+
+/// <p>One StringTable of a StringFileInfo: the strings for one language and code page.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionStrings {
+    /// The block's key, the language and code page as eight hex digits, like <code>040904E4</code>.
+    pub language: String,
+    pub strings: Vec<(String, String)>,
+}
+
+/// <p>An RT_VERSION resource.</p>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub fixed: Option<FixedFileInfo>,
+    pub string_tables: Vec<VersionStrings>,
+    /// The (language, code page) pairs from VarFileInfo\Translation.
+    pub translations: Vec<(u16, u16)>,
+}
+
+impl VersionInfo {
+    /// <p>Reads the 16-bit layout, where keys and values are ANSI and blocks have no type word.</p>
+    pub fn read_ne(data: &[u8]) -> Result<Self, Error> {
+        Self::read(data, false)
+    }
+    /// <p>Reads the 32-bit layout, where keys and values are UTF-16 and each block says whether its value is text.</p>
+    pub fn read_pe(data: &[u8]) -> Result<Self, Error> {
+        Self::read(data, true)
+    }
+
+    fn read(data: &[u8], wide: bool) -> Result<Self, Error> {
+        let bst = ByteStream::new(data.to_vec());
+        let (root, _) = Block::read(&bst, 0, wide, 0)?;
+        if root.key != "VS_VERSION_INFO" {
+            return Err(Error::InvalidResource { reason: "version resource without VS_VERSION_INFO" });
+        }
+        let mut info = VersionInfo {
+            fixed: match root.value.is_empty() {
+                true => None,
+                false => Some(FixedFileInfo::read(&root.value)?),
+            },
+            ..Default::default()
+        };
+        for child in &root.children {
+            match child.key.as_str() {
+                "StringFileInfo" => {
+                    for table in &child.children {
+                        info.string_tables.push(VersionStrings {
+                            language: table.key.clone(),
+                            strings: table.children.iter().map(|s| (s.key.clone(), s.text(wide))).collect(),
+                        });
+                    }
+                }
+                "VarFileInfo" => {
+                    for var in child.children.iter().filter(|v| v.key == "Translation") {
+                        info.translations.extend(
+                            var.value
+                                .chunks_exact(4)
+                                .map(|c| (u16::from_le_bytes([c[0], c[1]]), u16::from_le_bytes([c[2], c[3]]))),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// <p>A string like <code>CompanyName</code> or <code>FileVersion</code>, from the first table that has it.</p>
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.string_tables.iter().flat_map(|t| &t.strings).find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+/// <p>The nested blocks every part of a version resource is made of.</p>
+struct Block {
+    key: String,
+    value: Vec<u8>,
+    children: Vec<Block>,
+}
+
+/// <p>Blocks can't really be nested this deep, so anything deeper is a loop in bad data.</p>
+const MAX_DEPTH: usize = 8;
+
+fn align(pos: usize) -> usize {
+    (pos + 3) & !3
+}
+
+impl Block {
+    /// <p>Reads the block at <code>start</code> and returns it with where it ends.  Everything after the header is
+    /// aligned to 4 bytes from the start of the resource.</p>
+    fn read(bst: &ByteStream, start: usize, wide: bool, depth: usize) -> Result<(Self, usize), Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::InvalidResource { reason: "version blocks nested too deeply" });
+        }
+        let length = bst.read_word_at(start)? as usize;
+        let value_length = bst.read_word_at(start + 2)? as usize;
+        let mut pos = start + 4;
+        let text = match wide {
+            true => {
+                pos += 2;
+                bst.read_word_at(start + 4)? == 1
+            }
+            false => false,
+        };
+        if length < pos - start {
+            return Err(Error::InvalidResource { reason: "version block shorter than its header" });
+        }
+        let end = (start + length).min(bst.len());
+
+        let key_end = match wide {
+            true => (pos..end).step_by(2).find(|&p| bst.read_word_at(p).is_ok_and(|w| w == 0)),
+            false => bst.find_byte(pos, 0).filter(|&p| p < end),
+        }
+        .ok_or(Error::InvalidResource { reason: "version block key runs past the block" })?;
+        let key_bytes = bst.read_bytes_at(key_end - pos, pos)?;
+        let key = match wide {
+            true => utf16(&key_bytes),
+            false => decode_ansi(&key_bytes),
+        };
+        pos = align(key_end + if wide { 2 } else { 1 });
+
+        // a text value's length counts characters in the UTF-16 layout
+        let value_size = if text { value_length * 2 } else { value_length };
+        let value_end = (pos + value_size).min(end);
+        let value = bst.read_bytes_at(value_end.saturating_sub(pos), pos.min(value_end))?;
+        pos = align(value_end);
+
+        let mut children = Vec::new();
+        while pos < end {
+            let (child, child_end) = Block::read(bst, pos, wide, depth + 1)?;
+            children.push(child);
+            pos = align(child_end);
+        }
+        Ok((Block { key, value, children }, end))
+    }
+
+    /// <p>The value as text, up to the first terminator.</p>
+    fn text(&self, wide: bool) -> String {
+        match wide {
+            true => utf16(&self.value),
+            false => decode_ansi(self.value.split(|b| *b == 0).next().unwrap_or_default()),
+        }
+    }
+}
+
+/// <p>Little-endian UTF-16 up to the first terminator.</p>
+fn utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> =
+        bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).take_while(|u| *u != 0).collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Value<'a> {
+        Text(&'a str),
+        Binary(&'a [u8]),
+    }
+
+    /// <p>Encodes a block in either layout.  <code>text</code> values are given without their terminator.</p>
+    fn block(wide: bool, key: &str, value: Value, children: &[Vec<u8>]) -> Vec<u8> {
+        let encode = |s: &str| match wide {
+            true => s.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect::<Vec<u8>>(),
+            false => s.bytes().chain([0]).collect(),
+        };
+        let (value, value_length, text) = match value {
+            Value::Text(s) => {
+                let bytes = encode(s);
+                let length = if wide { bytes.len() / 2 } else { bytes.len() };
+                (bytes, length, true)
+            }
+            Value::Binary(bytes) => (bytes.to_vec(), bytes.len(), false),
+        };
+        let mut data = vec![0, 0];
+        data.extend_from_slice(&(value_length as u16).to_le_bytes());
+        if wide {
+            data.extend_from_slice(&(text as u16).to_le_bytes());
+        }
+        data.extend(encode(key));
+        data.resize(align(data.len()), 0);
+        data.extend(value);
+        for child in children {
+            data.resize(align(data.len()), 0);
+            data.extend(child);
+        }
+        let length = data.len() as u16;
+        data[0..2].copy_from_slice(&length.to_le_bytes());
+        data
+    }
+
+    fn resource(wide: bool) -> Vec<u8> {
+        let mut fixed = Vec::new();
+        for dword in [FIXED_FILE_INFO_SIGNATURE, 0x10000, 0x10002, 0x30004, 0x50006, 0x70008, 0x3F, 0, 1, 2, 0, 0, 0] {
+            fixed.extend_from_slice(&u32::to_le_bytes(dword));
+        }
+        let strings = [
+            block(wide, "CompanyName", Value::Text("Acme"), &[]),
+            block(wide, "FileVersion", Value::Text("1.2.3.4"), &[]),
+        ];
+        let table = block(wide, "040904E4", Value::Binary(&[]), &strings);
+        let translation = block(wide, "Translation", Value::Binary(&[0x09, 0x04, 0xE4, 0x04]), &[]);
+        let children = [
+            block(wide, "StringFileInfo", Value::Binary(&[]), &[table]),
+            block(wide, "VarFileInfo", Value::Binary(&[]), &[translation]),
+        ];
+        block(wide, "VS_VERSION_INFO", Value::Binary(&fixed), &children)
+    }
+
+    fn check(info: VersionInfo) {
+        let fixed = info.fixed.unwrap();
+        assert_eq!((fixed.file_version, fixed.product_version), ([1, 2, 3, 4], [5, 6, 7, 8]));
+        assert_eq!((fixed.file_os, fixed.file_type), (1, 2));
+        assert_eq!(info.string_tables[0].language, "040904E4");
+        assert_eq!(info.get("CompanyName"), Some("Acme"));
+        assert_eq!(info.get("FileVersion"), Some("1.2.3.4"));
+        assert_eq!(info.get("ProductName"), None);
+        assert_eq!(info.translations, [(0x0409, 0x04E4)]);
+    }
+
+    #[test]
+    fn reads_both_layouts() {
+        check(VersionInfo::read_ne(&resource(false)).unwrap());
+        check(VersionInfo::read_pe(&resource(true)).unwrap());
+    }
+
+    #[test]
+    fn rejects_other_roots() {
+        let data = block(false, "VS_SOMETHING", Value::Binary(&[]), &[]);
+        assert!(VersionInfo::read_ne(&data).is_err());
+    }
+}
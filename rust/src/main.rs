@@ -1,19 +1,23 @@
 use executable::Executable;
 
 pub mod byte_stream;
+pub mod com;
+pub mod error;
 pub mod executable;
+pub mod memory;
 pub mod mz;
 pub mod byte_operation;
 pub mod apis;
 
-fn log_info(exe: Executable) {}
+fn log_info(_exe: Executable) {}
 
-fn main() {
+fn main() -> Result<(), error::Error> {
     const FILE1: &str = "C:/Users/jjthe/Desktop/16-bit Programs/Spelling Jungle/BST.EXE";
     const FILE2: &str = "C:/Users/jjthe/Desktop/16-bit Programs/Spelling Jungle/BSTCDRES.DLL";
     const FILE3: &str = "C:/Users/jjthe/Downloads/American Girls Premiere/DISK1/SETUP.EXE";
 
-    log_info(executable::Executable::read(FILE1).unwrap());
-    log_info(executable::Executable::read(FILE2).unwrap());
-    log_info(executable::Executable::read(FILE3).unwrap());
+    log_info(executable::Executable::read(FILE1)?);
+    log_info(executable::Executable::read(FILE2)?);
+    log_info(executable::Executable::read(FILE3)?);
+    Ok(())
 }
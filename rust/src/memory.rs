@@ -0,0 +1,188 @@
+use std::{cell::RefCell, ops::Range};
+
+/// <p>One past the highest address an 8086 can reach, where addresses wrap around to zero.</p>
+pub const ONE_MIB: usize = 0x10_0000;
+/// <p>One past the highest address <code>FFFF:FFFF</code> reaches once the A20 line is enabled: the high memory
+/// area is the 64 KiB less 16 bytes above the first megabyte.</p>
+pub const HMA_END: usize = 0x10_FFF0;
+
+/// <p>Where programs get their PSP unless told otherwise: the first free paragraph after the interrupt vectors
+/// and the BIOS data area, since there is no DOS kernel taking up low memory.</p>
+pub const DEFAULT_PSP_SEGMENT: u16 = 0x0060;
+/// <p>The PSP is 256 bytes, so a program image starts this many paragraphs after it.</p>
+pub const PSP_PARAGRAPHS: u16 = 0x10;
+
+/// <p>A range of physical addresses that reads, writes or both should be reported for.</p>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: Range<usize>,
+    pub read: bool,
+    pub write: bool,
+}
+
+/// <p>A read or write of <code>address</code> that hit a watchpoint.</p>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub address: usize,
+    pub write: bool,
+}
+
+/// <p>The real-mode address space of an emulated machine.  Everything is addressed as <code>segment:offset</code>,
+/// which is <code>segment * 16 + offset</code>; reads and writes through a segment wrap at the end of it the way
+/// the 8086 does, so a word at offset <code>FFFF</code> takes its high byte from offset 0.</p>
+/// <p>With <code>a20</code> off, physical addresses past 1 MiB wrap to the bottom like on an 8086, which some
+/// programs count on.  With it on, the high memory area is reachable too.</p>
+/// <p>Reads and writes that fall in a watchpoint are remembered until <code>take_hits</code> collects them.  The
+/// <code>peek</code> reads are never reported, for looking at memory without setting anything off.</p>
+#[derive(Clone, PartialEq, Eq)]
+pub struct Memory {
+    bytes: Vec<u8>,
+    pub a20: bool,
+    pub watchpoints: Vec<Watchpoint>,
+    hits: RefCell<Vec<Access>>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory {
+    /// <p>A zeroed address space with the A20 line off.</p>
+    pub fn new() -> Self {
+        Self { bytes: vec![0; HMA_END], a20: false, watchpoints: Vec::new(), hits: RefCell::new(Vec::new()) }
+    }
+
+    /// <p>The segment a program image goes in when its PSP is at <code>psp</code>.</p>
+    pub fn program_segment(psp: u16) -> u16 {
+        psp.wrapping_add(PSP_PARAGRAPHS)
+    }
+
+    /// <p>The physical address of <code>segment:offset</code>, after the A20 wraparound if it applies.</p>
+    pub fn physical(&self, segment: u16, offset: u16) -> usize {
+        self.wrap(((segment as usize) << 4) + offset as usize)
+    }
+    fn wrap(&self, address: usize) -> usize {
+        match self.a20 {
+            true => address % HMA_END,
+            false => address % ONE_MIB,
+        }
+    }
+
+    /// <p>The accesses that hit a watchpoint since the last call, in the order they happened.</p>
+    pub fn take_hits(&self) -> Vec<Access> {
+        self.hits.take()
+    }
+    fn watch(&self, address: usize, write: bool) {
+        let watched = |w: &Watchpoint| w.range.contains(&address) && if write { w.write } else { w.read };
+        if self.watchpoints.iter().any(watched) {
+            self.hits.borrow_mut().push(Access { address, write });
+        }
+    }
+    fn get(&self, address: usize) -> u8 {
+        self.watch(address, false);
+        self.bytes[address]
+    }
+    fn set(&mut self, address: usize, v: u8) {
+        self.watch(address, true);
+        self.bytes[address] = v;
+    }
+
+    pub fn read_byte(&self, segment: u16, offset: u16) -> u8 {
+        self.get(self.physical(segment, offset))
+    }
+    pub fn write_byte(&mut self, segment: u16, offset: u16, v: u8) {
+        self.set(self.physical(segment, offset), v);
+    }
+    pub fn read_word(&self, segment: u16, offset: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(segment, offset), self.read_byte(segment, offset.wrapping_add(1))])
+    }
+    pub fn write_word(&mut self, segment: u16, offset: u16, v: u16) {
+        let [low, high] = v.to_le_bytes();
+        self.write_byte(segment, offset, low);
+        self.write_byte(segment, offset.wrapping_add(1), high);
+    }
+
+    /// <p><code>len</code> bytes from <code>segment:offset</code>, wrapping at the end of the segment.</p>
+    pub fn read_bytes(&self, segment: u16, offset: u16, len: usize) -> Vec<u8> {
+        (0..len).map(|i| self.read_byte(segment, offset.wrapping_add(i as u16))).collect()
+    }
+    /// <p>Like <code>read_bytes</code>, without reporting the reads to any watchpoint.</p>
+    pub fn peek_bytes(&self, segment: u16, offset: u16, len: usize) -> Vec<u8> {
+        (0..len).map(|i| self.bytes[self.physical(segment, offset.wrapping_add(i as u16))]).collect()
+    }
+    pub fn write_bytes(&mut self, segment: u16, offset: u16, bytes: &[u8]) {
+        for (i, b) in bytes.iter().enumerate() {
+            self.write_byte(segment, offset.wrapping_add(i as u16), *b);
+        }
+    }
+
+    pub fn read_byte_at(&self, address: usize) -> u8 {
+        self.get(self.wrap(address))
+    }
+    pub fn write_byte_at(&mut self, address: usize, v: u8) {
+        self.set(self.wrap(address), v);
+    }
+    pub fn read_word_at(&self, address: usize) -> u16 {
+        u16::from_le_bytes([self.read_byte_at(address), self.read_byte_at(address + 1)])
+    }
+    pub fn write_word_at(&mut self, address: usize, v: u16) {
+        let [low, high] = v.to_le_bytes();
+        self.write_byte_at(address, low);
+        self.write_byte_at(address + 1, high);
+    }
+
+    /// <p>Copies <code>image</code> to the start of <code>segment</code> and on, unlike <code>write_bytes</code> not
+    /// wrapping at the end of the segment, so it can take a load module bigger than 64 KiB.</p>
+    pub fn load(&mut self, segment: u16, image: &[u8]) {
+        let start = (segment as usize) << 4;
+        for (i, b) in image.iter().enumerate() {
+            self.write_byte_at(start + i, *b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a20_decides_where_ffff_0010_is() {
+        let mut memory = Memory::new();
+        memory.write_byte_at(0, 0x11);
+        memory.a20 = true;
+        memory.write_byte_at(ONE_MIB, 0x22);
+
+        memory.a20 = false;
+        assert_eq!(memory.physical(0xFFFF, 0x0010), 0);
+        assert_eq!(memory.read_byte(0xFFFF, 0x0010), 0x11);
+
+        memory.a20 = true;
+        assert_eq!(memory.physical(0xFFFF, 0x0010), ONE_MIB);
+        assert_eq!(memory.read_byte(0xFFFF, 0x0010), 0x22);
+        assert_eq!(memory.physical(0xFFFF, 0xFFFF), HMA_END - 1);
+    }
+
+    #[test]
+    fn words_wrap_within_the_segment() {
+        let mut memory = Memory::new();
+        memory.write_word(0x1000, 0xFFFF, 0xBEEF);
+        assert_eq!(memory.read_byte(0x1000, 0xFFFF), 0xEF);
+        assert_eq!(memory.read_byte(0x1000, 0x0000), 0xBE);
+        assert_eq!(memory.read_word(0x1000, 0xFFFF), 0xBEEF);
+    }
+
+    #[test]
+    fn watchpoints_report_matching_accesses() {
+        let mut memory = Memory::new();
+        memory.watchpoints.push(Watchpoint { range: 0x500..0x502, read: false, write: true });
+        memory.read_word_at(0x500);
+        assert!(memory.take_hits().is_empty());
+
+        memory.write_word(0x0050, 0x0001, 0x1234);
+        assert_eq!(memory.take_hits(), vec![Access { address: 0x501, write: true }]);
+        assert_eq!(memory.peek_bytes(0x0050, 0, 3), [0, 0x34, 0x12]);
+        assert!(memory.take_hits().is_empty());
+    }
+}
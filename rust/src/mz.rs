@@ -1,4 +1,20 @@
-use crate::{byte_stream::ByteStream, executable::Signature};
+use std::ops::Range;
+
+use crate::{
+    apis::dos::{Startup, MEMORY_TOP_SEGMENT},
+    byte_operation::{
+        cfg::ControlFlowGraph,
+        trace::{trace_code, Disassembly},
+        x86_16::Cpu,
+    },
+    byte_stream::ByteStream,
+    error::Error,
+    executable::Signature,
+    memory::Memory,
+};
+
+/// <p>The size of the DOS header with the fields Windows added, up to and including <code>new_header_start</code>.</p>
+pub const EXTENDED_HEADER_LEN: usize = 0x40;
 
 pub struct MZ {
     pub last_page_bytes: u16,
@@ -28,48 +44,122 @@ impl MZ {
         Signature::MZ
     }
 
-    pub fn read(bst: &mut ByteStream) -> Self {
-        let last_page_bytes = bst.read_word();
-        let page_count = bst.read_word();
-        let relocation_table_entry_count = bst.read_word();
-        let header_size = bst.read_word();
-        let min_alloc = bst.read_word();
-        let max_alloc = bst.read_word();
-        let init_ss = bst.read_word();
-        let init_sp = bst.read_word();
-        let checksum = bst.read_word();
-        let init_ip = bst.read_word();
-        let init_cs = bst.read_word();
-        let relocation_table_offset = bst.read_word();
-        let overlay = bst.read_word();
-
-        let mut oem_id = None;
-        let mut oem_info = None;
-        let mut new_header_start = None;
-        bst.pos += 8;//bst.check_reserved(8); // skip instead of throw, for linker compatibility reasons
-        oem_id = Some(bst.read_word());
-        oem_info = Some(bst.read_word());
-        bst.pos += 8;//bst.check_reserved(20);
-        new_header_start = Some(bst.read_dword());
+    /// <p>Where the NE, PE, LE or LX header is, if there is one: <code>new_header_start</code> when it points inside
+    /// the file, past the DOS header, at one of their signatures.  Anything else is a plain DOS program, whatever
+    /// the word at 3C holds.</p>
+    pub fn extension_offset(&self, bst: &ByteStream) -> Option<usize> {
+        let offset = self.new_header_start? as usize;
+        if offset < EXTENDED_HEADER_LEN {
+            return None;
+        }
+        match &bst.read_bytes_at(2, offset).ok()?[..] {
+            b"NE" | b"PE" | b"LE" | b"LX" => Some(offset),
+            _ => None,
+        }
+    }
 
-        let mut relocation_tables = Vec::new();
-        if bst.pos == relocation_table_offset as usize && relocation_table_entry_count > 0 {
-            for _ in 0..relocation_table_entry_count {
-                relocation_tables.push(RelocationTable::read(bst));
-            }
+    /// <p>Where the load module sits in a file of <code>file_len</code> bytes: from the end of the header to the
+    /// size given by the page counts, cut short if the file is.</p>
+    pub fn load_module_range(&self, file_len: usize) -> Range<usize> {
+        let start = (self.header_size as usize * 16).min(file_len);
+        let size = match self.page_count as usize {
+            0 => 0,
+            pages if self.last_page_bytes == 0 => pages * 512,
+            pages => (pages - 1) * 512 + self.last_page_bytes as usize,
+        };
+        start..size.clamp(start, file_len)
+    }
+    pub fn load_module<'a>(&self, bst: &'a ByteStream) -> &'a [u8] {
+        &bst.as_slice()[self.load_module_range(bst.as_slice().len())]
+    }
+
+    /// <p>The offset of <code>init_cs:init_ip</code> in the load module.</p>
+    pub fn entry_point(&self) -> usize {
+        (self.init_cs as usize * 16 + self.init_ip as usize) & 0xFFFFF
+    }
+
+    /// <p>Disassembles the load module by following control flow from the entry point.</p>
+    pub fn disassemble(&self, bst: &ByteStream) -> Disassembly {
+        trace_code(self.load_module(bst), &[self.entry_point()], true)
+    }
+    /// <p>Basic blocks and functions of the load module, traced from the entry point.</p>
+    pub fn control_flow_graph(&self, bst: &ByteStream) -> ControlFlowGraph {
+        ControlFlowGraph::build(&self.disassemble(bst), &[self.entry_point()], true)
+    }
+
+    /// <p>Loads the program the way DOS does: the load module goes right after the PSP, every relocation gets the
+    /// segment it went to added, the environment and PSP are written, and the registers are set up to start it
+    /// with CS:IP and SS:SP from the header relative to the load segment.  Fails if the program needs more memory
+    /// than there is below <code>MEMORY_TOP_SEGMENT</code>.</p>
+    pub fn load(&self, bst: &ByteStream, mem: &mut Memory, cpu: &mut Cpu, startup: &Startup) -> Result<(), Error> {
+        let image = self.load_module(bst);
+        let segment = Memory::program_segment(startup.psp_segment);
+        let needed = segment as usize + image.len().div_ceil(16) + self.min_alloc as usize;
+        if needed > MEMORY_TOP_SEGMENT as usize {
+            return Err(Error::InvalidHeader { field: "min_alloc" });
         }
 
-        if bst.pos < header_size as usize * 16 {
-            if !bst.check_reserved((header_size as usize * 16) - bst.pos) {
-                panic!();
-            }
+        mem.load(segment, image);
+        for relocation in &self.relocation_tables {
+            let at = segment.wrapping_add(relocation.segment);
+            let word = mem.read_word(at, relocation.offset);
+            mem.write_word(at, relocation.offset, word.wrapping_add(segment));
         }
+        startup.write(mem, MEMORY_TOP_SEGMENT);
 
-        new_header_start.unwrap(); // more like a "todo!();" call
+        startup.start(
+            cpu,
+            (segment.wrapping_add(self.init_cs), self.init_ip),
+            (segment.wrapping_add(self.init_ss), self.init_sp),
+        );
+        Ok(())
+    }
 
-        
+    pub fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        // "ZM" is accepted too, as DOS itself does
+        let magic = bst.read_word()?;
+        if &magic.to_le_bytes() != b"MZ" && &magic.to_le_bytes() != b"ZM" {
+            return Err(Error::BadMagic { offset: 0, found: magic });
+        }
+        let last_page_bytes = bst.read_word()?;
+        let page_count = bst.read_word()?;
+        let relocation_table_entry_count = bst.read_word()?;
+        let header_size = bst.read_word()?;
+        let min_alloc = bst.read_word()?;
+        let max_alloc = bst.read_word()?;
+        let init_ss = bst.read_word()?;
+        let init_sp = bst.read_word()?;
+        let checksum = bst.read_word()?;
+        let init_ip = bst.read_word()?;
+        let init_cs = bst.read_word()?;
+        let relocation_table_offset = bst.read_word()?;
+        let overlay = bst.read_word()?;
+
+        // Windows only looks for an extended header when the relocation table leaves room for one, and DOS programs
+        // often have their relocations or other data where it would be
+        let extended = relocation_table_offset as usize >= EXTENDED_HEADER_LEN
+            && header_size as usize * 16 >= EXTENDED_HEADER_LEN;
+        let (oem_id, oem_info, new_header_start) = if extended {
+            bst.pos += 8;//bst.check_reserved(8); // skip instead of throw, for linker compatibility reasons
+            let oem_id = Some(bst.read_word()?);
+            let oem_info = Some(bst.read_word()?);
+            bst.pos += 20;//bst.check_reserved(20);
+            (oem_id, oem_info, Some(bst.read_dword()?))
+        } else {
+            (None, None, None)
+        };
+        let header_end = bst.pos;
 
-        MZ {
+        let mut relocation_tables = Vec::new();
+        if relocation_table_entry_count > 0 {
+            bst.pos = relocation_table_offset as usize;
+            for _ in 0..relocation_table_entry_count {
+                relocation_tables.push(RelocationTable::read(bst)?);
+            }
+            bst.pos = bst.pos.max(header_end);
+        }
+
+        let mz = MZ {
             last_page_bytes,
             page_count,
             relocation_table_entry_count,
@@ -89,7 +179,18 @@ impl MZ {
             new_header_start,
             relocation_tables,
             header_code: Vec::new(),
+        };
+
+        // only checked in front of an NE, PE, LE or LX header, since DOS packers and linkers keep their own data in
+        // the padding, and plenty of DOS programs have relocations at 40 without any extended header
+        let header_len = header_size as usize * 16;
+        if mz.extension_offset(bst).is_some()
+            && bst.pos < header_len
+            && !bst.check_reserved(header_len - bst.pos)?
+        {
+            return Err(Error::InvalidHeader { field: "header padding" });
         }
+        Ok(mz)
     }
 }
 
@@ -99,9 +200,101 @@ pub struct RelocationTable {
 }
 
 impl RelocationTable {
-    pub fn read(bst: &mut ByteStream) -> Self {
-        let offset = bst.read_word();
-        let segment = bst.read_word();
-        Self { offset, segment }
+    pub fn read(bst: &mut ByteStream) -> Result<Self, Error> {
+        let offset = bst.read_word()?;
+        let segment = bst.read_word()?;
+        Ok(Self { offset, segment })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// <p>An MZ header of <code>header_size</code> paragraphs with its relocation table at <code>relocations</code>,
+    /// followed by a 16-byte load module.</p>
+    fn header(header_size: u16, relocations: u16, new_header_start: u32) -> Vec<u8> {
+        let mut file = vec![0; header_size as usize * 16 + 16];
+        let len = file.len() as u16;
+        for (at, word) in [(0, 0x5A4D), (2, len), (4, 1), (8, header_size), (0x18, relocations)] {
+            file[at..at + 2].copy_from_slice(&u16::to_le_bytes(word));
+        }
+        file[0x3C..0x40].copy_from_slice(&new_header_start.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn padding_is_ignored_without_an_extension() {
+        let mut file = header(8, 0x40, 0);
+        file[0x1C..0x3C].fill(0xAA);
+        file[0x60..0x80].fill(0xBB);
+        let mz = MZ::read(&mut ByteStream::new(file.clone())).unwrap();
+        assert_eq!(mz.extension_offset(&ByteStream::new(file)), None);
+    }
+
+    #[test]
+    fn padding_is_checked_in_front_of_an_extension() {
+        let mut file = header(8, 0x40, 0x80);
+        file[0x80..0x82].copy_from_slice(b"NE");
+        assert!(MZ::read(&mut ByteStream::new(file.clone())).is_ok());
+        file[0x60] = 0xBB;
+        assert!(matches!(MZ::read(&mut ByteStream::new(file)), Err(Error::InvalidHeader { field: "header padding" })));
+    }
+
+    /// <p>A three-paragraph header with relocations at 0000:0001 and 0012:0002 and its entry point at 0001:0000, in
+    /// front of a load module starting with <code>mov ax,seg</code> and with a far pointer to 0003:1234 at
+    /// 0012:0000.</p>
+    fn program(min_alloc: u16) -> Vec<u8> {
+        let mut file = vec![0; 0x30 + 0x130];
+        let len = file.len() as u16;
+        for (at, word) in [
+            (0x00, 0x5A4D),
+            (0x02, len),
+            (0x04, 1),
+            (0x06, 2),
+            (0x08, 3),
+            (0x0A, min_alloc),
+            (0x0E, 0x0010),
+            (0x10, 0x0200),
+            (0x16, 0x0001),
+            (0x18, 0x001C),
+            (0x1C, 0x0001),
+            (0x20, 0x0002),
+            (0x22, 0x0012),
+        ] {
+            file[at..at + 2].copy_from_slice(&u16::to_le_bytes(word));
+        }
+        file[0x30..0x33].copy_from_slice(&[0xB8, 0x00, 0x00]);
+        file[0x30 + 0x120..0x30 + 0x124].copy_from_slice(&[0x34, 0x12, 0x03, 0x00]);
+        file
+    }
+
+    #[test]
+    fn load_relocates_and_starts_the_program() {
+        let mut bst = ByteStream::new(program(0));
+        let mz = MZ::read(&mut bst).unwrap();
+        assert_eq!(mz.relocation_tables.len(), 2);
+
+        let (mut memory, mut cpu) = (Memory::new(), Cpu::new());
+        let startup = Startup::new("C:\\PROGRAM.EXE");
+        mz.load(&bst, &mut memory, &mut cpu, &startup).unwrap();
+
+        let segment = Memory::program_segment(startup.psp_segment);
+        assert_eq!(memory.read_bytes(segment, 0, 3), [0xB8, segment as u8, (segment >> 8) as u8]);
+        assert_eq!(memory.read_word(segment + 0x12, 0), 0x1234);
+        assert_eq!(memory.read_word(segment + 0x12, 2), segment + 3);
+
+        assert_eq!((cpu.cs, cpu.ip), (segment + 1, 0));
+        assert_eq!((cpu.ss, cpu.sp), (segment + 0x10, 0x200));
+        assert_eq!((cpu.ds, cpu.es), (startup.psp_segment, startup.psp_segment));
+        assert_eq!(memory.read_bytes(startup.psp_segment, 0, 2), [0xCD, 0x20]);
+    }
+
+    #[test]
+    fn load_fails_without_enough_memory() {
+        let mut bst = ByteStream::new(program(0xA000));
+        let mz = MZ::read(&mut bst).unwrap();
+        let result = mz.load(&bst, &mut Memory::new(), &mut Cpu::new(), &Startup::new("C:\\PROGRAM.EXE"));
+        assert!(matches!(result, Err(Error::InvalidHeader { field: "min_alloc" })));
     }
 }